@@ -1,4 +1,6 @@
 mod equal_words;
+pub mod prover;
 pub mod state_update;
-mod utils;
+pub mod utils;
+pub mod verifier;
 pub mod witness;
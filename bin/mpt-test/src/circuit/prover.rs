@@ -0,0 +1,568 @@
+//! Generate real proofs of the StateUpdate circuit.
+//!
+//! The KZG setup produces a single `ParamsKZG` (the "general params") that holds everything the
+//! prover needs. The verifier only needs the part of it returned by
+//! `general_params.verifier_params()`, so a proof created with the general params must always
+//! verify with the verifier params derived from them. [`assert_verifier_params_roundtrip`]
+//! checks exactly this.
+//!
+//! [`StateUpdateCircuit::prove_verify_and_assert`] is the recommended way to create production
+//! proofs: it checks the witness before proving, and checks that the proof verifies with the
+//! verifier params derived from the general params before returning it.
+
+use eyre::{Result, WrapErr};
+use halo2_proofs::{
+    halo2curves::bn256::{Bn256, Fr, G1Affine},
+    plonk::{create_proof, keygen_pk, keygen_vk, Circuit, ProvingKey, VerifyingKey},
+    poly::{
+        commitment::{Params, ParamsProver},
+        kzg::{
+            commitment::{KZGCommitmentScheme, ParamsKZG},
+            multiopen::ProverSHPLONK,
+        },
+    },
+    transcript::{Blake2bWrite, Challenge255, TranscriptWriterBuffer},
+};
+use rand_chacha::{rand_core::SeedableRng, ChaChaRng};
+use std::time::Instant;
+
+use super::{
+    state_update::{StateUpdateCircuit, StateUpdateCircuitParams},
+    utils::PublicInputs,
+    verifier::{verify, FullVerifierKey},
+    witness::Witness,
+};
+
+/// A proof of the StateUpdate circuit, with everything needed to verify it.
+pub struct ProofBundle {
+    pub fvk: FullVerifierKey,
+    pub proof: Vec<u8>,
+    pub public_inputs: Vec<Fr>,
+}
+
+impl ProofBundle {
+    /// Verify the proof against its public inputs.
+    pub fn verify(&self) -> Result<()> {
+        verify(&self.fvk, &self.proof, &self.public_inputs)
+    }
+}
+
+/// Proves several circuits with the same setup and proving key, so that proving a sequence of
+/// state transitions runs the setup and the keygen only once. The circuits must have the degree
+/// of the setup and the params of the circuit the proving key was generated for.
+pub struct BatchProver {
+    general_params: ParamsKZG<Bn256>,
+    pk: ProvingKey<G1Affine>,
+    /// Parameters of the circuit the proving key was generated for
+    circuit_params: StateUpdateCircuitParams,
+}
+
+impl BatchProver {
+    pub fn new(
+        general_params: ParamsKZG<Bn256>,
+        pk: ProvingKey<G1Affine>,
+        circuit_params: StateUpdateCircuitParams,
+    ) -> Self {
+        Self {
+            general_params,
+            pk,
+            circuit_params,
+        }
+    }
+
+    /// Create a proof of `circuit`, returns the proof and the public inputs it was created for.
+    pub fn prove_one(&self, circuit: StateUpdateCircuit<Fr>) -> Result<(Vec<u8>, Vec<Fr>)> {
+        eyre::ensure!(
+            circuit.degree as u32 == self.general_params.k(),
+            "the circuit has degree {} but the params are for degree {}",
+            circuit.degree,
+            self.general_params.k()
+        );
+        eyre::ensure!(
+            circuit.params() == self.circuit_params,
+            "the circuit has params {:?} but the proving key is for {:?}",
+            circuit.params(),
+            self.circuit_params
+        );
+        circuit.prove(&self.general_params, &self.pk)
+    }
+}
+
+/// Generate the KZG setup for a circuit of `2^degree` rows.
+pub fn setup_params(degree: u32) -> ParamsKZG<Bn256> {
+    let rng = ChaChaRng::seed_from_u64(42);
+    ParamsKZG::<Bn256>::setup(degree, rng)
+}
+
+/// Assert that a proof created with `general_params` verifies with the verifier params derived
+/// from them. Returns the key the proof was verified with.
+pub fn assert_verifier_params_roundtrip(
+    general_params: &ParamsKZG<Bn256>,
+    vk: &VerifyingKey<G1Affine>,
+    circuit_params: StateUpdateCircuitParams,
+    proof: &[u8],
+    public_inputs: &[Fr],
+) -> FullVerifierKey {
+    let fvk = FullVerifierKey {
+        verifier_params: general_params.verifier_params().clone(),
+        vk: vk.clone(),
+        circuit_params,
+    };
+    verify(&fvk, proof, public_inputs)
+        .expect("proof created with the general params should verify with the verifier params");
+    fvk
+}
+
+impl StateUpdateCircuit<Fr> {
+    /// Generate the proving key of the circuit.
+    pub fn keygen(&self, general_params: &ParamsKZG<Bn256>) -> Result<ProvingKey<G1Affine>> {
+        let vk = keygen_vk(general_params, self).wrap_err("keygen_vk failed")?;
+        let pk = keygen_pk(general_params, vk, self).wrap_err("keygen_pk failed")?;
+        Ok(pk)
+    }
+
+    /// The public inputs of the proof. A zero old root is not the hash of any trie, it means the
+    /// witness was never initialized, so it is rejected before proving.
+    fn public_inputs(&self) -> Result<Vec<Fr>> {
+        let public_inputs = PublicInputs::new(&self.lc_witness, &self.extra_public_inputs).0;
+        eyre::ensure!(
+            public_inputs[..2].iter().any(|limb| *limb != Fr::from(0)),
+            "the old root of the witness is zero"
+        );
+        Ok(public_inputs)
+    }
+
+    /// Create a proof using an existing setup and proving key, returns the proof and the public
+    /// inputs it was created for.
+    pub fn prove(
+        self,
+        general_params: &ParamsKZG<Bn256>,
+        pk: &ProvingKey<G1Affine>,
+    ) -> Result<(Vec<u8>, Vec<Fr>)> {
+        let public_inputs = self.public_inputs()?;
+
+        let rng = ChaChaRng::seed_from_u64(42);
+        let mut transcript = Blake2bWrite::<_, G1Affine, Challenge255<_>>::init(vec![]);
+
+        create_proof::<
+            KZGCommitmentScheme<Bn256>,
+            ProverSHPLONK<'_, Bn256>,
+            Challenge255<G1Affine>,
+            ChaChaRng,
+            Blake2bWrite<Vec<u8>, G1Affine, Challenge255<G1Affine>>,
+            StateUpdateCircuit<Fr>,
+        >(
+            general_params,
+            pk,
+            &[self],
+            &[&[&public_inputs]],
+            rng,
+            &mut transcript,
+        )?;
+
+        Ok((transcript.finalize(), public_inputs))
+    }
+
+    /// Like [`Self::prove`], but for the state at block `block_number`, which is added to the
+    /// public inputs as the only extra public input. Verify with
+    /// [`super::verifier::verify_at_block`].
+    pub fn prove_at_block(
+        mut self,
+        block_number: u64,
+        general_params: &ParamsKZG<Bn256>,
+        pk: &ProvingKey<G1Affine>,
+    ) -> Result<(Vec<u8>, Vec<Fr>)> {
+        self.extra_public_inputs = vec![Fr::from(block_number)];
+        self.prove(general_params, pk)
+    }
+
+    /// Generate the setup, the proving key and a proof. Returns the proving key, the key to
+    /// verify the proof, the proof and its public inputs.
+    pub fn gen_pk_and_prove(
+        self,
+    ) -> Result<(ProvingKey<G1Affine>, FullVerifierKey, Vec<u8>, Vec<Fr>)> {
+        let general_params = setup_params(self.degree as u32);
+        let pk = self.keygen(&general_params)?;
+        let circuit_params = self.params();
+        let (proof, public_inputs) = self.prove(&general_params, &pk)?;
+
+        let fvk = FullVerifierKey {
+            verifier_params: general_params.verifier_params().clone(),
+            vk: pk.get_vk().clone(),
+            circuit_params,
+        };
+
+        Ok((pk, fvk, proof, public_inputs))
+    }
+
+    /// Check the witness, prove and verify the proof, returning it only if every stage passes.
+    /// The stages go from the cheapest to the most expensive, so that an invalid witness fails
+    /// as early as possible:
+    /// - the public inputs are the values proven by the MPT proofs,
+    /// - the witness satisfies the circuit (MockProver),
+    /// - the proof created by the real prover verifies.
+    pub fn prove_verify_and_assert(self) -> Result<ProofBundle> {
+        let witness = Witness {
+            lc_witness: self.lc_witness.clone(),
+            mpt_witness: self.mpt_circuit.nodes.clone(),
+        };
+        eyre::ensure!(
+            witness.verify_public_inputs(),
+            "the public inputs do not match the MPT proofs"
+        );
+
+        self.check_satisfied()?;
+
+        self.assert_real_prover()
+    }
+
+    /// Prove the circuit with the real prover and check that the proof verifies, see
+    /// [`assert_verifier_params_roundtrip`].
+    pub fn assert_real_prover(self) -> Result<ProofBundle> {
+        let general_params = setup_params(self.degree as u32);
+        let pk = self.keygen(&general_params)?;
+        let circuit_params = self.params();
+        let (proof, public_inputs) = self.prove(&general_params, &pk)?;
+
+        let fvk = assert_verifier_params_roundtrip(
+            &general_params,
+            pk.get_vk(),
+            circuit_params,
+            &proof,
+            &public_inputs,
+        );
+
+        Ok(ProofBundle {
+            fvk,
+            proof,
+            public_inputs,
+        })
+    }
+
+    /// Time the MockProver and the real prover (`gen_pk_and_prove`) on the same witness and
+    /// print both durations along with the number of MPT rows.
+    pub fn bench_provers(self) -> Result<()> {
+        let num_rows = self.num_rows();
+        let degree = self.degree;
+
+        let start = Instant::now();
+        self.assert_satisfied();
+        let mock_time = start.elapsed();
+
+        let start = Instant::now();
+        self.gen_pk_and_prove()?;
+        let real_time = start.elapsed();
+
+        println!(
+            "rows: {} (degree {}), MockProver: {:?}, real prover: {:?}",
+            num_rows, degree, mock_time, real_time
+        );
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::{
+        state_update::keccak_rows_for_degree,
+        utils::InstanceBuilder,
+        verifier::verify_at_block,
+        witness::{FieldTrieModification, FieldTrieModifications},
+    };
+    use eth_types::{Address, ToScalar, H256, U256};
+    use std::str::FromStr;
+    use zkevm_circuits::{
+        mpt_circuit::load_proof_from_file, table::mpt_table::MPTProofType, util::word::WordLoHi,
+    };
+
+    /// The witness of the balance change of `BalanceModCShort.json`, with the public values
+    /// modified by `tamper`.
+    fn balance_change_witness(tamper: impl FnOnce(&mut FieldTrieModification<Fr>)) -> Witness<Fr> {
+        let mpt_witness = load_proof_from_file(
+            "../../zkevm-circuits/src/mpt_circuit/tests/BalanceModCShort.json",
+        );
+        let root = |hex: &str| WordLoHi::<Fr>::from(H256::from_str(hex).unwrap());
+        let mut lc = FieldTrieModification::<Fr> {
+            typ: Fr::from(MPTProofType::BalanceChanged as u64),
+            address: Address::from_str("0x68d5a6e78bd8734b7d190cbd98549b72bfa0800b")
+                .unwrap()
+                .to_scalar()
+                .unwrap(),
+            value: WordLoHi::<Fr>::from(U256::from(0x62)),
+            old_root: root("0x224124c6237ebee590056b9bcd2462b571d68d62c3348dc1f20449c05368649b"),
+            new_root: root("0xd777e3e92548110f8eb72385fbb351fe7f6bf6ae4a322b3859ad91dbafa7dbc5"),
+            ..Default::default()
+        };
+        tamper(&mut lc);
+
+        Witness {
+            lc_witness: FieldTrieModifications(vec![lc]),
+            mpt_witness,
+        }
+    }
+
+    /// The circuit of `degree` proving `witness`, with the keccak circuit sized for `degree`, or
+    /// for degree 15 when the minimal degree is selected.
+    fn new_circuit(
+        witness: Witness<Fr>,
+        degree: usize,
+        disable_storage: bool,
+    ) -> Result<StateUpdateCircuit<Fr>> {
+        let keccak_degree = if degree == 0 { 15 } else { degree };
+        StateUpdateCircuit::new(
+            witness,
+            degree,
+            520,
+            10,
+            disable_storage,
+            keccak_rows_for_degree(keccak_degree),
+        )
+    }
+
+    /// The circuit proving the witness of [`balance_change_witness`].
+    fn balance_change_circuit(
+        tamper: impl FnOnce(&mut FieldTrieModification<Fr>),
+    ) -> StateUpdateCircuit<Fr> {
+        new_circuit(balance_change_witness(tamper), 15, true).unwrap()
+    }
+
+    #[test]
+    #[ignore = "Due to the real prover running time"]
+    fn prove_verify_and_assert() {
+        let bundle = balance_change_circuit(|_| {})
+            .prove_verify_and_assert()
+            .unwrap();
+        assert!(bundle.verify().is_ok());
+    }
+
+    #[test]
+    #[ignore = "Due to the real prover running time"]
+    fn full_verifier_key_roundtrip() {
+        let (_, fvk, proof, public_inputs) =
+            balance_change_circuit(|_| {}).gen_pk_and_prove().unwrap();
+        let bytes = fvk.to_bytes().unwrap();
+
+        let fvk = FullVerifierKey::from_bytes(&bytes).unwrap();
+        assert_eq!(fvk.to_bytes().unwrap(), bytes);
+        verify(&fvk, &proof, &public_inputs).unwrap();
+
+        // Truncated or extended keys are rejected
+        assert!(FullVerifierKey::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(FullVerifierKey::from_bytes(&[&bytes[..], &[0]].concat()).is_err());
+    }
+
+    #[test]
+    #[ignore = "Due to the key generation running time"]
+    fn full_verifier_key_fingerprint() {
+        let fvk = |circuit: StateUpdateCircuit<Fr>| {
+            let general_params = setup_params(circuit.degree as u32);
+            let pk = circuit.keygen(&general_params).unwrap();
+            FullVerifierKey {
+                verifier_params: general_params.verifier_params().clone(),
+                vk: pk.get_vk().clone(),
+                circuit_params: circuit.params(),
+            }
+        };
+
+        // The key does not depend on the witness
+        let fingerprint = fvk(balance_change_circuit(|_| {})).fingerprint();
+        assert_eq!(
+            fvk(balance_change_circuit(|_| {})).fingerprint(),
+            fingerprint
+        );
+        assert_eq!(
+            fvk(balance_change_circuit(
+                |lc| lc.value = WordLoHi::from(U256::from(0x63))
+            ))
+            .fingerprint(),
+            fingerprint
+        );
+
+        // A circuit of another degree has another key
+        let circuit = new_circuit(balance_change_witness(|_| {}), 16, true).unwrap();
+        assert_ne!(fvk(circuit).fingerprint(), fingerprint);
+
+        // Nor does a circuit with the storage constraints
+        let circuit = new_circuit(balance_change_witness(|_| {}), 15, false).unwrap();
+        assert_ne!(fvk(circuit).fingerprint(), fingerprint);
+    }
+
+    #[test]
+    #[ignore = "Due to the real prover running time"]
+    fn batch_prover_reuses_key() {
+        // The balance change of `BalanceModCLong.json`, same account and old root as
+        // `BalanceModCShort.json` but a different new balance
+        let mut witness = balance_change_witness(|lc| {
+            lc.value = WordLoHi::<Fr>::from(U256::from(0x01b7));
+            lc.new_root = WordLoHi::<Fr>::from(
+                H256::from_str(
+                    "0xccb4e94fd1ee18e77580dfa23ea9013b2c6b06a0366eaea1cabb82d2179f3b35",
+                )
+                .unwrap(),
+            );
+        });
+        witness.mpt_witness =
+            load_proof_from_file("../../zkevm-circuits/src/mpt_circuit/tests/BalanceModCLong.json");
+        let circuits = [
+            balance_change_circuit(|_| {}),
+            new_circuit(witness, 15, true).unwrap(),
+        ];
+
+        let general_params = setup_params(15);
+        let pk = circuits[0].keygen(&general_params).unwrap();
+        let fvk = FullVerifierKey {
+            verifier_params: general_params.verifier_params().clone(),
+            vk: pk.get_vk().clone(),
+            circuit_params: circuits[0].params(),
+        };
+        let prover = BatchProver::new(general_params, pk, circuits[0].params());
+
+        let mut proven_inputs = vec![];
+        for circuit in circuits {
+            let (proof, public_inputs) = prover.prove_one(circuit).unwrap();
+            verify(&fvk, &proof, &public_inputs).unwrap();
+            proven_inputs.push(public_inputs);
+        }
+        assert_ne!(proven_inputs[0], proven_inputs[1]);
+
+        // A circuit of another degree cannot use the key
+        let circuit = new_circuit(balance_change_witness(|_| {}), 16, true).unwrap();
+        let err = prover.prove_one(circuit).err().unwrap();
+        assert!(err.to_string().contains("degree"), "{err}");
+
+        // Nor a circuit with the storage constraints
+        let circuit = new_circuit(balance_change_witness(|_| {}), 15, false).unwrap();
+        let err = prover.prove_one(circuit).err().unwrap();
+        assert!(err.to_string().contains("params"), "{err}");
+    }
+
+    #[test]
+    #[ignore = "Due to the real prover running time"]
+    fn prove_at_block() {
+        let circuit = balance_change_circuit(|_| {});
+        let general_params = setup_params(circuit.degree as u32);
+        let pk = circuit.keygen(&general_params).unwrap();
+        let fvk = FullVerifierKey {
+            verifier_params: general_params.verifier_params().clone(),
+            vk: pk.get_vk().clone(),
+            circuit_params: circuit.params(),
+        };
+
+        let (proof, public_inputs) = circuit.prove_at_block(42, &general_params, &pk).unwrap();
+        verify_at_block(&fvk, &proof, &public_inputs, 42).unwrap();
+        assert!(verify_at_block(&fvk, &proof, &public_inputs, 43).is_err());
+
+        // The proof does not verify with another block number in the public inputs
+        let mut other_block = public_inputs;
+        other_block[InstanceBuilder::<Fr>::EXTRA_OFFSET] = Fr::from(43);
+        assert!(verify_at_block(&fvk, &proof, &other_block, 43).is_err());
+    }
+
+    #[test]
+    fn prove_verify_and_assert_fails_early() {
+        // The new root is not the one of the MPT proof
+        let err = balance_change_circuit(|lc| lc.new_root = lc.old_root)
+            .prove_verify_and_assert()
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("public inputs"), "{err}");
+
+        // The value is not part of the public inputs check, the circuit rejects it
+        let err = balance_change_circuit(|lc| lc.value = WordLoHi::from(U256::from(0x63)))
+            .prove_verify_and_assert()
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("does not satisfy"), "{err}");
+    }
+
+    #[test]
+    fn gen_pk_and_prove_degree_too_small() {
+        // The circuit does not fit in 2^6 rows, the keygen must fail without panicking. `new`
+        // rejects such a degree, so it is set after the circuit is built.
+        let mut circuit = balance_change_circuit(|_| {});
+        circuit.degree = 6;
+        circuit.mpt_circuit.degree = 6;
+        let general_params = setup_params(6);
+        let err = circuit.keygen(&general_params).err().unwrap();
+        assert!(err.to_string().contains("keygen"), "{err}");
+
+        let err = circuit.gen_pk_and_prove().err().unwrap();
+        assert!(err.to_string().contains("keygen"), "{err}");
+    }
+
+    #[test]
+    fn min_degree_is_selected() {
+        let circuit = new_circuit(balance_change_witness(|_| {}), 0, true).unwrap();
+        assert_eq!(circuit.degree, circuit.min_degree() as usize);
+        assert_eq!(circuit.mpt_circuit.degree, circuit.degree);
+        circuit.assert_satisfied();
+
+        // The witness does not fit in fewer rows
+        let result = new_circuit(balance_change_witness(|_| {}), circuit.degree - 1, true);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    #[ignore = "Due to the real prover running time"]
+    fn min_degree_proves() {
+        let circuit = new_circuit(balance_change_witness(|_| {}), 0, true).unwrap();
+        let bundle = circuit.prove_verify_and_assert().unwrap();
+        assert!(bundle.verify().is_ok());
+    }
+
+    #[test]
+    fn disable_storage_rejects_storage_proofs() {
+        let witness = Witness {
+            lc_witness: FieldTrieModifications(vec![]),
+            mpt_witness: load_proof_from_file(
+                "../../zkevm-circuits/src/mpt_circuit/tests/FromNilToValue.json",
+            ),
+        };
+        let err = new_circuit(witness, 15, true).err().unwrap();
+        assert!(err.to_string().contains("storage"), "{err}");
+    }
+
+    #[test]
+    #[cfg(not(feature = "disable-keccak"))]
+    fn keccak_circuit_has_the_declared_rows() {
+        use zkevm_circuits::keccak_circuit::KeccakCircuit;
+
+        let keccak_data: Vec<_> = balance_change_witness(|_| {})
+            .mpt_witness
+            .iter()
+            .flat_map(|node| node.keccak_data.iter().map(|k| k.to_vec()))
+            .collect();
+        let min_rows = KeccakCircuit::<Fr>::min_num_rows(&keccak_data);
+        let new = |max_keccak_rows| {
+            StateUpdateCircuit::new(
+                balance_change_witness(|_| {}),
+                15,
+                520,
+                10,
+                true,
+                max_keccak_rows,
+            )
+        };
+
+        // The circuit params do not depend on the keccak data
+        let circuit = new(keccak_rows_for_degree(15)).unwrap();
+        assert_eq!(circuit.params().max_keccak_rows, keccak_rows_for_degree(15));
+        assert!(new(min_rows).is_ok());
+
+        let err = new(min_rows - 1).err().unwrap();
+        assert!(err.to_string().contains("keccak"), "{err}");
+    }
+
+    #[test]
+    fn public_inputs_reject_zero_old_root() {
+        assert!(balance_change_circuit(|_| {}).public_inputs().is_ok());
+
+        let err = balance_change_circuit(|lc| lc.old_root = WordLoHi::from(H256::zero()))
+            .public_inputs()
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("old root"), "{err}");
+    }
+}
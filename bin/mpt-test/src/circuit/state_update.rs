@@ -20,21 +20,42 @@ use halo2_proofs::{
 };
 
 use zkevm_circuits::{
-    mpt_circuit::{MPTCircuit, MPTCircuitParams, MPTConfig},
+    keccak_circuit::KeccakCircuit,
+    mpt_circuit::{MPTCircuit, MPTCircuitParams, MPTConfig, MPTConfigArgs},
     table::{KeccakTable, MptTable},
-    util::{word::WordLoHi, Challenges},
+    util::{log2_ceil, word::WordLoHi, Challenges, SubCircuit, SubCircuitConfig},
 };
 
 use crate::circuit::{
     equal_words::EqualWordsConfig,
+    utils::InstanceBuilder,
     witness::{FieldTrieModification, FieldTrieModifications, Witness},
 };
 
 #[cfg(not(feature = "disable-keccak"))]
-use zkevm_circuits::{
-    keccak_circuit::{KeccakCircuit, KeccakCircuitConfig, KeccakCircuitConfigArgs},
-    util::{SubCircuit, SubCircuitConfig},
-};
+use zkevm_circuits::keccak_circuit::{KeccakCircuitConfig, KeccakCircuitConfigArgs};
+
+/// Minimum degree of a keccak circuit of `max_keccak_rows` rows. The keccak circuit is sized to
+/// the declared maximum, not to the data actually hashed, so that its shape does not depend on
+/// the witness.
+#[cfg_attr(feature = "disable-keccak", allow(dead_code))]
+pub fn keccak_min_degree(max_keccak_rows: usize) -> usize {
+    log2_ceil(max_keccak_rows + KeccakCircuit::<Fr>::unusable_rows()) as usize
+}
+
+/// The most rows a keccak circuit can have in a circuit of `2^degree` rows.
+pub fn keccak_rows_for_degree(degree: usize) -> usize {
+    (1 << degree) - KeccakCircuit::<Fr>::unusable_rows()
+}
+
+/// Parameters of the StateUpdate circuit, they fix its shape and so its verifying key
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct StateUpdateCircuitParams {
+    /// Parameters of the MPT circuit
+    pub mpt: MPTCircuitParams,
+    /// Rows of the keccak circuit, the maximal number of rows the keccak data can use
+    pub max_keccak_rows: usize,
+}
 
 // negated A=>B  eq ~(A & ~B) (it is not the case that A is true and B is false)
 pub fn xnif<F: Field>(a: Expression<F>, b: Expression<F>) -> Expression<F> {
@@ -60,6 +81,9 @@ pub struct StateUpdateCircuitConfig<F: Field> {
     /// Intance column, used to check public inputs
     pub instance: Column<Instance>,
 
+    /// Extra public inputs (e.g. the block number)
+    pub pi_extra: Column<Advice>,
+
     /// ONE if the first row, ZERO otherwise
     pub is_first: Column<Fixed>,
 
@@ -91,6 +115,7 @@ pub struct StateUpdateCircuitConfig<F: Field> {
 ///     new_root_lo
 ///     new_root_hi
 ///     number of MPT changes
+///     extra public inputs (e.g. the block number)
 ///     for each change
 ///     | change_type
 ///     | address
@@ -105,30 +130,37 @@ pub struct StateUpdateCircuit<F: Field> {
     pub mpt_circuit: MPTCircuit<F>,
 
     pub lc_witness: FieldTrieModifications<F>,
+    pub extra_public_inputs: Vec<F>,
     pub degree: usize,
     pub max_proof_count: usize,
+    pub max_keccak_rows: usize,
 }
 
 impl<F: Field> Circuit<F> for StateUpdateCircuit<F> {
     type Config = (StateUpdateCircuitConfig<F>, Challenges);
     type FloorPlanner = SimpleFloorPlanner;
-    type Params = MPTCircuitParams;
+    type Params = StateUpdateCircuitParams;
 
     fn without_witnesses(&self) -> Self {
         Self::default()
     }
 
     fn params(&self) -> Self::Params {
-        MPTCircuitParams {
-            degree: self.mpt_circuit.degree,
-            disable_preimage_check: self.mpt_circuit.disable_preimage_check,
-            max_nodes: self.mpt_circuit.max_nodes,
+        StateUpdateCircuitParams {
+            mpt: MPTCircuitParams {
+                degree: self.mpt_circuit.degree,
+                disable_preimage_check: self.mpt_circuit.disable_preimage_check,
+                max_nodes: self.mpt_circuit.max_nodes,
+                disable_storage: self.mpt_circuit.disable_storage,
+                randomness: self.mpt_circuit.randomness,
+            },
+            max_keccak_rows: self.max_keccak_rows,
         }
     }
 
     fn configure_with_params(meta: &mut ConstraintSystem<F>, params: Self::Params) -> Self::Config {
         let challenges = Challenges::construct(meta);
-        let challenges_expr = challenges.exprs(meta);
+        let challenges_expr = params.mpt.randomness.exprs(meta, &challenges);
 
         let keccak_table = KeccakTable::construct(meta);
 
@@ -140,12 +172,20 @@ impl<F: Field> Circuit<F> for StateUpdateCircuit<F> {
                 challenges: challenges_expr.clone(),
             },
         );
-        let mpt_config = MPTConfig::new(meta, challenges_expr, keccak_table, params);
+        let mpt_config = MPTConfig::new(
+            meta,
+            MPTConfigArgs {
+                keccak_table,
+                challenges: challenges_expr,
+                params: params.mpt,
+            },
+        );
 
         let is_first = meta.fixed_column();
         let count = meta.advice_column();
         let q_enable = meta.complex_selector();
         let instance = meta.instance_column();
+        let pi_extra = meta.advice_column();
         let pi_mpt = MptTable {
             address: meta.advice_column(),
             storage_key: WordLoHi::new([meta.advice_column(), meta.advice_column()]),
@@ -177,6 +217,7 @@ impl<F: Field> Circuit<F> for StateUpdateCircuit<F> {
 
         meta.enable_equality(instance);
         meta.enable_equality(count);
+        meta.enable_equality(pi_extra);
 
         let is_padding_inv = meta.advice_column();
         let is_padding = IsZeroChip::configure(
@@ -315,6 +356,7 @@ impl<F: Field> Circuit<F> for StateUpdateCircuit<F> {
             root_chained,
             q_enable,
             instance,
+            pi_extra,
             pi_mpt,
         };
 
@@ -330,7 +372,10 @@ impl<F: Field> Circuit<F> for StateUpdateCircuit<F> {
         (config, _challenges): Self::Config,
         mut layouter: impl Layouter<F>,
     ) -> Result<(), Error> {
-        let challenges = _challenges.values(&mut layouter);
+        let challenges = self
+            .mpt_circuit
+            .randomness
+            .values(&mut layouter, &_challenges);
 
         // MPT witness
 
@@ -380,10 +425,14 @@ impl<F: Field> Circuit<F> for StateUpdateCircuit<F> {
                     config.count_decrement.value_inv,
                 );
                 region.name_column(|| "LC_instance", config.instance);
+                region.name_column(|| "LC_pi_extra", config.pi_extra);
 
                 region.assign_fixed(|| "", config.is_first, 0, || Value::known(F::ONE))?;
 
-                let mut pi = Vec::new();
+                let mut pi_old_root = None;
+                let mut pi_new_root = None;
+                let mut pi_count = None;
+                let mut pi_changes = Vec::new();
 
                 for offset in 0..self.max_proof_count {
 
@@ -468,30 +517,42 @@ impl<F: Field> Circuit<F> for StateUpdateCircuit<F> {
                     // at beggining, set the old root and number of proofs
 
                     if offset == 0 {
-                        pi.push(Some(old_root_lo));
-                        pi.push(Some(old_root_hi));
-                        pi.push(None);
-                        pi.push(None);
-                        pi.push(Some(count_cell));
+                        pi_old_root = Some([old_root_lo, old_root_hi]);
+                        pi_count = Some(count_cell);
                     }
 
-                    pi.append(vec![Some(typ), Some(addr), Some(value_lo), Some(value_hi), Some(key_lo), Some(key_hi)].as_mut());
+                    pi_changes.push([typ, addr, value_lo, value_hi, key_lo, key_hi]);
 
                     // at ending, set the last root in the last row (valid since we are propagating it)
 
                     if offset == self.max_proof_count -1 {
-                        pi[2] = Some(new_root_lo);
-                        pi[3] = Some(new_root_hi);
+                        pi_new_root = Some([new_root_lo, new_root_hi]);
                     }
 
                 }
-                Ok(pi)
+
+                let mut pi = InstanceBuilder::new(pi_old_root.unwrap(), pi_new_root.unwrap(), pi_count.unwrap());
+
+                for (offset, value) in self.extra_public_inputs.iter().enumerate() {
+                    pi.push_extra(region.assign_advice(
+                        || "",
+                        config.pi_extra,
+                        offset,
+                        || Value::known(*value),
+                    )?);
+                }
+
+                for change in pi_changes {
+                    pi.push_change(change);
+                }
+
+                Ok(pi.build())
             },
         )?;
 
         // check that state updates to lookup are the same that the specified in the public inputs
-        for (n, value) in pi.into_iter().enumerate() {
-            layouter.constrain_instance(value.unwrap().cell(), config.instance, n)?;
+        for (n, cell) in pi.into_iter().enumerate() {
+            layouter.constrain_instance(cell.cell(), config.instance, n)?;
         }
 
         Ok(())
@@ -499,49 +560,104 @@ impl<F: Field> Circuit<F> for StateUpdateCircuit<F> {
 }
 
 impl StateUpdateCircuit<Fr> {
+    /// Create the circuit proving `witness`. A `degree` of 0 selects the minimal degree for the
+    /// witness, see [`Self::min_degree`], a smaller nonzero degree is rejected.
+    /// `disable_storage` omits the storage leaf constraints, it is part of the circuit shape so
+    /// it is given by the caller and a witness with storage proofs is rejected when it is set.
+    /// Likewise the keccak circuit has `max_keccak_rows` rows, a witness with more keccak data
+    /// than fits in them is rejected.
     pub fn new(
         witness: Witness<Fr>,
         degree: usize,
         max_nodes: usize,
         max_proof_count: usize,
+        disable_storage: bool,
+        max_keccak_rows: usize,
     ) -> Result<StateUpdateCircuit<Fr>> {
         let Witness {
             mpt_witness,
             lc_witness,
         } = witness;
 
-        // populate the keccak data
-        let mut keccak_data = vec![];
-        for node in mpt_witness.iter() {
-            for k in node.keccak_data.iter() {
-                keccak_data.push(k.to_vec());
-            }
-        }
-
-        // verify the circuit
-        let disable_preimage_check = mpt_witness[0].start.clone().unwrap().disable_preimage_check;
+        eyre::ensure!(
+            !disable_storage || mpt_witness.iter().all(|node| node.storage.is_none()),
+            "the witness has storage proofs but the storage constraints are disabled"
+        );
 
-        let mpt_circuit = zkevm_circuits::mpt_circuit::MPTCircuit::<Fr> {
-            nodes: mpt_witness,
-            keccak_data: keccak_data.clone(),
-            degree,
-            max_nodes,
-            disable_preimage_check,
-            _marker: std::marker::PhantomData,
+        let mpt_circuit = MPTCircuit::<Fr> {
+            disable_storage,
+            ..MPTCircuit::new(mpt_witness, degree, max_nodes)
         };
 
         #[cfg(not(feature = "disable-keccak"))]
-        let keccak_circuit = KeccakCircuit::<Fr>::new(2usize.pow(degree as u32), keccak_data);
+        let keccak_circuit = {
+            let num_rows = KeccakCircuit::<Fr>::min_num_rows(&mpt_circuit.keccak_data);
+            eyre::ensure!(
+                num_rows <= max_keccak_rows,
+                "the keccak data needs {} rows but the keccak circuit has {}",
+                num_rows,
+                max_keccak_rows
+            );
+            KeccakCircuit::<Fr>::new(max_keccak_rows, mpt_circuit.keccak_data.clone())
+        };
 
-        let lc_circuit = StateUpdateCircuit::<Fr> {
+        let mut lc_circuit = StateUpdateCircuit::<Fr> {
             #[cfg(not(feature = "disable-keccak"))]
             keccak_circuit,
             mpt_circuit,
             lc_witness,
+            extra_public_inputs: Vec::new(),
             degree,
             max_proof_count,
+            max_keccak_rows,
         };
 
+        let min_degree = lc_circuit.min_degree() as usize;
+        if degree == 0 {
+            lc_circuit.degree = min_degree;
+            lc_circuit.mpt_circuit.degree = min_degree;
+        }
+        eyre::ensure!(
+            lc_circuit.degree >= min_degree,
+            "the witness needs degree {}",
+            min_degree
+        );
+
         Ok(lc_circuit)
     }
+
+    /// The minimal degree of the circuit for its witness: the rows of the MPT circuit, of the
+    /// state updates and, unless disabled, the declared rows of the keccak circuit, plus the
+    /// unusable rows.
+    pub fn min_degree(&self) -> u32 {
+        let rows = self.mpt_circuit.min_num_rows().max(self.max_proof_count);
+        let degree = log2_ceil(rows + MPTCircuit::<Fr>::unusable_rows());
+
+        #[cfg(not(feature = "disable-keccak"))]
+        let degree = degree.max(keccak_min_degree(self.max_keccak_rows) as u32);
+
+        degree
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keccak_rows_depend_on_keccak_data() {
+        let rows = KeccakCircuit::<Fr>::min_num_rows;
+        let few = vec![vec![0u8; 100]; 2];
+        let many = vec![vec![0u8; 100]; 200];
+        assert!(rows(&few) < rows(&many));
+
+        // Long inputs need more than one keccak_f each
+        let long = vec![vec![0u8; 1000]; 2];
+        assert!(rows(&few) < rows(&long));
+    }
+
+    #[test]
+    fn keccak_degree_depends_on_max_keccak_rows() {
+        assert!(keccak_min_degree(1 << 10) < keccak_min_degree(1 << 12));
+    }
 }
@@ -1,12 +1,62 @@
 //! Some utilities.
 
 use crate::circuit::witness::FieldTrieModifications;
-use eth_types::Field;
+use eth_types::{Address, Field};
+use eyre::{eyre, Result};
 use halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr};
 use std::ops::Deref;
+use zkevm_circuits::mpt_circuit::witness_row::Node;
 
 use super::state_update::StateUpdateCircuit;
 
+/// Layout of the instance column. It is shared by the circuit, that uses it to place the assigned
+/// cells to constrain, and by the verifier, that uses it to place the public input values, so
+/// both always agree on the position of each public input:
+///     old_root_lo, old_root_hi, new_root_lo, new_root_hi, number of MPT changes
+///     extra public inputs (e.g. the block number)
+///     for each change: change_type, address, value_lo, value_hi, key_lo, key_hi
+pub struct InstanceBuilder<T> {
+    old_root: [T; 2],
+    new_root: [T; 2],
+    count: T,
+    extra: Vec<T>,
+    changes: Vec<[T; 6]>,
+}
+
+impl<T> InstanceBuilder<T> {
+    /// Position of the first extra public input in the instance column
+    pub const EXTRA_OFFSET: usize = 5;
+
+    pub fn new(old_root: [T; 2], new_root: [T; 2], count: T) -> Self {
+        Self {
+            old_root,
+            new_root,
+            count,
+            extra: Vec::new(),
+            changes: Vec::new(),
+        }
+    }
+
+    /// Add an extra public input, placed after the number of changes.
+    pub fn push_extra(&mut self, value: T) {
+        self.extra.push(value);
+    }
+
+    /// Add a change: change_type, address, value_lo, value_hi, key_lo, key_hi.
+    pub fn push_change(&mut self, change: [T; 6]) {
+        self.changes.push(change);
+    }
+
+    pub fn build(self) -> Vec<T> {
+        let mut instance: Vec<T> = self.old_root.into_iter().collect();
+        instance.extend(self.new_root);
+        instance.push(self.count);
+        instance.extend(self.extra);
+        instance.extend(self.changes.into_iter().flatten());
+        instance
+    }
+}
+
 pub struct PublicInputs<F: Field>(pub Vec<F>);
 impl<F: Field> Deref for PublicInputs<F> {
     type Target = Vec<F>;
@@ -16,43 +66,193 @@ impl<F: Field> Deref for PublicInputs<F> {
     }
 }
 
-impl<F: Field> From<&FieldTrieModifications<F>> for PublicInputs<F> {
-    fn from(stm: &FieldTrieModifications<F>) -> Self {
-        let mut inputs = vec![
-            stm.0[0].old_root.lo(),
-            stm.0[0].old_root.hi(),
-            stm.0.last().unwrap().new_root.lo(),
-            stm.0.last().unwrap().new_root.hi(),
+impl<F: Field> PublicInputs<F> {
+    pub fn new(stm: &FieldTrieModifications<F>, extra_public_inputs: &[F]) -> Self {
+        let old_root = stm.0[0].old_root;
+        let new_root = stm.0.last().unwrap().new_root;
+        let mut instance = InstanceBuilder::new(
+            [old_root.lo(), old_root.hi()],
+            [new_root.lo(), new_root.hi()],
             F::from(stm.0.len() as u64),
-        ];
+        );
+
+        for value in extra_public_inputs {
+            instance.push_extra(*value);
+        }
 
         for proof in &stm.0 {
-            inputs.push(proof.typ);
-            inputs.push(proof.address);
-            inputs.push(proof.value.lo());
-            inputs.push(proof.value.hi());
-            inputs.push(proof.key.lo());
-            inputs.push(proof.key.hi());
+            instance.push_change([
+                proof.typ,
+                proof.address,
+                proof.value.lo(),
+                proof.value.hi(),
+                proof.key.lo(),
+                proof.key.hi(),
+            ]);
         }
 
-        PublicInputs(inputs)
+        PublicInputs(instance.build())
+    }
+}
+
+impl<F: Field> From<&FieldTrieModifications<F>> for PublicInputs<F> {
+    fn from(stm: &FieldTrieModifications<F>) -> Self {
+        Self::new(stm, &[])
+    }
+}
+
+/// Lists the (account address, storage key) pair of every storage leaf proven in `nodes`, in
+/// the order of the proofs.
+pub fn storage_slots_in_witness(nodes: &[Node]) -> Vec<(Address, [u8; 32])> {
+    let mut address = Address::zero();
+    let mut slots = Vec::new();
+    for node in nodes {
+        if let Some(account) = &node.account {
+            address = Address::from_slice(&account.address);
+        }
+        if let Some(storage) = &node.storage {
+            slots.push((address, storage.address.as_slice().try_into().unwrap()));
+        }
     }
+    slots
+}
+
+/// The nodes as JSON, in the format of the MPT witness files: the byte fields are hex encoded.
+/// Used to compare the node layouts of different runs.
+pub fn nodes_to_json(nodes: &[Node]) -> serde_json::Value {
+    serde_json::to_value(nodes).expect("nodes are serializable")
+}
+
+/// Pretty-prints the nodes as JSON, see [`nodes_to_json`].
+pub fn print_nodes(nodes: &[Node]) {
+    println!("{:#}", nodes_to_json(nodes));
 }
 
 impl StateUpdateCircuit<Fr> {
-    pub fn assert_satisfied(&self) {
-        let num_rows: usize = self
-            .mpt_circuit
+    /// Number of MPT rows used by the witness.
+    pub fn num_rows(&self) -> usize {
+        self.mpt_circuit
             .nodes
             .iter()
             .map(|node| node.values.len())
-            .sum();
+            .sum()
+    }
+
+    /// Returns the statistics of the circuit configuration as JSON, estimating the rows from the
+    /// MPT rows of the witness.
+    #[cfg(feature = "stats")]
+    pub fn circuit_stats_json(&self) -> serde_json::Value {
+        use halo2_proofs::plonk::Circuit;
+        zkevm_circuits::util::circuit_stats_json::<Fr, Self>(self.params(), self.num_rows())
+    }
+
+    pub fn assert_satisfied(&self) {
+        let num_rows = self.num_rows();
 
-        let public_inputs: PublicInputs<Fr> = (&self.lc_witness).into();
+        let public_inputs = PublicInputs::new(&self.lc_witness, &self.extra_public_inputs);
 
         let prover =
             MockProver::<Fr>::run(self.degree as u32, self, vec![public_inputs.0]).unwrap();
 
         prover.assert_satisfied_at_rows(0..num_rows, 0..num_rows);
     }
+
+    /// Like [`Self::assert_satisfied`], but returns the unsatisfied constraints as an error
+    /// instead of panicking.
+    pub fn check_satisfied(&self) -> Result<()> {
+        let num_rows = self.num_rows();
+
+        let public_inputs = PublicInputs::new(&self.lc_witness, &self.extra_public_inputs);
+
+        let prover = MockProver::<Fr>::run(self.degree as u32, self, vec![public_inputs.0])?;
+
+        prover
+            .verify_at_rows(0..num_rows, 0..num_rows)
+            .map_err(|failures| eyre!("the witness does not satisfy the circuit: {failures:?}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::witness::FieldTrieModification;
+    use std::str::FromStr;
+    use zkevm_circuits::{mpt_circuit::load_proof_from_file, util::word::WordLoHi};
+
+    #[test]
+    fn public_inputs_with_block_number() {
+        let word = |lo: u64, hi: u64| WordLoHi::new([Fr::from(lo), Fr::from(hi)]);
+        let stm = FieldTrieModifications(vec![
+            FieldTrieModification {
+                typ: Fr::from(1),
+                address: Fr::from(2),
+                value: word(3, 4),
+                key: word(5, 6),
+                old_root: word(7, 8),
+                new_root: word(9, 10),
+            },
+            FieldTrieModification {
+                typ: Fr::from(11),
+                address: Fr::from(12),
+                value: word(13, 14),
+                key: word(15, 16),
+                old_root: word(9, 10),
+                new_root: word(17, 18),
+            },
+        ]);
+        let block_number = Fr::from(19);
+
+        let expected = [
+            7, 8, 17, 18, 2, 19, 1, 2, 3, 4, 5, 6, 11, 12, 13, 14, 15, 16,
+        ]
+        .map(Fr::from);
+        assert_eq!(PublicInputs::new(&stm, &[block_number]).0, expected);
+
+        // Without extra public inputs the changes follow the number of changes
+        let public_inputs: PublicInputs<Fr> = (&stm).into();
+        assert_eq!(public_inputs.0[5..], expected[6..]);
+    }
+
+    #[test]
+    fn storage_slots() {
+        let nodes = load_proof_from_file(
+            "../../zkevm-circuits/src/mpt_circuit/tests/UpdateTwoModifications.json",
+        );
+        let address = Address::from_str("0xaaaccf12580138bc2bbceeeaa111df4e42ab81ff").unwrap();
+        let slot = |last_byte: u8| {
+            let mut slot = [0u8; 32];
+            slot[31] = last_byte;
+            slot
+        };
+        assert_eq!(
+            storage_slots_in_witness(&nodes),
+            vec![(address, slot(0x12)), (address, slot(0x21))]
+        );
+
+        // Account proofs have no storage slots
+        let nodes = load_proof_from_file(
+            "../../zkevm-circuits/src/mpt_circuit/tests/BalanceModCShort.json",
+        );
+        assert!(storage_slots_in_witness(&nodes).is_empty());
+    }
+
+    #[test]
+    fn nodes_json() {
+        let nodes = load_proof_from_file(
+            "../../zkevm-circuits/src/mpt_circuit/tests/BalanceModCShort.json",
+        );
+        let json = nodes_to_json(&nodes);
+        assert_eq!(json.as_array().unwrap().len(), nodes.len());
+        assert_eq!(
+            json[0]["values"][0],
+            serde_json::Value::String(hex::encode(&*nodes[0].values[0]))
+        );
+        assert_eq!(json[0]["start"]["proof_type"], "BalanceChanged");
+
+        // Loading the same witness twice gives the same JSON
+        let reloaded = load_proof_from_file(
+            "../../zkevm-circuits/src/mpt_circuit/tests/BalanceModCShort.json",
+        );
+        assert_eq!(nodes_to_json(&reloaded), json);
+    }
 }
@@ -0,0 +1,174 @@
+//! Verify proofs of the StateUpdate circuit.
+
+use eth_types::keccak256;
+use eyre::Result;
+use halo2_proofs::{
+    halo2curves::bn256::{Bn256, Fr, G1Affine},
+    plonk::{verify_proof, VerifyingKey},
+    poly::kzg::{
+        commitment::{KZGCommitmentScheme, ParamsVerifierKZG},
+        multiopen::VerifierSHPLONK,
+        strategy::SingleStrategy,
+    },
+    transcript::{Blake2bRead, Challenge255, TranscriptReadBuffer},
+    SerdeFormat,
+};
+use std::io::{Read, Write};
+use zkevm_circuits::mpt_circuit::{MPTCircuitParams, MPTRandomness};
+
+use super::{
+    state_update::{StateUpdateCircuit, StateUpdateCircuitParams},
+    utils::InstanceBuilder,
+};
+
+/// Format of the curve points of the serialized verifier params and verifying key
+const SERDE_FORMAT: SerdeFormat = SerdeFormat::RawBytes;
+
+/// Everything a verifier needs to check a proof: the verifier side of the KZG setup and the
+/// verifying key of the circuit.
+pub struct FullVerifierKey {
+    pub verifier_params: ParamsVerifierKZG<Bn256>,
+    pub vk: VerifyingKey<G1Affine>,
+    /// Parameters of the circuit the verifying key was generated for
+    pub circuit_params: StateUpdateCircuitParams,
+}
+
+impl FullVerifierKey {
+    /// Serialize the key, so that a verifier can load it with [`FullVerifierKey::from_bytes`]
+    /// without running the prover. The layout is, with the integers in little endian:
+    /// - the circuit params, needed to rebuild the constraint system of the verifying key:
+    ///   - `degree`: u64
+    ///   - `max_nodes`: u64
+    ///   - `disable_preimage_check`: u8, 0 or 1
+    ///   - `disable_storage`: u8, 0 or 1
+    ///   - `randomness`: u8, 0 for `Challenge` and 1 for `Fixed`, followed by the fixed value as
+    ///     u64 (0 for `Challenge`)
+    ///   - `max_keccak_rows`: u64
+    /// - the verifier params, written by `ParamsKZG::write_custom` in `SerdeFormat::RawBytes`
+    /// - the verifying key, written by `VerifyingKey::write` in `SerdeFormat::RawBytes`
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = vec![];
+        let params = &self.circuit_params.mpt;
+        bytes.write_all(&(params.degree as u64).to_le_bytes())?;
+        bytes.write_all(&(params.max_nodes as u64).to_le_bytes())?;
+        bytes.write_all(&[
+            params.disable_preimage_check as u8,
+            params.disable_storage as u8,
+        ])?;
+        let (tag, value) = match params.randomness {
+            MPTRandomness::Challenge => (0u8, 0u64),
+            MPTRandomness::Fixed(value) => (1u8, value),
+        };
+        bytes.write_all(&[tag])?;
+        bytes.write_all(&value.to_le_bytes())?;
+        bytes.write_all(&(self.circuit_params.max_keccak_rows as u64).to_le_bytes())?;
+        self.verifier_params
+            .write_custom(&mut bytes, SERDE_FORMAT)?;
+        self.vk.write(&mut bytes, SERDE_FORMAT)?;
+        Ok(bytes)
+    }
+
+    /// A keccak hash of the serialized key, see [`FullVerifierKey::to_bytes`]. Keys for the same
+    /// circuit shape (circuit params and setup) have the same fingerprint, so it can be used to
+    /// cache the keys and to check a proof is verified with the key of its circuit. The circuit
+    /// params are chosen by the caller of [`StateUpdateCircuit::new`], not derived from the
+    /// witness, so the fingerprint does not depend on the witness.
+    pub fn fingerprint(&self) -> [u8; 32] {
+        keccak256(&self.to_bytes().expect("writing to a Vec does not fail"))
+    }
+
+    /// Load a key serialized by [`FullVerifierKey::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = bytes;
+        let mpt = MPTCircuitParams {
+            degree: read_u64(&mut reader)? as usize,
+            max_nodes: read_u64(&mut reader)? as usize,
+            disable_preimage_check: read_bool(&mut reader)?,
+            disable_storage: read_bool(&mut reader)?,
+            randomness: match (read_u8(&mut reader)?, read_u64(&mut reader)?) {
+                (0, _) => MPTRandomness::Challenge,
+                (1, value) => MPTRandomness::Fixed(value),
+                (tag, _) => eyre::bail!("invalid randomness tag {}", tag),
+            },
+        };
+        let circuit_params = StateUpdateCircuitParams {
+            mpt,
+            max_keccak_rows: read_u64(&mut reader)? as usize,
+        };
+        let verifier_params = ParamsVerifierKZG::<Bn256>::read_custom(&mut reader, SERDE_FORMAT)?;
+        let vk = VerifyingKey::<G1Affine>::read::<_, StateUpdateCircuit<Fr>>(
+            &mut reader,
+            SERDE_FORMAT,
+            circuit_params,
+        )?;
+        eyre::ensure!(
+            reader.is_empty(),
+            "{} trailing bytes after the verifying key",
+            reader.len()
+        );
+
+        Ok(FullVerifierKey {
+            verifier_params,
+            vk,
+            circuit_params,
+        })
+    }
+}
+
+fn read_u8(reader: &mut impl Read) -> Result<u8> {
+    let mut buf = [0u8; 1];
+    reader.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_u64(reader: &mut impl Read) -> Result<u64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_bool(reader: &mut impl Read) -> Result<bool> {
+    match read_u8(reader)? {
+        0 => Ok(false),
+        1 => Ok(true),
+        byte => eyre::bail!("invalid bool {}", byte),
+    }
+}
+
+/// Verify a proof of the StateUpdate circuit against its public inputs.
+pub fn verify(fvk: &FullVerifierKey, proof: &[u8], public_inputs: &[Fr]) -> Result<()> {
+    let mut verifier_transcript = Blake2bRead::<_, G1Affine, Challenge255<_>>::init(proof);
+    let strategy = SingleStrategy::new(&fvk.verifier_params);
+
+    verify_proof::<
+        KZGCommitmentScheme<Bn256>,
+        VerifierSHPLONK<'_, Bn256>,
+        Challenge255<G1Affine>,
+        Blake2bRead<&[u8], G1Affine, Challenge255<G1Affine>>,
+        SingleStrategy<'_, Bn256>,
+    >(
+        &fvk.verifier_params,
+        &fvk.vk,
+        strategy,
+        &[&[public_inputs]],
+        &mut verifier_transcript,
+    )?;
+
+    Ok(())
+}
+
+/// Verify a proof of the state at block `block_number`, created by
+/// [`StateUpdateCircuit::prove_at_block`]: the block number is the first extra public input.
+pub fn verify_at_block(
+    fvk: &FullVerifierKey,
+    proof: &[u8],
+    public_inputs: &[Fr],
+    block_number: u64,
+) -> Result<()> {
+    eyre::ensure!(
+        public_inputs.get(InstanceBuilder::<Fr>::EXTRA_OFFSET) == Some(&Fr::from(block_number)),
+        "the public inputs are not for block {}",
+        block_number
+    );
+    verify(fvk, proof, public_inputs)
+}
@@ -22,7 +22,9 @@ use eyre::Result;
 
 use geth_utils::mpt::{ProofType, TrieModification};
 use zkevm_circuits::{
-    mpt_circuit::witness_row::Node, table::mpt_table::MPTProofType, util::word::WordLoHi,
+    mpt_circuit::{modified_value, witness_row::Node},
+    table::mpt_table::MPTProofType,
+    util::word::WordLoHi,
 };
 
 lazy_static! {
@@ -56,16 +58,20 @@ impl<F: Field> Deref for FieldTrieModifications<F> {
 struct Transforms {
     pub block_no: U64,
     pub prev_state_root: H256,
-    pub curr_state_root: H256,
+    /// `None` when the modifications are not the ones of a block, the root after them is then
+    /// only known from the witness
+    pub curr_state_root: Option<H256>,
     pub trie_modifications: Vec<TrieModification>,
 }
 
-trait TrieModificationBuilder {
+trait TrieModificationBuilder: Sized {
     fn balance(address: Address, balance: U256) -> Self;
     fn nonce(address: Address, nonce: U64) -> Self;
     fn codehash(address: Address, code_hash: H256) -> Self;
+    fn account_does_not_exist(address: Address) -> Self;
     fn storage(address: Address, key: H256, value: U256) -> Self;
     fn storage_does_not_exist(address: Address, key: H256, value: U256) -> Self;
+    fn storage_transition(address: Address, key: H256, prev: U256, next: U256) -> [Self; 2];
 }
 
 impl TrieModificationBuilder for TrieModification {
@@ -93,6 +99,13 @@ impl TrieModificationBuilder for TrieModification {
             ..Default::default()
         }
     }
+    fn account_does_not_exist(address: Address) -> Self {
+        Self {
+            typ: ProofType::AccountDoesNotExist,
+            address,
+            ..Default::default()
+        }
+    }
     fn storage(address: Address, key: H256, value: U256) -> Self {
         Self {
             typ: ProofType::StorageChanged,
@@ -111,6 +124,16 @@ impl TrieModificationBuilder for TrieModification {
             ..Default::default()
         }
     }
+    /// The modifications of a storage slot changing from `prev` to `next`: the first one proves
+    /// the previous value (or that the slot does not exist yet), the second one sets the new one.
+    fn storage_transition(address: Address, key: H256, prev: U256, next: U256) -> [Self; 2] {
+        let initial = if prev == U256::zero() {
+            Self::storage_does_not_exist(address, key, prev)
+        } else {
+            Self::storage(address, key, prev)
+        };
+        [initial, Self::storage(address, key, next)]
+    }
 }
 
 #[derive(Default)]
@@ -132,7 +155,7 @@ impl<F: Field> Witness<F> {
         let transforms =
             Self::get_transforms(provider, block_no, access_list, include_unchanged).await?;
 
-        if transforms.prev_state_root == transforms.curr_state_root {
+        if Some(transforms.prev_state_root) == transforms.curr_state_root {
             Ok(None)
         } else {
             let (mpt_witness, lc_witness) = Self::mpt_witness(&transforms, provider_url)?;
@@ -143,6 +166,126 @@ impl<F: Field> Witness<F> {
         }
     }
 
+    /// Build the witness proving that `address` does not exist in the state trie after block
+    /// `block_no`. Its MPT proof ends either in an empty slot of a branch or in the leaf of
+    /// another account sharing a prefix of the key, the wrong leaf, whose RLP bytes are the
+    /// `wrong_rlp_bytes` of the account node.
+    pub async fn account_does_not_exist(
+        provider_url: &str,
+        block_no: U64,
+        address: Address,
+    ) -> Result<Self> {
+        let provider: Provider<Http> = Provider::<Http>::try_from(provider_url)?;
+        let block_id = BlockId::Number(BlockNumber::Number(block_no));
+
+        let block = provider
+            .get_block(block_id)
+            .await?
+            .ok_or_else(|| eyre::eyre!("block {} not found", block_no))?;
+
+        // Depending on the node, the code hash of a missing account is zero or the empty hash
+        let proof = provider.get_proof(address, vec![], Some(block_id)).await?;
+        eyre::ensure!(
+            proof.nonce.is_zero()
+                && proof.balance.is_zero()
+                && (proof.code_hash.is_zero() || proof.code_hash == *DEFAULT_CODE_HASH),
+            "account {:?} exists at block {}",
+            address,
+            block_no
+        );
+
+        // The witness is generated on top of the state of the block before `transforms.block_no`
+        let transforms = Transforms {
+            block_no: block_no + 1,
+            prev_state_root: block.state_root,
+            curr_state_root: Some(block.state_root),
+            trie_modifications: vec![TrieModification::account_does_not_exist(address)],
+        };
+        let (mpt_witness, lc_witness) = Self::mpt_witness(&transforms, provider_url)?;
+        Ok(Self {
+            mpt_witness,
+            lc_witness,
+        })
+    }
+
+    /// Build the witness of an SSTORE of `next` to the slot `key` of the existing account
+    /// `address`, on top of the state after block `block_no`. The first proof sets the slot to
+    /// `prev` (it only reads the slot when it already holds `prev`), the second one is the storage
+    /// modification from `prev` to `next`: its storage leaf holds `prev` before and `next` after.
+    pub async fn storage_update(
+        provider_url: &str,
+        block_no: U64,
+        address: Address,
+        key: H256,
+        prev: U256,
+        next: U256,
+    ) -> Result<Self> {
+        let provider: Provider<Http> = Provider::<Http>::try_from(provider_url)?;
+        let block = provider
+            .get_block(BlockId::Number(BlockNumber::Number(block_no)))
+            .await?
+            .ok_or_else(|| eyre::eyre!("block {} not found", block_no))?;
+
+        // The witness is generated on top of the state of the block before `transforms.block_no`
+        let transforms = Transforms {
+            block_no: block_no + 1,
+            prev_state_root: block.state_root,
+            curr_state_root: None,
+            trie_modifications: TrieModification::storage_transition(address, key, prev, next)
+                .to_vec(),
+        };
+        let (mpt_witness, lc_witness) = Self::mpt_witness(&transforms, provider_url)?;
+        Ok(Self {
+            mpt_witness,
+            lc_witness,
+        })
+    }
+
+    /// Check that the public values in `lc_witness` are the ones proven by `mpt_witness`: for
+    /// every MPT proof, the proof type, the address of the account, the new value and storage
+    /// key read from the leaf rows, and the roots before and after the modification.
+    pub fn verify_public_inputs(&self) -> bool {
+        let proofs: Vec<_> = self
+            .mpt_witness
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| {
+                node.start
+                    .as_ref()
+                    .map_or(false, |start| start.proof_type != MPTProofType::Disabled)
+            })
+            .collect();
+
+        proofs.len() == self.lc_witness.len()
+            && proofs
+                .iter()
+                .zip(self.lc_witness.iter())
+                .all(|((idx, node), lc)| {
+                    let proof_type = node.start.as_ref().unwrap().proof_type;
+                    let old_root = H256::from_slice(&node.values[0][1..33]);
+                    let new_root = H256::from_slice(&node.values[1][1..33]);
+                    let address = self.mpt_witness[*idx..]
+                        .iter()
+                        .find_map(|node| node.account.as_ref())
+                        .map(|account| Address::from_slice(&account.address));
+                    let end = self.mpt_witness[idx + 1..]
+                        .iter()
+                        .position(|node| node.start.is_some())
+                        .map_or(self.mpt_witness.len(), |len| idx + 1 + len);
+                    let value_and_key = modified_value(&self.mpt_witness[*idx..end]);
+
+                    lc.typ == F::from(proof_type as u64)
+                        && address
+                            .map_or(false, |address| lc.address == address.to_scalar().unwrap())
+                        && value_and_key.map_or(false, |(value, key)| {
+                            lc.value == WordLoHi::<F>::from(value)
+                                && lc.key == WordLoHi::<F>::from(key)
+                        })
+                        && lc.old_root == WordLoHi::<F>::from(old_root)
+                        && lc.new_root == WordLoHi::<F>::from(new_root)
+                })
+    }
+
     async fn get_transforms(
         provider: Provider<Http>,
         block_no: U64,
@@ -256,17 +399,6 @@ impl<F: Field> Witness<F> {
                 initial_values.push(TrieModification::balance(address, old.balance));
                 initial_values.push(TrieModification::nonce(address, old.nonce));
                 initial_values.push(TrieModification::codehash(address, old.code_hash));
-
-                for key in storage_keys.iter() {
-                    let old = old.storage_proof.iter().find(|p| p.key == *key).unwrap();
-                    if old.value == U256::zero() {
-                        initial_values.push(TrieModification::storage_does_not_exist(
-                            address, *key, old.value,
-                        ));
-                    } else {
-                        initial_values.push(TrieModification::storage(address, *key, old.value));
-                    }
-                }
             }
 
             // check for this address changes
@@ -283,8 +415,14 @@ impl<F: Field> Witness<F> {
             }
 
             for key in storage_keys {
+                let old = old.storage_proof.iter().find(|p| p.key == key).unwrap();
                 let new = new.storage_proof.iter().find(|p| p.key == key).unwrap();
-                changed_values.push(TrieModification::storage(address, key, new.value));
+                let [initial, change] =
+                    TrieModification::storage_transition(address, key, old.value, new.value);
+                if include_initial_values {
+                    initial_values.push(initial);
+                }
+                changed_values.push(change);
             }
         }
 
@@ -293,7 +431,7 @@ impl<F: Field> Witness<F> {
 
         Ok(Transforms {
             block_no,
-            curr_state_root: curr_block.state_root,
+            curr_state_root: Some(curr_block.state_root),
             prev_state_root: prev_block.state_root,
             trie_modifications,
         })
@@ -324,10 +462,12 @@ impl<F: Field> Witness<F> {
             witness_previous_state_root, trns.prev_state_root,
             "previous state root does not match"
         );
-        assert_eq!(
-            witness_current_state_root, trns.curr_state_root,
-            "current state root does not match"
-        );
+        if let Some(curr_state_root) = trns.curr_state_root {
+            assert_eq!(
+                witness_current_state_root, curr_state_root,
+                "current state root does not match"
+            );
+        }
 
         // extract roots
         let mut lc_proofs = Vec::new();
@@ -373,6 +513,12 @@ impl<F: Field> Witness<F> {
                 ProofType::StorageDoesNotExist => {
                     (ProofType::StorageDoesNotExist, m.address, m.value, m.key)
                 }
+                ProofType::AccountDoesNotExist => (
+                    ProofType::AccountDoesNotExist,
+                    m.address,
+                    U256::zero(),
+                    H256::zero(),
+                ),
                 _ => {
                     println!("type unimplemented: {:?}", m.typ);
                     unimplemented!()
@@ -395,3 +541,200 @@ impl<F: Field> Witness<F> {
         Ok((nodes, FieldTrieModifications(lc_proofs)))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::state_update::{keccak_rows_for_degree, StateUpdateCircuit};
+    use halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr};
+    use rand_chacha::{
+        rand_core::{RngCore, SeedableRng},
+        ChaChaRng,
+    };
+    use zkevm_circuits::mpt_circuit::{load_proof_from_file, EthProof, MPTCircuit};
+
+    #[test]
+    fn storage_transition() {
+        let address = Address::from_low_u64_be(0x10);
+        let key = H256::from_low_u64_be(1);
+
+        let [initial, change] =
+            TrieModification::storage_transition(address, key, U256::from(3), U256::from(5));
+        assert!(matches!(initial.typ, ProofType::StorageChanged));
+        assert_eq!(initial.value, U256::from(3));
+        assert!(matches!(change.typ, ProofType::StorageChanged));
+        assert_eq!(change.value, U256::from(5));
+        assert!([initial, change]
+            .iter()
+            .all(|m| m.address == address && m.key == key));
+
+        // A slot that did not exist before
+        let [initial, change] =
+            TrieModification::storage_transition(address, key, U256::zero(), U256::from(5));
+        assert!(matches!(initial.typ, ProofType::StorageDoesNotExist));
+        assert!(matches!(change.typ, ProofType::StorageChanged));
+    }
+
+    #[test]
+    fn verify_public_inputs() {
+        let mpt_witness = load_proof_from_file(
+            "../../zkevm-circuits/src/mpt_circuit/tests/BalanceModCShort.json",
+        );
+        let root = |hex: &str| WordLoHi::<Fr>::from(H256::from_str(hex).unwrap());
+        let lc = FieldTrieModification::<Fr> {
+            typ: Fr::from(MPTProofType::BalanceChanged as u64),
+            address: Address::from_str("0x68d5a6e78bd8734b7d190cbd98549b72bfa0800b")
+                .unwrap()
+                .to_scalar()
+                .unwrap(),
+            old_root: root("0x224124c6237ebee590056b9bcd2462b571d68d62c3348dc1f20449c05368649b"),
+            new_root: root("0xd777e3e92548110f8eb72385fbb351fe7f6bf6ae4a322b3859ad91dbafa7dbc5"),
+            value: WordLoHi::<Fr>::from(U256::from(0x62)),
+            key: WordLoHi::<Fr>::from(H256::zero()),
+        };
+
+        let witness = |lc: FieldTrieModification<Fr>| Witness {
+            lc_witness: FieldTrieModifications(vec![lc]),
+            mpt_witness: mpt_witness.clone(),
+        };
+        assert!(witness(lc.clone()).verify_public_inputs());
+
+        // Tampered values
+        let mut tampered = lc.clone();
+        tampered.new_root = tampered.old_root;
+        assert!(!witness(tampered).verify_public_inputs());
+
+        let mut tampered = lc.clone();
+        tampered.typ = Fr::from(MPTProofType::NonceChanged as u64);
+        assert!(!witness(tampered).verify_public_inputs());
+
+        let mut tampered = lc.clone();
+        tampered.address += Fr::from(1);
+        assert!(!witness(tampered).verify_public_inputs());
+
+        let mut tampered = lc.clone();
+        tampered.value = WordLoHi::<Fr>::from(U256::from(0x63));
+        assert!(!witness(tampered).verify_public_inputs());
+
+        let mut tampered = lc;
+        tampered.key = WordLoHi::<Fr>::from(H256::from_low_u64_be(1));
+        assert!(!witness(tampered).verify_public_inputs());
+    }
+
+    #[tokio::test]
+    #[ignore = "Requires an archive node at WEB3_PROVIDER_URL"]
+    async fn account_does_not_exist() {
+        let provider_url = std::env::var("WEB3_PROVIDER_URL").unwrap();
+        let provider = Provider::<Http>::try_from(provider_url.as_str()).unwrap();
+        let block_no = provider.get_block_number().await.unwrap();
+
+        // A random address, that no one ever used
+        let mut address = Address::zero();
+        ChaChaRng::seed_from_u64(42).fill_bytes(&mut address.0);
+
+        let witness = Witness::<Fr>::account_does_not_exist(&provider_url, block_no, address)
+            .await
+            .unwrap();
+        assert!(witness.verify_public_inputs());
+        assert!(witness.mpt_witness[0]
+            .start
+            .as_ref()
+            .map_or(false, |start| start.proof_type
+                == MPTProofType::AccountDoesNotExist));
+
+        StateUpdateCircuit::new(witness, 15, 520, 10, false, keccak_rows_for_degree(15))
+            .unwrap()
+            .assert_satisfied();
+    }
+
+    #[tokio::test]
+    #[ignore = "Requires an archive node at WEB3_PROVIDER_URL"]
+    async fn nodes_from_eth_proof() {
+        let provider_url = std::env::var("WEB3_PROVIDER_URL").unwrap();
+        let provider = Provider::<Http>::try_from(provider_url.as_str()).unwrap();
+        let block = provider
+            .get_block(BlockNumber::Latest)
+            .await
+            .unwrap()
+            .unwrap();
+
+        // The fee recipient exists, a random slot of its storage is empty
+        let mut key = H256::zero();
+        ChaChaRng::seed_from_u64(42).fill_bytes(&mut key.0);
+        let proof: EthProof = provider
+            .request(
+                "eth_getProof",
+                (
+                    block.author.unwrap(),
+                    [H256::zero(), key],
+                    block.number.unwrap(),
+                ),
+            )
+            .await
+            .unwrap();
+
+        let nodes = Node::from_eth_proof(proof).unwrap();
+        let starts: Vec<_> = nodes
+            .iter()
+            .filter(|node| {
+                node.start
+                    .as_ref()
+                    .map_or(false, |start| start.proof_type != MPTProofType::Disabled)
+            })
+            .collect();
+        assert_eq!(starts.len(), 2);
+        for start in starts.iter() {
+            assert_eq!(H256::from_slice(&start.values[0][1..33]), block.state_root);
+            assert_eq!(start.values[0], start.values[1]);
+        }
+        assert_eq!(
+            starts[1].start.as_ref().unwrap().proof_type,
+            MPTProofType::StorageDoesNotExist
+        );
+
+        let circuit = MPTCircuit::<Fr>::new(nodes, 15, 520);
+        MockProver::<Fr>::run(15, &circuit, vec![])
+            .unwrap()
+            .assert_satisfied();
+    }
+
+    #[tokio::test]
+    #[ignore = "Requires an archive node at WEB3_PROVIDER_URL"]
+    async fn storage_update() {
+        let provider_url = std::env::var("WEB3_PROVIDER_URL").unwrap();
+        let provider = Provider::<Http>::try_from(provider_url.as_str()).unwrap();
+        let block = provider
+            .get_block(BlockNumber::Latest)
+            .await
+            .unwrap()
+            .unwrap();
+
+        // A random slot of the fee recipient, an account that exists
+        let mut key = H256::zero();
+        ChaChaRng::seed_from_u64(42).fill_bytes(&mut key.0);
+
+        let witness = Witness::<Fr>::storage_update(
+            &provider_url,
+            block.number.unwrap(),
+            block.author.unwrap(),
+            key,
+            U256::from(1),
+            U256::from(17),
+        )
+        .await
+        .unwrap();
+        assert!(witness.verify_public_inputs());
+
+        let [set, update] = witness.lc_witness.as_slice() else {
+            panic!("expected two proofs");
+        };
+        assert_eq!(set.new_root, update.old_root);
+        assert_eq!(update.typ, Fr::from(MPTProofType::StorageChanged as u64));
+        assert_eq!(update.value, WordLoHi::from(U256::from(17)));
+        assert_ne!(update.old_root, update.new_root);
+
+        StateUpdateCircuit::new(witness, 15, 520, 10, false, keccak_rows_for_degree(15))
+            .unwrap()
+            .assert_satisfied();
+    }
+}
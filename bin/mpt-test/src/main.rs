@@ -8,9 +8,15 @@ use ethers::{prelude::*, types::transaction::eip2930::AccessList};
 use eyre::Result;
 use halo2_proofs::halo2curves::bn256::Fr;
 
-use circuit::{state_update::StateUpdateCircuit, witness::Witness};
+use circuit::{
+    state_update::{keccak_rows_for_degree, StateUpdateCircuit},
+    utils::storage_slots_in_witness,
+    witness::Witness,
+};
 
-// test the StateUpdate circuit with a mock prover
+// test the StateUpdate circuit with a mock prover (or the real one if REAL_PROVER is set, or
+// time both if BENCH_PROVERS is set), printing the storage slots of the witness if
+// PRINT_STORAGE_SLOTS is set
 async fn mock_prove(block_no: u64, access_list: &str) -> Result<()> {
     let provider_url = "http://localhost:3000";
 
@@ -28,8 +34,34 @@ async fn mock_prove(block_no: u64, access_list: &str) -> Result<()> {
         .await?
         .unwrap();
 
-    let circuit = StateUpdateCircuit::new(witness, 16, max_nodes, proof_count + 10)?;
-    circuit.assert_satisfied();
+    assert!(
+        witness.verify_public_inputs(),
+        "the public inputs of block {block_no} do not match the MPT proofs"
+    );
+
+    if std::env::var("PRINT_STORAGE_SLOTS").is_ok() {
+        for (address, key) in storage_slots_in_witness(&witness.mpt_witness) {
+            println!("{:?} {:?}", address, H256::from(key));
+        }
+    }
+
+    let mut circuit = StateUpdateCircuit::new(
+        witness,
+        16,
+        max_nodes,
+        proof_count + 10,
+        false,
+        keccak_rows_for_degree(16),
+    )?;
+    circuit.extra_public_inputs.push(Fr::from(block_no));
+
+    if std::env::var("BENCH_PROVERS").is_ok() {
+        circuit.bench_provers()?;
+    } else if std::env::var("REAL_PROVER").is_ok() {
+        circuit.prove_verify_and_assert()?;
+    } else {
+        circuit.assert_satisfied();
+    }
     Ok(())
 }
 
@@ -42,6 +42,12 @@ pub enum Error {
     InternalError(&'static str),
     /// Rw number overflow
     RwsNotEnough(usize, usize),
+    /// Call of a precompile at an address that is not a precompiled contract
+    NotPrecompiled(Address),
+    /// Precompile that can't be run in this build, e.g. in WASM
+    PrecompileNotSupported(Address),
+    /// Invalid input of a precompile call
+    InvalidPrecompileInput(&'static str),
 }
 
 impl From<eth_types::Error> for Error {
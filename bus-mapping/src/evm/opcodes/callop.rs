@@ -8,7 +8,7 @@ use crate::{
         precompiles::gen_associated_ops as precompile_associated_ops,
     },
     operation::{AccountField, CallContextField, TxAccessListAccountOp},
-    precompile::{execute_precompiled, is_precompiled, PrecompileCalls},
+    precompile::{execute_precompiled, is_precompiled, PrecompileCalls, PrecompileFailure},
     state_db::CodeDB,
     Error,
 };
@@ -274,7 +274,7 @@ impl<const N_ARGS: usize> Opcode for CallOpcode<N_ARGS> {
 
                 // get the result of the precompile call.
                 // For failed call, it will cost all gas provided
-                let (result, precompile_call_gas_cost, has_oog_err) = execute_precompiled(
+                let (result, precompile_call_gas_cost, failure) = execute_precompiled(
                     &code_address,
                     if args_length != 0 {
                         let caller_memory = &state.caller_ctx()?.memory;
@@ -283,7 +283,7 @@ impl<const N_ARGS: usize> Opcode for CallOpcode<N_ARGS> {
                         &[]
                     },
                     callee_gas_left_with_stipend,
-                );
+                )?;
 
                 // mutate the callee memory by at least the precompile call's result that will be
                 // written from memory addr 0 to memory addr result.len()
@@ -441,7 +441,7 @@ impl<const N_ARGS: usize> Opcode for CallOpcode<N_ARGS> {
                     );
                 }
 
-                if has_oog_err {
+                if failure == Some(PrecompileFailure::OutOfGas) {
                     let mut oog_step = ErrorOOGPrecompile::gen_associated_ops(
                         state,
                         &geth_steps[1],
@@ -33,6 +33,22 @@ fn exp_by_squaring(base: U256, exponent: U256, steps: &mut Vec<ExpStep>) -> U256
     }
 }
 
+impl ExpEvent {
+    /// Returns the event of the exponentiation `base ^ exponent (mod 2^256)`, with the
+    /// multiplication steps of the exponentiation by squaring.
+    pub fn new(identifier: usize, base: U256, exponent: U256) -> Self {
+        let mut steps = Vec::new();
+        let exponentiation = exp_by_squaring(base, exponent, &mut steps);
+        Self {
+            identifier,
+            base,
+            exponent,
+            exponentiation,
+            steps,
+        }
+    }
+}
+
 impl Opcode for Exponentiation {
     fn gen_associated_ops(
         state: &mut CircuitInputStateRef,
@@ -53,16 +69,9 @@ impl Opcode for Exponentiation {
             exponentiation,
         )?;
 
-        let mut steps = Vec::new();
-        let exponentiation_calc = exp_by_squaring(base, exponent, &mut steps);
-        debug_assert_eq!(exponentiation, exponentiation_calc);
-        state.push_exponentiation(ExpEvent {
-            identifier: state.block_ctx.rwc.0,
-            base,
-            exponent,
-            exponentiation,
-            steps,
-        });
+        let exp_event = ExpEvent::new(state.block_ctx.rwc.0, base, exponent);
+        debug_assert_eq!(exponentiation, exp_event.exponentiation);
+        state.push_exponentiation(exp_event);
 
         Ok(vec![exec_step])
     }
@@ -59,3 +59,121 @@ fn common_call_ctx_reads(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        mock::BlockData,
+        precompile::{is_precompiled, PrecompileCallArgs},
+    };
+    use eth_types::{bytecode, evm_types::OpcodeId, geth_types::GethData, word};
+    use mock::{
+        test_ctx::helpers::{account_0_code_account_1_no_code, tx_from_1_to_0},
+        TestContext,
+    };
+
+    #[test]
+    fn test_precompiles_called_in_sequence() {
+        let ecrecover = PrecompileCallArgs {
+            name: "ecrecover",
+            setup_code: bytecode! {
+                // msg hash
+                PUSH32(word!("0x456e9aea5e197a1f1af7a3e85a3212fa4049a3ba34c2289b4c860fc0b0c64ef3"))
+                PUSH1(0x00)
+                MSTORE
+                // v
+                PUSH1(28)
+                PUSH1(0x20)
+                MSTORE
+                // r
+                PUSH32(word!("0x9242685bf161793cc25603c231bc2f568eb630ea16aa137d2664ac8038825608"))
+                PUSH1(0x40)
+                MSTORE
+                // s
+                PUSH32(word!("0x4f8ae3bd7535248d0bd448298cc2e2071e56992d0774dc340c368ae950852ada"))
+                PUSH1(0x60)
+                MSTORE
+            },
+            call_data_offset: 0x00.into(),
+            call_data_length: 0x80.into(),
+            ret_offset: 0x80.into(),
+            ret_size: 0x20.into(),
+            address: PrecompileCalls::ECRecover.address().to_word(),
+            ..Default::default()
+        };
+        let sha256 = PrecompileCallArgs {
+            name: "sha256",
+            setup_code: bytecode! {
+                PUSH16(word!("0x0123456789abcdef0f1e2d3c4b5a6978"))
+                PUSH1(0xa0)
+                MSTORE
+            },
+            call_data_offset: 0xa0.into(),
+            call_data_length: 0x20.into(),
+            ret_offset: 0xc0.into(),
+            ret_size: 0x20.into(),
+            address: PrecompileCalls::Sha256.address().to_word(),
+            ..Default::default()
+        };
+
+        let mut code = ecrecover.with_call_op(OpcodeId::CALL);
+        code.append(&sha256.with_call_op(OpcodeId::CALL));
+        code.write_op(OpcodeId::STOP);
+
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(code),
+            tx_from_1_to_0,
+            |block, _tx| block,
+        )
+        .unwrap()
+        .into();
+
+        let builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        let builder = builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+        let tx = &builder.block.txs()[0];
+
+        // One precompile step per call, in the order of the calls
+        let precompile_steps: Vec<_> = tx
+            .steps()
+            .iter()
+            .filter_map(|step| match step.exec_state {
+                ExecState::Precompile(precompile) => Some(precompile),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            precompile_steps,
+            vec![PrecompileCalls::ECRecover, PrecompileCalls::Sha256]
+        );
+
+        // Each precompile call carries its own call data and return data
+        let precompile_calls: Vec<_> = tx
+            .calls()
+            .iter()
+            .filter(|call| {
+                call.code_address()
+                    .map_or(false, |addr| is_precompiled(&addr))
+            })
+            .map(|call| {
+                (
+                    PrecompileCalls::from(call.code_address().unwrap().0[19]),
+                    call.call_data_offset,
+                    call.call_data_length,
+                    call.return_data_offset,
+                    call.return_data_length,
+                )
+            })
+            .collect();
+        assert_eq!(
+            precompile_calls,
+            vec![
+                (PrecompileCalls::ECRecover, 0x00, 0x80, 0x80, 0x20),
+                (PrecompileCalls::Sha256, 0xa0, 0x20, 0xc0, 0x20),
+            ]
+        );
+    }
+}
@@ -395,6 +395,25 @@ mod stackonlyop_tests {
         );
     }
 
+    fn not_opcode_word_impl(a: Word, result: Word) {
+        stack_only_opcode_impl::<1, 1>(
+            OpcodeId::NOT,
+            bytecode! {
+                PUSH32(a)
+                NOT
+                STOP
+            },
+            vec![StackOp::new(1, StackAddress(1023), a)],
+            vec![StackOp::new(1, StackAddress(1023), result)],
+        );
+    }
+    #[test]
+    fn test_not_operate() {
+        not_opcode_word_impl(Word::zero(), Word::MAX);
+        not_opcode_word_impl(Word::MAX, Word::zero());
+        not_opcode_word_impl(Word::from(0xff), Word::MAX - Word::from(0xff));
+    }
+
     #[test]
     fn difficulty_opcode_impl() {
         stack_only_opcode_impl::<0, 1>(
@@ -1,63 +1,231 @@
 //! precompile helpers
 
+use crate::Error;
 use eth_types::{
-    evm_types::{GasCost, OpcodeId},
+    evm_types::{gas_utils::eip150_gas, GasCost, OpcodeId, GAS_STIPEND_CALL_WITH_VALUE},
     Address, Bytecode, Word,
 };
 #[cfg(not(target_arch = "wasm32"))]
 use revm_precompile::{Precompile, PrecompileError, Precompiles};
 
-#[allow(unused_variables)]
+/// Hard forks that changed the set of precompiled contracts or their gas costs, mirroring the
+/// `SpecId` of `revm-precompile`. Defaults to Berlin.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Hardfork {
+    /// ecRecover, SHA2-256, RIPEMD-160 and identity
+    Homestead,
+    /// Adds modexp (EIP-198) and the BN256 precompiles (EIP-196, EIP-197)
+    Byzantium,
+    /// Adds blake2f (EIP-152) and reprices the BN256 precompiles (EIP-1108)
+    Istanbul,
+    /// Reprices modexp (EIP-2565)
+    #[default]
+    Berlin,
+    /// Same precompiles as Berlin
+    London,
+}
+
+impl Hardfork {
+    /// Number of precompiled contracts, at addresses `1..=precompile_count()`.
+    pub fn precompile_count(&self) -> u64 {
+        match self {
+            Self::Homestead => 4,
+            Self::Byzantium => 8,
+            Self::Istanbul | Self::Berlin | Self::London => 9,
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn precompiles(&self) -> &'static Precompiles {
+        match self {
+            Self::Homestead => Precompiles::homestead(),
+            Self::Byzantium => Precompiles::byzantium(),
+            Self::Istanbul => Precompiles::istanbul(),
+            Self::Berlin | Self::London => Precompiles::berlin(),
+        }
+    }
+}
+
 /// Check if address is a precompiled or not.
 pub fn is_precompiled(address: &Address) -> bool {
+    is_precompiled_in(address, Hardfork::default())
+}
+
+/// Check if address is a precompiled contract at `hardfork`.
+pub fn is_precompiled_in(address: &Address, hardfork: Hardfork) -> bool {
     #[cfg(target_arch = "wasm32")]
-    if address.0[0..19] == [0u8; 19] && (1..=9).contains(&address.0[19]) {
-        // TODO add support for precompiles in WASM
-        panic!("Precompile {address} is currently not supported in WASM");
-    } else {
-        return false;
-    }
+    return address.0[0..19] == [0u8; 19]
+        && (1..=hardfork.precompile_count()).contains(&u64::from(address.0[19]));
 
     #[cfg(not(target_arch = "wasm32"))]
-    Precompiles::berlin()
+    hardfork
+        .precompiles()
         .get(address.as_fixed_bytes())
         .is_some()
 }
 
-#[allow(unused_variables)]
+/// Reason a precompile failed, see [`execute_precompiled_in`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum PrecompileFailure {
+    /// The gas forwarded to the precompile is not enough to run it
+    OutOfGas,
+    /// Any other error of `revm-precompile`, e.g. an invalid input
+    #[cfg(not(target_arch = "wasm32"))]
+    Revm(PrecompileError),
+}
+
+/// Run the precompile at `address` on `input` with `forwarded_gas`, the gas forwarded to it by
+/// the call, with the precompiles of Berlin. See [`execute_precompiled_in`].
 pub(crate) fn execute_precompiled(
     address: &Address,
     input: &[u8],
-    gas: u64,
-) -> (Vec<u8>, u64, bool) {
+    forwarded_gas: u64,
+) -> Result<(Vec<u8>, u64, Option<PrecompileFailure>), Error> {
+    execute_precompiled_in(address, input, forwarded_gas, Hardfork::default())
+}
+
+/// Run the precompile at `address` on `input` with `forwarded_gas`, the gas forwarded to it by
+/// the call, with the precompiles and gas costs of `hardfork`. Returns the return data, the gas
+/// consumed and the reason the precompile failed, if it did.
+///
+/// A precompile that fails consumes all of `forwarded_gas` and returns no data: on out of gas
+/// the result is `(vec![], forwarded_gas, Some(PrecompileFailure::OutOfGas))`, on other errors
+/// (invalid input) `(vec![], forwarded_gas, Some(PrecompileFailure::Revm(err)))` with the error
+/// of `revm-precompile`.
+///
+/// Returns [`Error::NotPrecompiled`] when `address` is not a precompiled contract at
+/// `hardfork`. In WASM only the precompiles of the `wasm-precompiles` feature (ecRecover,
+/// SHA2-256 and identity) can be run, the other ones return [`Error::PrecompileNotSupported`].
+#[cfg_attr(target_arch = "wasm32", allow(unused_variables))]
+pub(crate) fn execute_precompiled_in(
+    address: &Address,
+    input: &[u8],
+    forwarded_gas: u64,
+    hardfork: Hardfork,
+) -> Result<(Vec<u8>, u64, Option<PrecompileFailure>), Error> {
+    if !is_precompiled_in(address, hardfork) {
+        return Err(Error::NotPrecompiled(*address));
+    }
+
     #[cfg(target_arch = "wasm32")]
-    // TODO add support for precompiles in WASM
-    panic!("Running precompile {address} is currently not supported in WASM");
+    {
+        #[cfg(feature = "wasm-precompiles")]
+        if let Some(result) = wasm::execute(address.0[19].into(), input, forwarded_gas) {
+            return Ok(result);
+        }
+        Err(Error::PrecompileNotSupported(*address))
+    }
 
     #[cfg(not(target_arch = "wasm32"))]
     {
-        let Some(Precompile::Standard(precompile_fn)) = Precompiles::berlin()
-        .get(address.as_fixed_bytes())  else {
-            panic!("calling non-exist precompiled contract address")
+        let Some(Precompile::Standard(precompile_fn)) =
+            hardfork.precompiles().get(address.as_fixed_bytes())
+        else {
+            return Err(Error::PrecompileNotSupported(*address));
         };
-        let (return_data, gas_cost, is_oog, is_ok) = match precompile_fn(input, gas) {
+        let (return_data, gas_cost, failure) = match precompile_fn(input, forwarded_gas) {
             Ok((gas_cost, return_value)) => {
                 // Some Revm behavior for invalid inputs might be overridden.
-                (return_value, gas_cost, false, true)
+                (return_value, gas_cost, None)
+            }
+            Err(PrecompileError::OutOfGas) => {
+                (vec![], forwarded_gas, Some(PrecompileFailure::OutOfGas))
             }
-            Err(err) => match err {
-                PrecompileError::OutOfGas => (vec![], gas, true, false),
-                _ => {
-                    log::warn!("unknown precompile err {err:?}");
-                    (vec![], gas, false, false)
-                }
-            },
+            Err(err) => (vec![], forwarded_gas, Some(PrecompileFailure::Revm(err))),
         };
-        log::trace!("called precompile with is_ok {is_ok} is_oog {is_oog}, gas_cost {gas_cost}, return_data len {}", return_data.len());
-        (return_data, gas_cost, is_oog)
+        log::trace!(
+            "called precompile with failure {failure:?}, gas_cost {gas_cost}, return_data len {}",
+            return_data.len()
+        );
+        Ok((return_data, gas_cost, failure))
     }
 }
 
+/// Pure Rust implementation of the precompiles that compile to WASM, used in place of
+/// `revm-precompile` when targeting `wasm32`.
+#[cfg(feature = "wasm-precompiles")]
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+mod wasm {
+    use super::{PrecompileCalls, PrecompileFailure};
+    use eth_types::{evm_types::GasCost, keccak256};
+    use sha2::{Digest, Sha256};
+
+    /// Same result as `execute_precompiled`, or `None` when `precompile` is not implemented.
+    pub(super) fn execute(
+        precompile: PrecompileCalls,
+        input: &[u8],
+        forwarded_gas: u64,
+    ) -> Option<(Vec<u8>, u64, Option<PrecompileFailure>)> {
+        let words = (input.len() as u64 + 31) / 32;
+        let gas_cost = match precompile {
+            PrecompileCalls::ECRecover => GasCost::PRECOMPILE_ECRECOVER_BASE,
+            PrecompileCalls::Sha256 => {
+                GasCost::PRECOMPILE_SHA256_BASE + GasCost::PRECOMPILE_SHA256_PER_WORD * words
+            }
+            PrecompileCalls::Identity => {
+                GasCost::PRECOMPILE_IDENTITY_BASE + GasCost::PRECOMPILE_IDENTITY_PER_WORD * words
+            }
+            _ => return None,
+        };
+        if gas_cost > forwarded_gas {
+            return Some((vec![], forwarded_gas, Some(PrecompileFailure::OutOfGas)));
+        }
+        let output = match precompile {
+            PrecompileCalls::ECRecover => ecrecover(input),
+            PrecompileCalls::Sha256 => Sha256::digest(input).to_vec(),
+            _ => input.to_vec(),
+        };
+        Some((output, gas_cost, None))
+    }
+
+    /// Address of the signer left padded to 32 bytes, no output when the signature is invalid.
+    fn ecrecover(input: &[u8]) -> Vec<u8> {
+        // The input is the message hash, v, r and s, right padded with zeros
+        let mut input = input.to_vec();
+        input.resize(128, 0);
+        if input[32..63].iter().any(|byte| *byte != 0) || !matches!(input[63], 27 | 28) {
+            return vec![];
+        }
+        let recover = || {
+            let message = libsecp256k1::Message::parse_slice(&input[..32])?;
+            let signature = libsecp256k1::Signature::parse_standard_slice(&input[64..])?;
+            let recovery_id = libsecp256k1::RecoveryId::parse_rpc(input[63])?;
+            libsecp256k1::recover(&message, &signature, &recovery_id)
+        };
+        match recover() {
+            Ok(pk) => {
+                let mut address = keccak256(&pk.serialize()[1..]);
+                address[..12].fill(0);
+                address.to_vec()
+            }
+            Err(_) => vec![],
+        }
+    }
+}
+
+/// Gas of a `CALL` to `precompile` with `input`. `available_gas` is the gas left after paying
+/// for the `CALL` itself and `gas_specified` is the gas argument of the `CALL`.
+///
+/// Returns the gas forwarded to the precompile, which is all but one 64th of the available gas
+/// capped by `gas_specified` plus the stipend when value is sent, and the gas consumed by the
+/// precompile. When the forwarded gas is not enough to run the precompile, all of it is consumed.
+pub fn call_gas_for_precompile(
+    available_gas: u64,
+    gas_specified: Word,
+    has_value: bool,
+    precompile: PrecompileCalls,
+    input: &[u8],
+) -> Result<(u64, u64), Error> {
+    let stipend = if has_value {
+        GAS_STIPEND_CALL_WITH_VALUE
+    } else {
+        0
+    };
+    let forwarded = eip150_gas(available_gas, gas_specified) + stipend;
+    let (_, precompile_cost, _) = execute_precompiled(&precompile.into(), input, forwarded)?;
+    Ok((forwarded, precompile_cost))
+}
+
 /// Addresses of the precompiled contracts.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum PrecompileCalls {
@@ -121,14 +289,26 @@ impl From<u8> for PrecompileCalls {
 impl PrecompileCalls {
     /// Get the base gas cost for the precompile call.
     pub fn base_gas_cost(&self) -> u64 {
+        self.base_gas_cost_in(Hardfork::default())
+    }
+
+    /// Get the base gas cost for the precompile call at `hardfork`. The cost is the one of the
+    /// first hard fork with the precompile when it doesn't exist yet at `hardfork`.
+    pub fn base_gas_cost_in(&self, hardfork: Hardfork) -> u64 {
+        let before_istanbul = hardfork < Hardfork::Istanbul;
         match self {
             Self::ECRecover => GasCost::PRECOMPILE_ECRECOVER_BASE,
             Self::Sha256 => GasCost::PRECOMPILE_SHA256_BASE,
             Self::Ripemd160 => GasCost::PRECOMPILE_RIPEMD160_BASE,
             Self::Identity => GasCost::PRECOMPILE_IDENTITY_BASE,
+            // The modexp of EIP-198 has no minimum cost
+            Self::Modexp if hardfork < Hardfork::Berlin => 0,
             Self::Modexp => GasCost::PRECOMPILE_MODEXP,
+            Self::Bn128Add if before_istanbul => GasCost::PRECOMPILE_BN256ADD_BYZANTIUM,
             Self::Bn128Add => GasCost::PRECOMPILE_BN256ADD,
+            Self::Bn128Mul if before_istanbul => GasCost::PRECOMPILE_BN256MUL_BYZANTIUM,
             Self::Bn128Mul => GasCost::PRECOMPILE_BN256MUL,
+            Self::Bn128Pairing if before_istanbul => GasCost::PRECOMPILE_BN256PAIRING_BYZANTIUM,
             Self::Bn128Pairing => GasCost::PRECOMPILE_BN256PAIRING,
             Self::Blake2F => GasCost::PRECOMPILE_BLAKE2F,
         }
@@ -139,14 +319,154 @@ impl PrecompileCalls {
         (*self).into()
     }
 
-    /// Maximum length of input bytes considered for the precompile call.
+    /// Maximum length of input bytes considered for the precompile call, longer inputs are
+    /// truncated to this length.
     pub fn input_len(&self) -> Option<usize> {
         match self {
             Self::ECRecover | Self::Bn128Add => Some(128),
             Self::Bn128Mul => Some(96),
+            Self::Blake2F => Some(213),
             _ => None,
         }
     }
+
+    /// Length of the input for the precompiles that only accept inputs of exactly this length,
+    /// inputs of any other length are invalid.
+    pub fn fixed_input_len(&self) -> Option<usize> {
+        match self {
+            Self::Blake2F => Some(213),
+            _ => None,
+        }
+    }
+
+    /// Minimum length of the input read by the precompile call. For modexp this is the header of
+    /// the three 32-byte lengths, a shorter input is right padded with zeros.
+    pub fn min_input_len(&self) -> Option<usize> {
+        match self {
+            Self::Modexp => Some(96),
+            _ => self.fixed_input_len(),
+        }
+    }
+}
+
+/// Auxiliary data of a precompile call: the witness its execution gadget needs on top of the
+/// input and output bytes of the call.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PrecompileAuxData {
+    /// Modular exponentiation
+    Modexp(ModexpAuxData),
+    /// Blake2 compression function
+    Blake2F(Blake2fAuxData),
+}
+
+/// Input and output of a call to modexp, with the input split according to EIP-198: a header of
+/// three 32-byte big-endian lengths followed by the base, the exponent and the modulus.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ModexpAuxData {
+    /// Lengths of the base, the exponent and the modulus declared in the header
+    pub input_lens: [usize; 3],
+    /// Base, `input_lens[0]` bytes
+    pub base: Vec<u8>,
+    /// Exponent, `input_lens[1]` bytes
+    pub exponent: Vec<u8>,
+    /// Modulus, `input_lens[2]` bytes
+    pub modulus: Vec<u8>,
+    /// `base^exponent % modulus`, empty when the modulus length is zero
+    pub output: Vec<u8>,
+}
+
+impl ModexpAuxData {
+    /// Longest base, exponent or modulus that is parsed, in bytes
+    pub const MAX_LEN: usize = 1024;
+
+    /// Parse the `input` of a call to modexp which returned `output`.
+    ///
+    /// Like the precompile, the input is read as if it was followed by zeros: a truncated header
+    /// declares zero lengths for the missing fields, and a base, exponent or modulus cut short is
+    /// zero-padded to its declared length.
+    ///
+    /// Returns an error when a declared length is above [`Self::MAX_LEN`], before allocating it.
+    pub fn new(input: &[u8], output: &[u8]) -> Result<Self, Error> {
+        let read = |offset: usize, len: usize| {
+            let mut bytes = vec![0u8; len];
+            if offset < input.len() {
+                let end = input.len().min(offset + len);
+                bytes[..end - offset].copy_from_slice(&input[offset..end]);
+            }
+            bytes
+        };
+
+        let input_lens = [0, 32, 64].map(|offset| Word::from_big_endian(&read(offset, 32)));
+        if input_lens
+            .iter()
+            .any(|len| *len > Word::from(Self::MAX_LEN))
+        {
+            return Err(Error::InvalidPrecompileInput(
+                "modexp length is above ModexpAuxData::MAX_LEN",
+            ));
+        }
+        let input_lens = input_lens.map(|len| len.as_usize());
+        let [base_len, exp_len, mod_len] = input_lens;
+
+        Ok(Self {
+            input_lens,
+            base: read(96, base_len),
+            exponent: read(96 + base_len, exp_len),
+            modulus: read(96 + base_len + exp_len, mod_len),
+            output: output.to_vec(),
+        })
+    }
+}
+
+/// Input of a call to blake2f, split according to EIP-152.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Blake2fAuxData {
+    /// Number of rounds
+    pub rounds: u32,
+    /// State vector
+    pub h: [u64; 8],
+    /// Message block vector
+    pub m: [u64; 16],
+    /// Offset counters
+    pub t: [u64; 2],
+    /// Final block indicator flag
+    pub f: bool,
+}
+
+impl Blake2fAuxData {
+    /// Parse the `input` of a call to blake2f: the number of rounds as a 4-byte big-endian
+    /// integer, followed by the 8-byte little-endian words of `h`, `m` and `t`, and the flag `f`.
+    ///
+    /// Returns an error when the input is not exactly 213 bytes or `f` is neither 0 nor 1, the
+    /// call fails in both cases.
+    pub fn new(input: &[u8]) -> Result<Self, Error> {
+        if Some(input.len()) != PrecompileCalls::Blake2F.fixed_input_len() {
+            return Err(Error::InvalidPrecompileInput(
+                "blake2f input is not 213 bytes",
+            ));
+        }
+        let f = match input[212] {
+            0 => false,
+            1 => true,
+            _ => {
+                return Err(Error::InvalidPrecompileInput(
+                    "blake2f final block flag is not 0 or 1",
+                ))
+            }
+        };
+        let word = |idx: usize| {
+            let offset = 4 + 8 * idx;
+            u64::from_le_bytes(input[offset..offset + 8].try_into().unwrap())
+        };
+
+        Ok(Self {
+            rounds: u32::from_be_bytes(input[..4].try_into().unwrap()),
+            h: std::array::from_fn(word),
+            m: std::array::from_fn(|idx| word(8 + idx)),
+            t: std::array::from_fn(|idx| word(24 + idx)),
+            f,
+        })
+    }
 }
 
 /// Precompile call args
@@ -220,3 +540,495 @@ impl PrecompileCallArgs {
         code
     }
 }
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    const GAS: u64 = 1_000_000;
+
+    fn modexp_input(base: &[u8], exp: &[u8], modulus: &[u8]) -> Vec<u8> {
+        let mut input = vec![];
+        for len in [base.len(), exp.len(), modulus.len()] {
+            let mut word = [0u8; 32];
+            Word::from(len).to_big_endian(&mut word);
+            input.extend_from_slice(&word);
+        }
+        input.extend_from_slice(base);
+        input.extend_from_slice(exp);
+        input.extend_from_slice(modulus);
+        input
+    }
+
+    fn blake2f_input(rounds: u32) -> Vec<u8> {
+        let mut input = rounds.to_be_bytes().to_vec();
+        // h, m, t
+        input.extend_from_slice(&[0u8; 64 + 128 + 16]);
+        // f
+        input.push(1);
+        input
+    }
+
+    #[test]
+    fn precompile_gas_cost() {
+        // secp256k1 field modulus, EIP-198 example 1 computes 3^(p - 1) mod p
+        let p = hex::decode("fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f")
+            .unwrap();
+        let mut p_minus_one = p.clone();
+        p_minus_one[31] -= 1;
+
+        let cases: Vec<(PrecompileCalls, Vec<u8>, u64)> = vec![
+            // ecRecover has a fixed cost
+            (PrecompileCalls::ECRecover, vec![], 3000),
+            (PrecompileCalls::ECRecover, vec![1; 128], 3000),
+            (PrecompileCalls::ECRecover, vec![1; 200], 3000),
+            // 60 + 12 * words
+            (PrecompileCalls::Sha256, vec![], 60),
+            (PrecompileCalls::Sha256, vec![1; 1], 72),
+            (PrecompileCalls::Sha256, vec![1; 32], 72),
+            (PrecompileCalls::Sha256, vec![1; 33], 84),
+            // 600 + 120 * words
+            (PrecompileCalls::Ripemd160, vec![], 600),
+            (PrecompileCalls::Ripemd160, vec![1; 32], 720),
+            (PrecompileCalls::Ripemd160, vec![1; 65], 960),
+            // 15 + 3 * words
+            (PrecompileCalls::Identity, vec![], 15),
+            (PrecompileCalls::Identity, vec![1; 1], 18),
+            (PrecompileCalls::Identity, vec![1; 64], 21),
+            (PrecompileCalls::Identity, vec![1; 1024], 111),
+            // EIP-2565: max(200, ceil(max(len(B), len(M)) / 8)^2 * max(1, bits(E) - 1) / 3)
+            (PrecompileCalls::Modexp, vec![], 200),
+            (
+                PrecompileCalls::Modexp,
+                modexp_input(&[3], &[0], &[1; 32]),
+                200,
+            ),
+            // nagydani_1_square
+            (
+                PrecompileCalls::Modexp,
+                modexp_input(&[0xff; 64], &[2], &[0xff; 64]),
+                200,
+            ),
+            // nagydani_1_pow0x10001
+            (
+                PrecompileCalls::Modexp,
+                modexp_input(&[0xff; 64], &[1, 0, 1], &[0xff; 64]),
+                341,
+            ),
+            // nagydani_2_pow0x10001
+            (
+                PrecompileCalls::Modexp,
+                modexp_input(&[0xff; 128], &[1, 0, 1], &[0xff; 128]),
+                1365,
+            ),
+            // eip_example1
+            (
+                PrecompileCalls::Modexp,
+                modexp_input(&[3], &p_minus_one, &p),
+                1360,
+            ),
+            // ecAdd and ecMul have a fixed cost, missing input is zero padded
+            (PrecompileCalls::Bn128Add, vec![], 150),
+            (PrecompileCalls::Bn128Add, vec![0; 128], 150),
+            (PrecompileCalls::Bn128Mul, vec![], 6000),
+            (PrecompileCalls::Bn128Mul, vec![0; 96], 6000),
+            // 45000 + 34000 * pairs, using points at infinity
+            (PrecompileCalls::Bn128Pairing, vec![], 45000),
+            (PrecompileCalls::Bn128Pairing, vec![0; 192], 79000),
+            (PrecompileCalls::Bn128Pairing, vec![0; 2 * 192], 113000),
+            // one gas per round
+            (PrecompileCalls::Blake2F, blake2f_input(0), 0),
+            (PrecompileCalls::Blake2F, blake2f_input(12), 12),
+            // invalid input length consumes all the gas
+            (PrecompileCalls::Blake2F, vec![], GAS),
+        ];
+
+        for (precompile, input, expected_gas) in cases {
+            let (_, gas_cost, failure) =
+                execute_precompiled(&precompile.into(), &input, GAS).unwrap();
+            assert_ne!(
+                failure,
+                Some(PrecompileFailure::OutOfGas),
+                "{precompile:?} with input len {}",
+                input.len()
+            );
+            assert_eq!(
+                gas_cost,
+                expected_gas,
+                "{precompile:?} with input len {}",
+                input.len()
+            );
+        }
+    }
+
+    #[test]
+    fn precompile_oog_consumes_forwarded_gas() {
+        // secp256k1 field modulus, eip_example1 costs 1360
+        let p = hex::decode("fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f")
+            .unwrap();
+        let mut p_minus_one = p.clone();
+        p_minus_one[31] -= 1;
+        let input = modexp_input(&[3], &p_minus_one, &p);
+        let modexp = |forwarded_gas| {
+            execute_precompiled(&PrecompileCalls::Modexp.into(), &input, forwarded_gas).unwrap()
+        };
+
+        let (return_data, gas_cost, failure) = modexp(1360);
+        assert_eq!((return_data.len(), gas_cost, failure), (32, 1360, None));
+
+        for forwarded_gas in [1359, 200, 0] {
+            assert_eq!(
+                modexp(forwarded_gas),
+                (vec![], forwarded_gas, Some(PrecompileFailure::OutOfGas))
+            );
+        }
+    }
+
+    #[test]
+    fn modexp_aux_data() {
+        let modexp = |input: &[u8]| {
+            let (output, _, _) =
+                execute_precompiled(&PrecompileCalls::Modexp.into(), input, GAS).unwrap();
+            ModexpAuxData::new(input, &output).unwrap()
+        };
+
+        // 3^5 % 7 = 5
+        let aux_data = modexp(&modexp_input(&[3], &[5], &[7]));
+        assert_eq!(aux_data.input_lens, [1, 1, 1]);
+        assert_eq!(
+            (
+                aux_data.base,
+                aux_data.exponent,
+                aux_data.modulus,
+                aux_data.output
+            ),
+            (vec![3], vec![5], vec![7], vec![5])
+        );
+
+        // The modulus is cut short and zero-padded: 2^3 % 0x0700 = 8
+        let mut input = modexp_input(&[2], &[3], &[7, 0]);
+        input.pop();
+        let aux_data = modexp(&input);
+        assert_eq!(aux_data.input_lens, [1, 1, 2]);
+        assert_eq!(
+            (aux_data.modulus, aux_data.output),
+            (vec![7, 0], vec![0, 8])
+        );
+
+        // All lengths are zero
+        let aux_data = modexp(&modexp_input(&[], &[], &[]));
+        assert_eq!(aux_data, ModexpAuxData::new(&[0; 96], &[]).unwrap());
+        assert_eq!(aux_data.input_lens, [0, 0, 0]);
+        assert!(aux_data.output.is_empty());
+
+        // The header is truncated, the modulus length is missing and read as zero
+        let input = &modexp_input(&[2], &[3], &[])[..64];
+        let aux_data = modexp(input);
+        assert_eq!(aux_data.input_lens, [1, 1, 0]);
+        assert_eq!(
+            (
+                aux_data.base,
+                aux_data.exponent,
+                aux_data.modulus,
+                aux_data.output
+            ),
+            (vec![0], vec![0], vec![], vec![])
+        );
+        // Without any input all lengths are zero
+        assert_eq!(modexp(&[]), ModexpAuxData::default());
+
+        // Declared lengths above the maximum are rejected without being allocated
+        let header = |lens: [Word; 3]| {
+            let mut input = vec![0u8; 96];
+            for (len, bytes) in lens.iter().zip(input.chunks_mut(32)) {
+                len.to_big_endian(bytes);
+            }
+            input
+        };
+        let max_len = Word::from(ModexpAuxData::MAX_LEN);
+        let aux_data = ModexpAuxData::new(&header([max_len, 1.into(), 1.into()]), &[]).unwrap();
+        assert_eq!(aux_data.base.len(), ModexpAuxData::MAX_LEN);
+        for lens in [
+            [max_len + 1, 1.into(), 1.into()],
+            [1.into(), Word::from(u32::MAX), 1.into()],
+            [1.into(), 1.into(), Word::MAX],
+        ] {
+            assert!(matches!(
+                ModexpAuxData::new(&header(lens), &[]),
+                Err(Error::InvalidPrecompileInput(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn blake2f_aux_data() {
+        // Test vectors of EIP-152, built from the rounds and the final block flag
+        let input = |rounds: &[u8], f: u8| {
+            let h = "48c9bdf267e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5\
+                     d182e6ad7f520e511f6c3e2b8c68059b6bbd41fbabd9831f79217e1319cde05b";
+            let mut input = rounds.to_vec();
+            input.extend(hex::decode(h).unwrap());
+            // m is "abc", t is 3
+            input.extend_from_slice(b"abc");
+            input.extend_from_slice(&[0; 125]);
+            input.extend_from_slice(&[3, 0, 0, 0, 0, 0, 0, 0]);
+            input.extend_from_slice(&[0; 8]);
+            input.push(f);
+            input
+        };
+        let blake2f = |input: &[u8]| {
+            let (output, _, _) =
+                execute_precompiled(&PrecompileCalls::Blake2F.into(), input, GAS).unwrap();
+            (Blake2fAuxData::new(input), output)
+        };
+
+        // Vectors 0 to 3 are invalid: wrong length or final block flag
+        for invalid in [
+            vec![],
+            input(&[0, 0, 12], 1),
+            input(&[0, 0, 0, 0, 12], 1),
+            input(&[0, 0, 0, 12], 2),
+        ] {
+            let (aux_data, output) = blake2f(&invalid);
+            assert!(aux_data.is_err(), "input len {}", invalid.len());
+            assert!(output.is_empty());
+        }
+
+        // Vector 5 is the compression of the single block "abc" with BLAKE2b-512
+        let (aux_data, output) = blake2f(&input(&[0, 0, 0, 12], 1));
+        let aux_data = aux_data.unwrap();
+        assert_eq!(aux_data.rounds, 12);
+        assert_eq!(aux_data.h[0], 0x6a09e667f2bdc948);
+        assert_eq!(aux_data.h[7], 0x5be0cd19137e2179);
+        assert_eq!(aux_data.m[0], 0x636261);
+        assert!(aux_data.m[1..].iter().all(|word| *word == 0));
+        assert_eq!(aux_data.t, [3, 0]);
+        assert!(aux_data.f);
+        assert_eq!(
+            hex::encode(output),
+            "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d1\
+             7d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923"
+        );
+
+        // Vectors 4, 6, 7 and 8
+        for (rounds, f) in [(0, true), (12, false), (1, true), (u32::MAX, true)] {
+            let aux_data = Blake2fAuxData::new(&input(&rounds.to_be_bytes(), f as u8)).unwrap();
+            assert_eq!((aux_data.rounds, aux_data.f), (rounds, f));
+        }
+    }
+
+    #[test]
+    fn precompile_call_gas() {
+        let input = [1; 128];
+        let ecrecover = |available_gas, gas_specified: u64, has_value| {
+            call_gas_for_precompile(
+                available_gas,
+                gas_specified.into(),
+                has_value,
+                PrecompileCalls::ECRecover,
+                &input,
+            )
+            .unwrap()
+        };
+
+        // Ample gas: all but one 64th of the available gas is forwarded
+        assert_eq!(ecrecover(100_000, u64::MAX, false), (98_438, 3000));
+        // The stipend is added when value is sent
+        assert_eq!(ecrecover(100_000, u64::MAX, true), (100_738, 3000));
+        // The gas argument caps the forwarded gas
+        assert_eq!(ecrecover(100_000, 3000, false), (3000, 3000));
+        assert_eq!(
+            call_gas_for_precompile(
+                100_000,
+                Word::MAX,
+                false,
+                PrecompileCalls::ECRecover,
+                &input
+            )
+            .unwrap(),
+            (98_438, 3000)
+        );
+
+        // Insufficient gas: the precompile consumes all the forwarded gas
+        assert_eq!(ecrecover(100_000, 2999, false), (2999, 2999));
+        assert_eq!(ecrecover(2000, u64::MAX, false), (1969, 1969));
+        // The stipend alone is not enough either
+        assert_eq!(ecrecover(0, u64::MAX, true), (2300, 2300));
+    }
+
+    #[test]
+    fn precompile_input_len() {
+        use PrecompileCalls::*;
+
+        for (precompile, input_len, fixed_input_len, min_input_len) in [
+            (ECRecover, Some(128), None, None),
+            (Sha256, None, None, None),
+            (Ripemd160, None, None, None),
+            (Identity, None, None, None),
+            (Modexp, None, None, Some(96)),
+            (Bn128Add, Some(128), None, None),
+            (Bn128Mul, Some(96), None, None),
+            (Bn128Pairing, None, None, None),
+            (Blake2F, Some(213), Some(213), Some(213)),
+        ] {
+            assert_eq!(
+                (
+                    precompile.input_len(),
+                    precompile.fixed_input_len(),
+                    precompile.min_input_len()
+                ),
+                (input_len, fixed_input_len, min_input_len),
+                "{precompile:?}"
+            );
+        }
+
+        // The fixed length is the only valid one
+        assert_eq!(blake2f_input(12).len(), 213);
+        let (_, gas_cost, failure) =
+            execute_precompiled(&Blake2F.into(), &blake2f_input(12)[..212], GAS).unwrap();
+        assert_eq!(gas_cost, GAS);
+        assert_eq!(
+            failure,
+            Some(PrecompileFailure::Revm(PrecompileError::Blake2WrongLength))
+        );
+        // The header of modexp is the minimum input
+        assert_eq!(modexp_input(&[], &[], &[]).len(), 96);
+    }
+
+    #[test]
+    fn precompile_base_gas_cost() {
+        // The base cost is the cost of a call with empty input.
+        for address in 1..=9u8 {
+            let precompile = PrecompileCalls::from(address);
+            if precompile == PrecompileCalls::Blake2F {
+                // empty input is invalid
+                continue;
+            }
+            let (_, gas_cost, _) = execute_precompiled(&precompile.into(), &[], GAS).unwrap();
+            assert_eq!(gas_cost, precompile.base_gas_cost(), "{precompile:?}");
+        }
+    }
+
+    #[cfg(feature = "wasm-precompiles")]
+    #[test]
+    fn wasm_precompiles_match_revm() {
+        let message = eth_types::keccak256(b"wasm precompiles");
+        let secret_key = libsecp256k1::SecretKey::parse(&[7; 32]).unwrap();
+        let (signature, recovery_id) =
+            libsecp256k1::sign(&libsecp256k1::Message::parse(&message), &secret_key);
+        let ecrecover_input = |v: u8, signature: &[u8]| {
+            let mut input = message.to_vec();
+            input.extend_from_slice(&[[0; 31].as_slice(), &[v]].concat());
+            input.extend_from_slice(signature);
+            input
+        };
+        let signature = signature.serialize();
+        let v = 27 + recovery_id.serialize();
+
+        let cases = [
+            (PrecompileCalls::ECRecover, ecrecover_input(v, &signature)),
+            // the input is right padded with zeros, and truncated to 128 bytes
+            (
+                PrecompileCalls::ECRecover,
+                ecrecover_input(v, &signature[..40]),
+            ),
+            (
+                PrecompileCalls::ECRecover,
+                [ecrecover_input(v, &signature), vec![1; 10]].concat(),
+            ),
+            // invalid v, and invalid signature
+            (PrecompileCalls::ECRecover, ecrecover_input(29, &signature)),
+            (PrecompileCalls::ECRecover, ecrecover_input(v, &[0; 64])),
+            (PrecompileCalls::Sha256, vec![]),
+            (PrecompileCalls::Sha256, vec![0xab; 100]),
+            (PrecompileCalls::Identity, vec![]),
+            (PrecompileCalls::Identity, vec![0xab; 100]),
+        ];
+        for (precompile, input) in cases {
+            for forwarded_gas in [GAS, precompile.base_gas_cost(), 10] {
+                assert_eq!(
+                    wasm::execute(precompile, &input, forwarded_gas),
+                    Some(execute_precompiled(&precompile.into(), &input, forwarded_gas).unwrap()),
+                    "{precompile:?} with input len {} and gas {forwarded_gas}",
+                    input.len()
+                );
+            }
+        }
+
+        assert_eq!(wasm::execute(PrecompileCalls::Modexp, &[], GAS), None);
+    }
+
+    #[test]
+    fn execute_not_precompiled() {
+        // 0x0a is the point evaluation precompile of Cancun, not a Berlin precompile
+        for address in [Address::zero(), Address::from_low_u64_be(0x0a)] {
+            assert!(matches!(
+                execute_precompiled(&address, &[], GAS),
+                Err(Error::NotPrecompiled(not_precompiled)) if not_precompiled == address
+            ));
+        }
+    }
+
+    #[test]
+    fn precompiles_in_hardfork() {
+        let modexp = PrecompileCalls::Modexp.into();
+        let blake2f = PrecompileCalls::Blake2F.into();
+        assert!(!is_precompiled_in(&modexp, Hardfork::Homestead));
+        assert!(is_precompiled_in(&modexp, Hardfork::Byzantium));
+        assert!(!is_precompiled_in(&blake2f, Hardfork::Byzantium));
+        assert!(is_precompiled_in(&blake2f, Hardfork::Istanbul));
+        assert!(matches!(
+            execute_precompiled_in(&blake2f, &blake2f_input(1), GAS, Hardfork::Byzantium),
+            Err(Error::NotPrecompiled(_))
+        ));
+
+        for hardfork in [
+            Hardfork::Homestead,
+            Hardfork::Byzantium,
+            Hardfork::Istanbul,
+            Hardfork::Berlin,
+            Hardfork::London,
+        ] {
+            let count = (1..=9u64)
+                .filter(|address| is_precompiled_in(&Address::from_low_u64_be(*address), hardfork))
+                .count();
+            assert_eq!(count as u64, hardfork.precompile_count(), "{hardfork:?}");
+        }
+    }
+
+    #[test]
+    fn modexp_gas_in_hardfork() {
+        let modexp = PrecompileCalls::Modexp.into();
+        let gas = |input: &[u8], hardfork| {
+            let (_, gas_cost, failure) =
+                execute_precompiled_in(&modexp, input, GAS, hardfork).unwrap();
+            assert_eq!(failure, None);
+            gas_cost
+        };
+
+        // EIP-198 rounds down to 0, EIP-2565 charges its minimum
+        let input = modexp_input(&[3], &[5], &[7]);
+        assert_eq!(gas(&input, Hardfork::Istanbul), 0);
+        assert_eq!(
+            gas(&input, Hardfork::Berlin),
+            GasCost::PRECOMPILE_MODEXP_MIN
+        );
+
+        // 64 byte modulus and 255 iterations: 64^2 * 255 / 20 before Berlin, and
+        // (64 / 8)^2 * 255 / 3 from Berlin
+        let input = modexp_input(&[3], &[0xff; 32], &[0xff; 64]);
+        assert_eq!(gas(&input, Hardfork::Byzantium), 52224);
+        assert_eq!(gas(&input, Hardfork::Istanbul), 52224);
+        assert_eq!(gas(&input, Hardfork::Berlin), 5440);
+        assert_eq!(gas(&input, Hardfork::London), 5440);
+
+        assert_eq!(
+            PrecompileCalls::Modexp.base_gas_cost_in(Hardfork::Istanbul),
+            0
+        );
+        assert_eq!(
+            PrecompileCalls::Modexp.base_gas_cost(),
+            PrecompileCalls::Modexp.base_gas_cost_in(Hardfork::Berlin)
+        );
+    }
+}
@@ -22,7 +22,9 @@ mod tests {
     use rand::SeedableRng;
     use rand_xorshift::XorShiftRng;
     use std::{env::var, ops::Deref};
-    use zkevm_circuits::mpt_circuit::{load_proof_from_file, witness_row::Node, MPTCircuit};
+    use zkevm_circuits::mpt_circuit::{
+        load_proof_from_file, witness_row::Node, MPTCircuit, MPTRandomness,
+    };
 
     #[cfg_attr(not(feature = "benches"), ignore)]
     #[test]
@@ -55,6 +57,8 @@ mod tests {
             degree: degree as usize,
             max_nodes,
             disable_preimage_check: false,
+            disable_storage: false,
+            randomness: MPTRandomness::Challenge,
             _marker: PhantomData,
         };
 
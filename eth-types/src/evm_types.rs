@@ -148,6 +148,12 @@ impl GasCost {
     pub const PRECOMPILE_BN256PAIRING: u64 = 45000;
     /// Per-pair gas cost for BN256 pairing
     pub const PRECOMPILE_BN256PAIRING_PER_PAIR: u64 = 34000;
+    /// Base gas cost for precompile call before Istanbul (EIP-1108): BN256 point addition
+    pub const PRECOMPILE_BN256ADD_BYZANTIUM: u64 = 500;
+    /// Base gas cost for precompile call before Istanbul (EIP-1108): BN256 scalar multiplication
+    pub const PRECOMPILE_BN256MUL_BYZANTIUM: u64 = 40000;
+    /// Base gas cost for precompile call before Istanbul (EIP-1108): BN256 pairing op base cost
+    pub const PRECOMPILE_BN256PAIRING_BYZANTIUM: u64 = 100000;
     /// Base gas cost for precompile call: MODEXP
     pub const PRECOMPILE_MODEXP: u64 = 200; // eip255
     /// Minimum gas cost for precompile calls: MODEXP
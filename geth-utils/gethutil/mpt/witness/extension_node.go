@@ -1,5 +1,7 @@
 package witness
 
+import "github.com/ethereum/go-ethereum/rlp"
+
 func prepareExtensions(extNibbles [][]byte, extensionNodeInd int, proofEl1, proofEl2 []byte) (byte, []byte, [][]byte) {
 	var values [][]byte
 	v1 := make([]byte, valueLen)
@@ -180,3 +182,25 @@ func prepareExtension(v1, v2, proofEl []byte, setKey bool) []byte {
 
 	return listRlpBytes
 }
+
+// getProofExtNibbles returns the nibbles of the extension nodes in the proof, as returned by
+// GetProof alongside the proof.
+func getProofExtNibbles(proof [][]byte) [][]byte {
+	var extNibbles [][]byte
+	for _, proofEl := range proof {
+		if isBranch(proofEl) {
+			continue
+		}
+		elems, _, err := rlp.SplitList(proofEl)
+		check(err)
+		key, _, err := rlp.SplitString(elems)
+		check(err)
+		// The terminator flag in the first nibble of the key is set for leaves
+		if key[0] >= 32 {
+			continue
+		}
+		extNibbles = append(extNibbles, getExtensionNodeNibbles(proofEl))
+	}
+
+	return extNibbles
+}
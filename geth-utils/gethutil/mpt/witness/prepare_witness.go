@@ -65,6 +65,65 @@ func GetWitness(nodeUrl string, blockNum int, trieModifications []TrieModificati
 	return obtainTwoProofsAndConvertToWitness(trieModifications, statedb, 0)
 }
 
+// StorageProof is a storage proof of an eth_getProof response.
+type StorageProof struct {
+	Key   common.Hash
+	Value common.Hash
+	Proof [][]byte
+}
+
+// GetWitnessFromProof is to be used by external programs to generate the witness from an
+// eth_getProof response instead of fetching the proofs from a node. The state is not modified,
+// the proof before and after the modification is the proof of the response. A witness is
+// prepared for each of the storage proofs, or for the account only when there are none.
+// When the account doesn't exist, the storage proofs are ignored.
+func GetWitnessFromProof(addr common.Address, accountProof [][]byte, accountExists bool, storageProofs []StorageProof) []Node {
+	addrh := crypto.Keccak256(addr.Bytes())
+	accountAddr := trie.KeybytesToHex(addrh)
+	aExtNibbles := getProofExtNibbles(accountProof)
+
+	root := crypto.Keccak256Hash(accountProof[0])
+
+	accountWitness := func(proofType string, key common.Hash) []Node {
+		nodes := []Node{GetStartNode(proofType, root, root, 0)}
+		nodesAccount :=
+			convertProofToWitness(nil, addr, addrh, accountProof, accountProof, aExtNibbles, aExtNibbles, key, accountAddr, nil, true, !accountExists, false, false)
+		return append(nodes, nodesAccount...)
+	}
+
+	var nodes []Node
+	if !accountExists {
+		nodes = append(nodes, accountWitness("AccountDoesNotExist", common.Hash{})...)
+		nodes = append(nodes, GetEndNode())
+		return nodes
+	}
+	if len(storageProofs) == 0 {
+		nodes = append(nodes, accountWitness("NonceChanged", common.Hash{})...)
+		nodes = append(nodes, GetEndNode())
+		return nodes
+	}
+
+	for _, storageProof := range storageProofs {
+		keyHashed := trie.KeybytesToHex(crypto.Keccak256(storageProof.Key.Bytes()))
+		extNibbles := getProofExtNibbles(storageProof.Proof)
+
+		// Zero values are not stored in the storage trie
+		nonExisting := storageProof.Value == (common.Hash{})
+		proofType := "StorageChanged"
+		if nonExisting {
+			proofType = "StorageDoesNotExist"
+		}
+
+		nodes = append(nodes, accountWitness(proofType, storageProof.Key)...)
+		nodesStorage :=
+			convertProofToWitness(nil, addr, addrh, storageProof.Proof, storageProof.Proof, extNibbles, extNibbles, storageProof.Key, keyHashed, nil, false, false, nonExisting, false)
+		nodes = append(nodes, nodesStorage...)
+		nodes = append(nodes, GetEndNode())
+	}
+
+	return nodes
+}
+
 func obtainAccountProofAndConvertToWitness(i int, tMod TrieModification, tModsLen int, statedb *state.StateDB, specialTest byte) []Node {
 	statedb.IntermediateRoot(false)
 
@@ -10,6 +10,8 @@ import (
 	"main/gethutil"
 	"main/gethutil/mpt/witness"
 	"unsafe"
+
+	"github.com/ethereum/go-ethereum/common"
 )
 
 // TODO: Add proper error handling.  For example, return an int, where 0 means
@@ -68,6 +70,31 @@ func GetMptWitness(proofConf *C.char) *C.char {
 	return C.CString(string(b))
 }
 
+type GetWitnessFromProofRequest struct {
+	Address       common.Address
+	AccountProof  [][]byte
+	AccountExists bool
+	StorageProofs []witness.StorageProof
+}
+
+//export GetMptWitnessFromProof
+func GetMptWitnessFromProof(proofConf *C.char) *C.char {
+	var config GetWitnessFromProofRequest
+
+	err := json.Unmarshal([]byte(C.GoString(proofConf)), &config)
+	if err != nil {
+		panic(err)
+	}
+
+	proof := witness.GetWitnessFromProof(config.Address, config.AccountProof, config.AccountExists, config.StorageProofs)
+	b, err := json.Marshal(proof)
+	if err != nil {
+		fmt.Println(err)
+	}
+
+	return C.CString(string(b))
+}
+
 //export FreeString
 func FreeString(str *C.char) {
 	C.free(unsafe.Pointer(str))
@@ -11,6 +11,7 @@ mod go {
     extern "C" {
         pub fn CreateTrace(str: *const c_char) -> *const c_char;
         pub fn GetMptWitness(str: *const c_char) -> *const c_char;
+        pub fn GetMptWitnessFromProof(str: *const c_char) -> *const c_char;
         pub fn FreeString(str: *const c_char);
     }
 }
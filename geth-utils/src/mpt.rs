@@ -1,9 +1,12 @@
 use ethers::types::{Address, H256, U64};
 use serde::Serialize;
-use std::ffi::{CStr, CString};
+use std::{
+    ffi::{CStr, CString},
+    os::raw::c_char,
+};
 
 use crate::go;
-use eth_types::U256;
+use eth_types::{keccak256, EIP1186ProofResponse, U256};
 use num_enum::IntoPrimitive;
 
 #[derive(Default, Debug, IntoPrimitive, Clone, Copy)]
@@ -66,11 +69,7 @@ pub fn get_witness(block_no: u64, mods: &[TrieModification], node_url: &str) ->
         .map(|m| TrieModificationJson {
             typ: m.typ as u8,
             key: m.key,
-            value: {
-                let mut bytes = [0u8; 32];
-                m.value.to_big_endian(&mut bytes);
-                H256::from_slice(&bytes)
-            },
+            value: u256_to_h256(m.value),
             address: m.address,
             nonce: m.nonce.as_u64(),
             balance: serde_json::Number::from_string_unchecked(format!("{}", m.balance)),
@@ -85,8 +84,71 @@ pub fn get_witness(block_no: u64, mods: &[TrieModification], node_url: &str) ->
     };
 
     let json = serde_json::to_string(&req).expect("Invalid request");
+    // Note: previously this function returned a Vec of Nodes, but now returning a JSON string
+    // to avoid imporing zkEVM circuit here (that will create a circular dependency).
+    // TODO: consider defining Node types in another crate.
+    call_go(go::GetMptWitness, json)
+}
+
+#[derive(Debug, Serialize)]
+struct StorageProofJson {
+    #[serde(rename = "Key")]
+    key: H256,
+    #[serde(rename = "Value")]
+    value: H256,
+    #[serde(rename = "Proof")]
+    proof: Vec<Vec<u8>>,
+}
+
+#[derive(Debug, Serialize)]
+struct GetWitnessFromProofRequest {
+    #[serde(rename = "Address")]
+    address: Address,
+    #[serde(rename = "AccountProof")]
+    account_proof: Vec<Vec<u8>>,
+    #[serde(rename = "AccountExists")]
+    account_exists: bool,
+    #[serde(rename = "StorageProofs")]
+    storage_proofs: Vec<StorageProofJson>,
+}
+
+/// Returns the witness proving the account and the storage values of an `eth_getProof`
+/// response at the state root of the response, as JSON. The trie is not modified, there is one
+/// proof per storage value, or a single account proof when there are no storage values.
+pub fn get_witness_from_proof(proof: &EIP1186ProofResponse) -> String {
+    let account_exists = !(proof.nonce.is_zero()
+        && proof.balance.is_zero()
+        && (proof.code_hash.is_zero() || proof.code_hash.0 == keccak256(&[])));
+
+    let req = GetWitnessFromProofRequest {
+        address: proof.address,
+        account_proof: proof.account_proof.iter().map(|p| p.to_vec()).collect(),
+        account_exists,
+        storage_proofs: proof
+            .storage_proof
+            .iter()
+            .map(|p| StorageProofJson {
+                key: u256_to_h256(p.key),
+                value: u256_to_h256(p.value),
+                proof: p.proof.iter().map(|p| p.to_vec()).collect(),
+            })
+            .collect(),
+    };
+
+    let json = serde_json::to_string(&req).expect("Invalid request");
+    call_go(go::GetMptWitnessFromProof, json)
+}
+
+fn u256_to_h256(value: U256) -> H256 {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    H256::from_slice(&bytes)
+}
+
+/// Calls the Go function `f` with `json` and returns its result.
+fn call_go(f: unsafe extern "C" fn(*const c_char) -> *const c_char, json: String) -> String {
     let c_config = CString::new(json).expect("invalid config");
-    let result = unsafe { go::GetMptWitness(c_config.as_ptr() as *const i8) };
+    let result = unsafe { f(c_config.as_ptr()) };
     let c_str = unsafe { CStr::from_ptr(result) };
     let json = c_str
         .to_str()
@@ -95,9 +157,5 @@ pub fn get_witness(block_no: u64, mods: &[TrieModification], node_url: &str) ->
 
     unsafe { go::FreeString(c_str.as_ptr()) };
 
-    // Note: previously this function returned a Vec of Nodes, but now returning a JSON string
-    // to avoid imporing zkEVM circuit here (that will create a circular dependency).
-    // TODO: consider defining Node types in another crate.
-
     json
 }
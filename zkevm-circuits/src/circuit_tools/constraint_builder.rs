@@ -1,6 +1,6 @@
 //! Circuit utilities
 use std::{
-    collections::HashMap,
+    collections::{BTreeSet, HashMap},
     marker::PhantomData,
     ops::{Add, Mul},
     vec,
@@ -622,6 +622,37 @@ impl<F: Field, C: CellType> ConstraintBuilder<F, C> {
             println!("'{}': {}", name, expr.degree());
         }
     }
+
+    /// Returns the rotations queried by each constraint, useful to debug which rows a
+    /// constraint reaches.
+    pub(crate) fn rotations(&self) -> Vec<(&'static str, BTreeSet<i32>)> {
+        self.constraints
+            .iter()
+            .map(|(name, expr)| (*name, query_rotations(expr)))
+            .collect()
+    }
+}
+
+/// Returns all the rotations at which `expr` queries a fixed, advice or instance column.
+pub(crate) fn query_rotations<F: Field>(expr: &Expression<F>) -> BTreeSet<i32> {
+    expr.evaluate(
+        &|_| BTreeSet::new(),
+        &|_| BTreeSet::new(),
+        &|fixed_query| BTreeSet::from([fixed_query.rotation().0]),
+        &|advice_query| BTreeSet::from([advice_query.rotation().0]),
+        &|instance_query| BTreeSet::from([instance_query.rotation().0]),
+        &|_| BTreeSet::new(),
+        &|a| a,
+        &|mut a, b| {
+            a.extend(b);
+            a
+        },
+        &|mut a, b| {
+            a.extend(b);
+            a
+        },
+        &|a, _| a,
+    )
 }
 
 /// General trait to convert to a vec
@@ -147,17 +147,19 @@ impl<F: Field> SubCircuitConfig<F> for EvmCircuitConfig<F> {
 }
 
 impl<F: Field> EvmCircuitConfig<F> {
-    /// Load fixed table
+    /// Load fixed table, with the rows of `fixed_table_tags` followed by `extra_entries`
     pub fn load_fixed_table(
         &self,
         layouter: &mut impl Layouter<F>,
         fixed_table_tags: Vec<FixedTableTag>,
+        extra_entries: &[[F; 4]],
     ) -> Result<(), Error> {
         layouter.assign_region(
             || "fixed table",
             |mut region| {
                 for (offset, row) in std::iter::once([F::ZERO; 4])
                     .chain(fixed_table_tags.iter().flat_map(|tag| tag.build()))
+                    .chain(extra_entries.iter().copied())
                     .enumerate()
                 {
                     for (column, value) in self.fixed_table.iter().zip_eq(row) {
@@ -177,6 +179,8 @@ pub struct EvmCircuit<F: Field> {
     /// Block
     pub block: Option<Block<F>>,
     fixed_table_tags: Vec<FixedTableTag>,
+    // Rows loaded in the fixed table on top of the ones of the tags, only set by tests
+    extra_fixed_entries: Vec<[F; 4]>,
 }
 
 impl<F: Field> EvmCircuit<F> {
@@ -185,6 +189,7 @@ impl<F: Field> EvmCircuit<F> {
         Self {
             block: Some(block),
             fixed_table_tags: FixedTableTag::iter().collect(),
+            extra_fixed_entries: vec![],
         }
     }
     #[cfg(any(test, feature = "test-circuits"))]
@@ -195,9 +200,17 @@ impl<F: Field> EvmCircuit<F> {
         Self {
             block: Some(block),
             fixed_table_tags,
+            extra_fixed_entries: vec![],
         }
     }
     #[cfg(any(test, feature = "test-circuits"))]
+    /// Load `entries` in the fixed table after the rows of the fixed table tags, so that a new
+    /// gadget can be tested with its fixed table lookup before the lookup gets its own tag.
+    pub(crate) fn with_extra_fixed_entries(mut self, entries: Vec<[F; 4]>) -> Self {
+        self.extra_fixed_entries = entries;
+        self
+    }
+    #[cfg(any(test, feature = "test-circuits"))]
     /// Calculate which rows are "actually" used in the circuit
     pub(crate) fn get_active_rows(block: &Block<F>) -> (Vec<usize>, Vec<usize>) {
         let max_offset = Self::get_num_rows_required(block);
@@ -270,7 +283,11 @@ impl<F: Field> SubCircuit<F> for EvmCircuit<F> {
     ) -> Result<(), Error> {
         let block = self.block.as_ref().unwrap();
 
-        config.load_fixed_table(layouter, self.fixed_table_tags.clone())?;
+        config.load_fixed_table(
+            layouter,
+            self.fixed_table_tags.clone(),
+            &self.extra_fixed_entries,
+        )?;
         config.execution.assign_block(layouter, block, challenges)
     }
 }
@@ -354,6 +371,10 @@ pub(crate) mod cached {
         pub(crate) fn get_test_circuit_from_block(block: Block<Fr>) -> Self {
             Self(EvmCircuit::<Fr>::get_test_circuit_from_block(block))
         }
+
+        pub(crate) fn with_extra_fixed_entries(self, entries: Vec<[Fr; 4]>) -> Self {
+            Self(self.0.with_extra_fixed_entries(entries))
+        }
     }
 }
 
@@ -456,7 +477,11 @@ impl<F: Field> Circuit<F> for EvmCircuit<F> {
 #[cfg(test)]
 mod evm_circuit_stats {
     use crate::{
-        evm_circuit::EvmCircuit,
+        evm_circuit::{
+            step::{ExecutionState, HasExecutionState},
+            table::FixedTableTag,
+            EvmCircuit,
+        },
         test_util::CircuitTestBuilder,
         util::{unusable_rows, SubCircuit},
         witness::block_convert,
@@ -466,8 +491,9 @@ mod evm_circuit_stats {
         mock::BlockData,
     };
 
-    use eth_types::{bytecode, geth_types::GethData};
+    use eth_types::{bytecode, geth_types::GethData, word};
     use halo2_proofs::{self, dev::MockProver, halo2curves::bn256::Fr};
+    use strum::IntoEnumIterator;
 
     use mock::test_ctx::{
         helpers::{account_0_code_account_1_no_code, tx_from_1_to_0},
@@ -557,4 +583,70 @@ mod evm_circuit_stats {
         assert_eq!(prover1.fixed(), prover2.fixed());
         assert_eq!(prover1.permutation(), prover2.permutation());
     }
+
+    #[test]
+    fn iter_steps() {
+        let code = bytecode! {
+            PUSH1(0)
+            STOP
+        };
+        let block = CircuitTestBuilder::new_from_test_ctx(
+            TestContext::<2, 1>::simple_ctx_with_bytecode(code).unwrap(),
+        )
+        .build_block()
+        .unwrap();
+
+        let steps = block.iter_steps().collect::<Vec<_>>();
+        assert_eq!(
+            steps
+                .iter()
+                .map(|(_, _, step)| step.execution_state())
+                .collect::<Vec<_>>(),
+            [
+                ExecutionState::BeginTx,
+                ExecutionState::PUSH,
+                ExecutionState::STOP,
+                ExecutionState::EndTx,
+            ]
+        );
+        assert!(steps
+            .iter()
+            .all(|(tx, call, _)| std::ptr::eq(*tx, &block.txs[0]) && call.is_root));
+    }
+
+    #[test]
+    fn extra_fixed_entries() {
+        // Hypothetical lookup selecting the little-endian limb of a word that holds the byte at
+        // the big-endian index used by BYTE: [tag, byte index, limb index, 0]
+        let tag = Fr::from(FixedTableTag::iter().count() as u64 + 1);
+        let entries = (0..32u64)
+            .map(|idx| [tag, Fr::from(idx), Fr::from(31 - idx), Fr::from(0)])
+            .collect::<Vec<_>>();
+        let code = bytecode! {
+            PUSH32(word!("0x0102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f20"))
+            PUSH1(0x05)
+            BYTE
+            STOP
+        };
+        let ctx = || TestContext::<2, 1>::simple_ctx_with_bytecode(code.clone()).unwrap();
+
+        CircuitTestBuilder::new_from_test_ctx(ctx())
+            .with_extra_fixed_entries(entries.clone())
+            .run();
+
+        // The extra rows end up in the fixed table, after the rows of the tags
+        let block: GethData = ctx().into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+        let block = block_convert::<Fr>(&builder).unwrap();
+        let k = block.get_test_degree();
+        let circuit = EvmCircuit::<Fr>::get_test_circuit_from_block(block);
+        let prover1 = MockProver::<Fr>::run(k, &circuit, vec![]).unwrap();
+        let circuit = circuit.with_extra_fixed_entries(entries);
+        let prover2 = MockProver::<Fr>::run(k, &circuit, vec![]).unwrap();
+        prover2.assert_satisfied_par();
+        assert_ne!(prover1.fixed(), prover2.fixed());
+    }
 }
@@ -211,6 +211,14 @@ pub(crate) trait ExecutionGadget<F: Field> {
 
     const EXECUTION_STATE: ExecutionState;
 
+    /// Number of rw operations of a step, for the gadgets where it is the same for every step.
+    /// Checked against the witness by `assert_declared_deltas`.
+    const RW_DELTA: Option<usize> = None;
+
+    /// Stack pointer change of a step, for the gadgets where it is the same for every step.
+    /// Checked against the witness by `assert_declared_deltas`.
+    const STACK_DELTA: Option<i32> = None;
+
     fn configure(cb: &mut EVMConstraintBuilder<F>) -> Self;
 
     fn assign_exec_step(
@@ -979,13 +987,7 @@ impl<F: Field> ExecutionConfig<F> {
                 let end_block_last = &block.end_block_last;
                 // Collect all steps
                 let mut steps = block
-                    .txs
-                    .iter()
-                    .flat_map(|tx| {
-                        tx.steps()
-                            .iter()
-                            .map(move |step| (tx, &tx.calls()[step.call_index], step))
-                    })
+                    .iter_steps()
                     .chain(std::iter::once((&dummy_tx, &last_call, end_block_not_last)))
                     .peekable();
 
@@ -228,7 +228,7 @@ impl<F: Field> ExecutionGadget<F> for AddModGadget<F> {
 
 #[cfg(test)]
 mod test {
-    use crate::test_util::CircuitTestBuilder;
+    use crate::{evm_circuit::test::rand_word, test_util::CircuitTestBuilder};
     use eth_types::{bytecode, evm_types::Stack, Word};
     use mock::TestContext;
 
@@ -291,6 +291,11 @@ mod test {
         test(0.into(), 0.into(), Word::MAX, None, true);
     }
 
+    #[test]
+    fn addmod_rand() {
+        test(rand_word(), rand_word(), rand_word(), None, true);
+    }
+
     #[test]
     fn addmod_bad_r_on_nonzero_n() {
         test_ok_u32(7, 18, 10, Some(5));
@@ -132,6 +132,14 @@ mod test {
         test_ok(0x12_34_56.into(), 0x78_9A_BC.into());
     }
 
+    #[test]
+    fn bitwise_gadget_zero_and_max() {
+        test_ok(Word::zero(), Word::zero());
+        test_ok(Word::zero(), Word::MAX);
+        test_ok(Word::MAX, Word::MAX);
+        test_ok(Word::MAX, rand_word());
+    }
+
     #[test]
     fn bitwise_gadget_rand() {
         let a = rand_word();
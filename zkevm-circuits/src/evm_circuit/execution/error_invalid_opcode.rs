@@ -3,23 +3,35 @@ use crate::evm_circuit::{
     step::ExecutionState,
     table::{FixedTableTag, Lookup},
     util::{
-        common_gadget::CommonErrorGadget, constraint_builder::EVMConstraintBuilder, CachedRegion,
-        Cell,
+        common_gadget::CommonErrorGadget,
+        constraint_builder::{EVMConstraintBuilder, StepStateTransition, Transition::Delta},
+        CachedRegion, Cell,
     },
     witness::{Block, Call, ExecStep, Transaction},
 };
 use eth_types::Field;
 use gadgets::util::Expr;
+#[cfg(test)]
+use halo2_proofs::plonk::Expression;
 use halo2_proofs::{circuit::Value, plonk::Error};
 
 /// Gadget for invalid opcodes. It verifies by a fixed lookup for
-/// ResponsibleOpcode.
+/// ResponsibleOpcode, and that all the gas left is consumed.
 #[derive(Clone, Debug)]
 pub(crate) struct ErrorInvalidOpcodeGadget<F> {
     opcode: Cell<F>,
+    gas_consumed: Cell<F>,
     common_error_gadget: CommonErrorGadget<F>,
 }
 
+impl<F: Field> ErrorInvalidOpcodeGadget<F> {
+    /// Gas consumed by the invalid opcode, which is all the gas left
+    #[cfg(test)]
+    pub(crate) fn gas_consumed(&self) -> Expression<F> {
+        self.gas_consumed.expr()
+    }
+}
+
 impl<F: Field> ExecutionGadget<F> for ErrorInvalidOpcodeGadget<F> {
     const NAME: &'static str = "ErrorInvalidOpcode";
 
@@ -39,10 +51,26 @@ impl<F: Field> ExecutionGadget<F> for ErrorInvalidOpcodeGadget<F> {
             },
         );
 
+        // An invalid opcode consumes all the gas left. For an internal call no gas is returned
+        // to the caller, which is done when restoring the caller context in CommonErrorGadget.
+        let gas_consumed = cb.query_cell();
+        cb.require_equal(
+            "gas_consumed == gas_left",
+            gas_consumed.expr(),
+            cb.curr.state.gas_left.expr(),
+        );
+        cb.condition(cb.curr.state.is_root.expr(), |cb| {
+            cb.require_step_state_transition(StepStateTransition {
+                gas_left: Delta(-gas_consumed.expr()),
+                ..StepStateTransition::any()
+            });
+        });
+
         let common_error_gadget = CommonErrorGadget::construct(cb, opcode.expr(), 0.expr());
 
         Self {
             opcode,
+            gas_consumed,
             common_error_gadget,
         }
     }
@@ -58,6 +86,8 @@ impl<F: Field> ExecutionGadget<F> for ErrorInvalidOpcodeGadget<F> {
     ) -> Result<(), Error> {
         let opcode = F::from(step.opcode().unwrap().as_u64());
         self.opcode.assign(region, offset, Value::known(opcode))?;
+        self.gas_consumed
+            .assign(region, offset, Value::known(F::from(step.gas_left)))?;
 
         self.common_error_gadget
             .assign(region, offset, block, call, step, 2)?;
@@ -67,8 +97,20 @@ impl<F: Field> ExecutionGadget<F> for ErrorInvalidOpcodeGadget<F> {
 
 #[cfg(test)]
 mod test {
-    use crate::{evm_circuit::test::rand_bytes, test_util::CircuitTestBuilder};
-    use eth_types::{bytecode::Bytecode, Word};
+    use crate::{
+        evm_circuit::{
+            step::{ExecutionState, HasExecutionState},
+            test::rand_bytes,
+            EvmCircuit,
+        },
+        test_util::{constraint_failure_in_gate, CircuitTestBuilder},
+    };
+    use bus_mapping::circuit_input_builder::FeatureConfig;
+    use eth_types::{bytecode::Bytecode, evm_types::OpcodeId, Word};
+    use halo2_proofs::{
+        halo2curves::bn256::Fr,
+        plonk::{Circuit, ConstraintSystem},
+    };
     use lazy_static::lazy_static;
     use mock::{generate_mock_call_bytecode, MockCallBytecodeParams, TestContext};
 
@@ -99,6 +141,73 @@ mod test {
         }
     }
 
+    #[test]
+    #[ignore = "Runs the circuit twice for every undefined opcode byte"]
+    fn invalid_opcode_all_undefined_bytes() {
+        let invalid_bytes: Vec<u8> = (u8::MIN..=u8::MAX)
+            .filter(|byte| matches!(OpcodeId::from(*byte), OpcodeId::INVALID(_)))
+            .collect();
+        assert_eq!(
+            invalid_bytes
+                .iter()
+                .map(|byte| OpcodeId::INVALID(*byte))
+                .collect::<Vec<_>>(),
+            OpcodeId::invalid_opcodes()
+        );
+
+        for byte in invalid_bytes {
+            test_root_ok(&[byte]);
+            test_internal_ok(0x20, 0x00, &[byte]);
+        }
+    }
+
+    #[test]
+    fn invalid_opcode_partial_gas_consumption() {
+        let mut code = Bytecode::default();
+        code.write(0xfe, true);
+
+        CircuitTestBuilder::new_from_test_ctx(
+            TestContext::<2, 1>::simple_ctx_with_bytecode(code).unwrap(),
+        )
+        .block_modifier(Box::new(|block| {
+            // Claim that the invalid opcode only consumed half of the gas left, returning the
+            // other half to the transaction
+            let steps = block.txs[0].steps_mut();
+            let idx = steps
+                .iter()
+                .position(|step| step.execution_state() == ExecutionState::ErrorInvalidOpcode)
+                .unwrap();
+            assert_eq!(steps[idx + 1].execution_state(), ExecutionState::EndTx);
+            assert_eq!(steps[idx + 1].gas_left, 0);
+            steps[idx + 1].gas_left = steps[idx].gas_left / 2;
+        }))
+        // EndTx rejects the refund too, the gas left transition of the invalid opcode must fail
+        .expect_failure(constraint_failure_in_gate("ErrorInvalidOpcode"))
+    }
+
+    #[test]
+    fn invalid_opcode_gas_consumed_is_constrained() {
+        let mut meta = ConstraintSystem::<Fr>::default();
+        let (config, _) =
+            EvmCircuit::<Fr>::configure_with_params(&mut meta, FeatureConfig::default());
+        let gas_consumed = config
+            .execution
+            .error_invalid_opcode
+            .gas_consumed()
+            .identifier();
+
+        // The gas consumed is read by the constraints of the invalid opcode, which require it to
+        // be all the gas left
+        let num_constraints = meta
+            .gates()
+            .iter()
+            .filter(|gate| gate.name() == "ErrorInvalidOpcode")
+            .flat_map(|gate| gate.polynomials())
+            .filter(|poly| poly.identifier().contains(&gas_consumed))
+            .count();
+        assert!(num_constraints > 0);
+    }
+
     fn test_root_ok(invalid_code: &[u8]) {
         let mut code = Bytecode::default();
         invalid_code.iter().for_each(|b| {
@@ -233,21 +233,48 @@ impl<F: Field> ExecutionGadget<F> for ExponentiationGadget<F> {
 
 #[cfg(test)]
 mod tests {
-    use crate::test_util::CircuitTestBuilder;
-    use eth_types::{bytecode, Word};
+    use crate::{
+        evm_circuit::step::{ExecutionState, HasExecutionState},
+        test_util::{constraint_failure_in_gate, CircuitTestBuilder},
+        witness::Rw,
+    };
+    use bus_mapping::{evm::OpcodeId, operation::Target};
+    use eth_types::{bytecode, evm_types::GasCost, Word};
     use mock::TestContext;
 
-    fn test_ok(base: Word, exponent: Word) {
+    fn test(base: Word, exponent: Word, exponentiation: Option<Word>, ok: bool) {
         let code = bytecode! {
             PUSH32(exponent)
             PUSH32(base)
             EXP
             STOP
         };
-        CircuitTestBuilder::new_from_test_ctx(
+        let mut builder = CircuitTestBuilder::new_from_test_ctx(
             TestContext::<2, 1>::simple_ctx_with_bytecode(code).unwrap(),
-        )
-        .run();
+        );
+        if let Some(exponentiation) = exponentiation {
+            builder = builder.block_modifier(Box::new(move |block| {
+                // Overwrite the result pushed by EXP, which is the last stack operation.
+                let stack_rws = block.rws.0.get_mut(&Target::Stack).unwrap();
+                let last = stack_rws
+                    .iter_mut()
+                    .max_by_key(|rw| rw.rw_counter())
+                    .unwrap();
+                if let Rw::Stack { value, .. } = last {
+                    *value = exponentiation;
+                }
+            }));
+        }
+        let result = builder.run_with_result();
+        if ok {
+            assert!(result.is_ok());
+        } else {
+            result.unwrap_err().assert_evm_failure()
+        };
+    }
+
+    fn test_ok(base: Word, exponent: Word) {
+        test(base, exponent, None, true)
     }
 
     #[test]
@@ -258,6 +285,22 @@ mod tests {
         test_ok(Word::MAX, Word::zero());
     }
 
+    #[test]
+    fn exp_gadget_zero_wrong_result() {
+        // x^0 == 1 for every x, including 0^0
+        test(Word::zero(), Word::zero(), Some(Word::one()), true);
+        test(Word::zero(), Word::zero(), Some(Word::zero()), false);
+        test(0xcafeu64.into(), Word::zero(), Some(Word::zero()), false);
+        test(Word::MAX, Word::zero(), Some(Word::zero()), false);
+        test(Word::MAX, Word::zero(), Some(Word::MAX), false);
+        test(
+            0xcafeu64.into(),
+            Word::zero(),
+            Some(Word::from_str_radix("0x100000000000000000000000000000001", 16).unwrap()),
+            false,
+        );
+    }
+
     #[test]
     fn exp_gadget_one() {
         test_ok(Word::zero(), Word::one());
@@ -279,4 +322,54 @@ mod tests {
         test_ok(Word::MAX, 2.into());
         test_ok(Word::MAX, 3.into());
     }
+
+    fn exp_builder(exponent: Word) -> CircuitTestBuilder<2, 1> {
+        let code = bytecode! {
+            PUSH32(exponent)
+            PUSH32(2)
+            EXP
+            STOP
+        };
+        CircuitTestBuilder::new_from_test_ctx(
+            TestContext::<2, 1>::simple_ctx_with_bytecode(code).unwrap(),
+        )
+    }
+
+    #[test]
+    fn exp_gadget_gas_cost() {
+        // 10 + 50 per byte of the exponent
+        for (exponent, num_bytes) in [
+            (Word::zero(), 0),
+            (Word::one(), 1),
+            (0x100.into(), 2),
+            (Word::one() << 248, 32),
+        ] {
+            let builder = exp_builder(exponent);
+            let block = builder.build_block().unwrap();
+            let (_, _, step) = block
+                .iter_steps()
+                .find(|(_, _, step)| step.execution_state() == ExecutionState::EXP)
+                .unwrap();
+            assert_eq!(
+                step.gas_cost,
+                OpcodeId::EXP.constant_gas_cost() + GasCost::EXP_BYTE_TIMES * num_bytes
+            );
+            builder.run();
+        }
+    }
+
+    #[test]
+    fn exp_gadget_wrong_gas_cost() {
+        // Charge one gas less than the 32 byte exponent costs
+        exp_builder(Word::one() << 248)
+            .block_modifier(Box::new(|block| {
+                let steps = block.txs[0].steps_mut();
+                let exp_idx = steps
+                    .iter()
+                    .position(|step| step.execution_state() == ExecutionState::EXP)
+                    .unwrap();
+                steps[exp_idx + 1].gas_left += 1;
+            }))
+            .expect_failure(constraint_failure_in_gate("EXP"));
+    }
 }
@@ -8,7 +8,7 @@ use crate::{
                 ConstrainBuilderCommon, EVMConstraintBuilder, StepStateTransition,
                 Transition::Delta,
             },
-            math_gadget::{IsZeroWordGadget, LtWordGadget, MulAddWordsGadget},
+            math_gadget::{ModuloGadget, OneHotGadget},
             CachedRegion,
         },
         witness::{Block, Call, ExecStep, Transaction},
@@ -32,12 +32,8 @@ pub(crate) struct MulDivModGadget<F> {
     same_context: SameContextGadget<F>,
     /// Words a, b, c, d
     pub words: [Word32Cell<F>; 4],
-    /// Gadget that verifies a * b + c = d
-    mul_add_words: MulAddWordsGadget<F>,
-    /// Check if divisor is zero for DIV and MOD
-    divisor_is_zero: IsZeroWordGadget<F, Word32Cell<F>>,
-    /// Check if residue < divisor when divisor != 0 for DIV and MOD
-    lt_word: LtWordGadget<F>,
+    /// Gadget that verifies a * b + c = d, and for DIV and MOD that c < b when b != 0
+    modulo: ModuloGadget<F>,
 }
 
 impl<F: Field> ExecutionGadget<F> for MulDivModGadget<F> {
@@ -45,26 +41,36 @@ impl<F: Field> ExecutionGadget<F> for MulDivModGadget<F> {
 
     const EXECUTION_STATE: ExecutionState = ExecutionState::MUL_DIV_MOD;
 
+    // Two pops and one push
+    const RW_DELTA: Option<usize> = Some(3);
+
+    const STACK_DELTA: Option<i32> = Some(1);
+
     fn configure(cb: &mut EVMConstraintBuilder<F>) -> Self {
         let opcode = cb.query_cell();
 
-        let is_mul = (OpcodeId::DIV.expr() - opcode.expr())
-            * (OpcodeId::MOD.expr() - opcode.expr())
-            * F::from(8).invert().unwrap();
-        let is_div = (opcode.expr() - OpcodeId::MUL.expr())
-            * (OpcodeId::MOD.expr() - opcode.expr())
-            * F::from(4).invert().unwrap();
-        let is_mod = (opcode.expr() - OpcodeId::MUL.expr())
-            * (opcode.expr() - OpcodeId::DIV.expr())
-            * F::from(8).invert().unwrap();
+        // Lagrange selectors of MUL, DIV and MOD, exactly one of them is set
+        let [is_mul, is_div, is_mod] = OneHotGadget::construct(
+            cb,
+            [
+                (OpcodeId::DIV.expr() - opcode.expr())
+                    * (OpcodeId::MOD.expr() - opcode.expr())
+                    * F::from(8).invert().unwrap(),
+                (opcode.expr() - OpcodeId::MUL.expr())
+                    * (OpcodeId::MOD.expr() - opcode.expr())
+                    * F::from(4).invert().unwrap(),
+                (opcode.expr() - OpcodeId::MUL.expr())
+                    * (opcode.expr() - OpcodeId::DIV.expr())
+                    * F::from(8).invert().unwrap(),
+            ],
+        )
+        .selectors();
         let a = cb.query_word32();
         let b = cb.query_word32();
         let c = cb.query_word32();
         let d = cb.query_word32();
 
-        let mul_add_words = MulAddWordsGadget::construct(cb, [&a, &b, &c, &d]);
-        let divisor_is_zero = IsZeroWordGadget::construct(cb, &b);
-        let lt_word = LtWordGadget::construct(cb, &c.to_word(), &b.to_word());
+        let modulo = ModuloGadget::construct(cb, [&a, &b, &c, &d], 1.expr() - is_mul.clone());
 
         // Pop a and b from the stack, push result on the stack
         // The first pop is multiplier for MUL and dividend for DIV/MOD
@@ -78,34 +84,22 @@ impl<F: Field> ExecutionGadget<F> for MulDivModGadget<F> {
                 .mul_selector(is_mul.clone())
                 .add_unchecked(
                     a.to_word()
-                        .mul_selector(is_div * (1.expr() - divisor_is_zero.expr())),
+                        .mul_selector(is_div * (1.expr() - modulo.n_is_zero())),
                 )
                 .add_unchecked(
                     c.to_word()
-                        .mul_selector(is_mod * (1.expr() - divisor_is_zero.expr())),
+                        .mul_selector(is_mod * (1.expr() - modulo.n_is_zero())),
                 ),
         );
 
         // Constraint for MUL case
-        cb.require_zero_word(
-            "c == 0 for opcode MUL",
-            c.to_word().mul_selector(is_mul.clone()),
-        );
-
-        // Constraints for DIV and MOD cases
-        cb.condition(1.expr() - is_mul, |cb| {
-            cb.add_constraint(
-                "residue < divisor when divisor != 0 for opcode DIV/MOD",
-                (1.expr() - lt_word.expr()) * (1.expr() - divisor_is_zero.expr()),
-            );
-            cb.require_zero("overflow == 0 for opcode DIV/MOD", mul_add_words.overflow());
-        });
+        cb.require_zero_word("c == 0 for opcode MUL", c.to_word().mul_selector(is_mul));
 
         // State transition
         let step_state_transition = StepStateTransition {
-            rw_counter: Delta(3.expr()),
+            rw_counter: Delta(Self::RW_DELTA.unwrap().expr()),
             program_counter: Delta(1.expr()),
-            stack_pointer: Delta(1.expr()),
+            stack_pointer: Delta(Self::STACK_DELTA.unwrap().expr()),
             gas_left: Delta(-OpcodeId::MUL.constant_gas_cost().expr()),
             ..Default::default()
         };
@@ -114,9 +108,7 @@ impl<F: Field> ExecutionGadget<F> for MulDivModGadget<F> {
         Self {
             words: [a, b, c, d],
             same_context,
-            mul_add_words,
-            divisor_is_zero,
-            lt_word,
+            modulo,
         }
     }
 
@@ -150,95 +142,189 @@ impl<F: Field> ExecutionGadget<F> for MulDivModGadget<F> {
         self.words[1].assign_u256(region, offset, b)?;
         self.words[2].assign_u256(region, offset, c)?;
         self.words[3].assign_u256(region, offset, d)?;
-        self.mul_add_words.assign(region, offset, [a, b, c, d])?;
-        self.lt_word.assign(region, offset, c, b)?;
-        self.divisor_is_zero
-            .assign(region, offset, WordLoHi::from(b))?;
+        self.modulo.assign(region, offset, [a, b, c, d])?;
         Ok(())
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::{evm_circuit::test::rand_word, test_util::CircuitTestBuilder};
-    use eth_types::{bytecode, evm_types::OpcodeId, Word};
-    use mock::TestContext;
-
-    fn test_ok(opcode: OpcodeId, a: Word, b: Word) {
-        let bytecode = bytecode! {
-            PUSH32(b)
-            PUSH32(a)
-            #[start]
-            .write_op(opcode)
-            STOP
-        };
+    use super::MulDivModGadget;
+    use crate::{
+        evm_circuit::{
+            step::{ExecutionState, HasExecutionState},
+            test::{assert_declared_deltas, rand_word},
+        },
+        test_util::{
+            assert_shared_constant_gas_cost, constraint_failure_in_gate, run_all_gadget_tests,
+            CircuitTestBuilder, TestCase,
+        },
+    };
+    use eth_types::{
+        bytecode,
+        evm_types::{GasCost, OpcodeId, Stack},
+        Word,
+    };
+    use halo2_proofs::halo2curves::bn256::Fr;
+    use mock::{test_ctx::helpers::account_0_code_account_1_no_code, TestContext};
 
-        CircuitTestBuilder::new_from_test_ctx(
-            TestContext::<2, 1>::simple_ctx_with_bytecode(bytecode).unwrap(),
+    fn case(opcode: OpcodeId, a: Word, b: Word) -> TestCase {
+        TestCase::new(
+            format!("{opcode:?}({a:#x}, {b:#x})"),
+            bytecode! {
+                PUSH32(b)
+                PUSH32(a)
+                .write_op(opcode)
+            },
         )
-        .run();
+    }
+
+    #[test]
+    fn mul_div_mod_gadget_shared_gas_cost() {
+        // The gadget charges the constant gas cost of MUL for the three opcodes
+        assert_shared_constant_gas_cost(ExecutionState::MUL_DIV_MOD);
+    }
+
+    #[test]
+    fn mul_div_mod_gadget_declared_deltas() {
+        for opcode in [OpcodeId::MUL, OpcodeId::DIV, OpcodeId::MOD] {
+            assert_declared_deltas::<MulDivModGadget<Fr>>(bytecode! {
+                PUSH32(3)
+                PUSH32(2)
+                .write_op(opcode)
+                STOP
+            });
+        }
     }
 
     #[test]
     fn mul_gadget_simple() {
-        test_ok(OpcodeId::MUL, 0x030201.into(), 0x060504.into());
+        run_all_gadget_tests(&[case(OpcodeId::MUL, 0x030201.into(), 0x060504.into())]);
     }
 
     #[test]
     fn mul_gadget_overflow() {
         let a = Word::from_dec_str("3402823669209384634633746074317682114560").unwrap(); // 2**128 * 10
         let b = Word::from_dec_str("34028236692093846346337460743176821145600").unwrap(); // 2**128 * 100
-        test_ok(OpcodeId::MUL, a, b);
-
-        let a = Word::from_dec_str("3402823669209384634633746074317682114560").unwrap(); // 2**128 * 10
-        let b = Word::from_dec_str("34028236692093846346337460743176821145500").unwrap(); // (2**128 - 1) * 100
-        test_ok(OpcodeId::MUL, a, b);
+        let b_minus = Word::from_dec_str("34028236692093846346337460743176821145500").unwrap(); // (2**128 - 1) * 100
+        run_all_gadget_tests(&[case(OpcodeId::MUL, a, b), case(OpcodeId::MUL, a, b_minus)]);
     }
 
     #[test]
     fn mul_gadget_rand() {
         let a = rand_word();
         let b = rand_word();
-        test_ok(OpcodeId::MUL, a, b);
+        run_all_gadget_tests(&[case(OpcodeId::MUL, a, b)]);
+    }
+
+    /// Runs `MUL` in a transaction with `missing_gas` less gas than the code needs and returns
+    /// the execution states of its steps.
+    fn mul_execution_states(missing_gas: u64) -> Vec<ExecutionState> {
+        let bytecode = bytecode! {
+            PUSH32(3)
+            PUSH32(2)
+            MUL
+            STOP
+        };
+        let gas = GasCost::TX
+            + 2 * OpcodeId::PUSH32.constant_gas_cost()
+            + OpcodeId::MUL.constant_gas_cost()
+            - missing_gas;
+        let ctx = TestContext::<2, 1>::new(
+            None,
+            account_0_code_account_1_no_code(bytecode),
+            |mut txs, accs| {
+                txs[0]
+                    .from(accs[1].address)
+                    .to(accs[0].address)
+                    .gas(gas.into());
+            },
+            |block, _tx| block,
+        )
+        .unwrap();
+
+        let test = CircuitTestBuilder::new_from_test_ctx(ctx);
+        let states = test
+            .build_block()
+            .unwrap()
+            .iter_steps()
+            .map(|(_, _, step)| step.execution_state())
+            .collect();
+        test.run();
+        states
+    }
+
+    #[test]
+    fn mul_gadget_gas_boundary() {
+        // Exactly enough gas
+        let states = mul_execution_states(0);
+        assert!(states.contains(&ExecutionState::MUL_DIV_MOD));
+        assert!(!states.contains(&ExecutionState::ErrorOutOfGasConstant));
+
+        // One gas short
+        let states = mul_execution_states(1);
+        assert!(!states.contains(&ExecutionState::MUL_DIV_MOD));
+        assert!(states.contains(&ExecutionState::ErrorOutOfGasConstant));
     }
 
     #[test]
     fn div_gadget_simple() {
-        test_ok(OpcodeId::DIV, 0xFFFFFF.into(), 0xABC.into());
-        test_ok(OpcodeId::DIV, 0xABC.into(), 0xFFFFFF.into());
-        test_ok(OpcodeId::DIV, 0xFFFFFF.into(), 0xFFFFFFF.into());
-        test_ok(OpcodeId::DIV, 0xABC.into(), 0.into());
-        test_ok(
-            OpcodeId::DIV,
-            Word::from_big_endian(&[255u8; 32]),
-            0xABCDEF.into(),
-        );
+        run_all_gadget_tests(&[
+            case(OpcodeId::DIV, 0xFFFFFF.into(), 0xABC.into()),
+            case(OpcodeId::DIV, 0xABC.into(), 0xFFFFFF.into()),
+            case(OpcodeId::DIV, 0xFFFFFF.into(), 0xFFFFFFF.into()),
+            case(OpcodeId::DIV, 0xABC.into(), 0.into()),
+            case(
+                OpcodeId::DIV,
+                Word::from_big_endian(&[255u8; 32]),
+                0xABCDEF.into(),
+            ),
+        ]);
+    }
+
+    #[test]
+    fn div_gadget_wrong_quotient() {
+        let bytecode = bytecode! {
+            PUSH32(0xABC)
+            PUSH32(0xFFFFFF)
+            DIV
+            STOP
+        };
+        let mut ctx = TestContext::<2, 1>::simple_ctx_with_bytecode(bytecode).unwrap();
+        // 0xFFFFFF / 0xABC = 6105, a quotient one less leaves a remainder larger than the divisor
+        let last = ctx.geth_traces[0].struct_logs.last_mut().unwrap();
+        assert_eq!(last.stack, Stack::from_vec(vec![6105.into()]));
+        last.stack = Stack::from_vec(vec![6104.into()]);
+        CircuitTestBuilder::new_from_test_ctx(ctx)
+            .expect_failure(constraint_failure_in_gate("MUL_DIV_MOD"));
     }
 
     #[test]
     fn div_gadget_rand() {
         let dividend = rand_word();
         let divisor = rand_word();
-        test_ok(OpcodeId::DIV, dividend, divisor);
+        run_all_gadget_tests(&[case(OpcodeId::DIV, dividend, divisor)]);
     }
 
     #[test]
     fn mod_gadget_simple() {
-        test_ok(OpcodeId::MOD, 0xFFFFFF.into(), 0xABC.into());
-        test_ok(OpcodeId::MOD, 0xABC.into(), 0xFFFFFF.into());
-        test_ok(OpcodeId::MOD, 0xFFFFFF.into(), 0xFFFFFFF.into());
-        test_ok(OpcodeId::MOD, 0xABC.into(), 0.into());
-        test_ok(
-            OpcodeId::MOD,
-            Word::from_big_endian(&[255u8; 32]),
-            0xABCDEF.into(),
-        );
+        run_all_gadget_tests(&[
+            case(OpcodeId::MOD, 0xFFFFFF.into(), 0xABC.into()),
+            case(OpcodeId::MOD, 0xABC.into(), 0xFFFFFF.into()),
+            case(OpcodeId::MOD, 0xFFFFFF.into(), 0xFFFFFFF.into()),
+            case(OpcodeId::MOD, 0xABC.into(), 0.into()),
+            case(
+                OpcodeId::MOD,
+                Word::from_big_endian(&[255u8; 32]),
+                0xABCDEF.into(),
+            ),
+        ]);
     }
 
     #[test]
     fn mod_gadget_rand() {
         let dividend = rand_word();
         let divisor = rand_word();
-        test_ok(OpcodeId::MOD, dividend, divisor);
+        run_all_gadget_tests(&[case(OpcodeId::MOD, dividend, divisor)]);
     }
 }
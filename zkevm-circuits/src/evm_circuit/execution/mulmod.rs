@@ -164,7 +164,7 @@ impl<F: Field> ExecutionGadget<F> for MulModGadget<F> {
 
 #[cfg(test)]
 mod test {
-    use crate::test_util::CircuitTestBuilder;
+    use crate::{evm_circuit::test::rand_word, test_util::CircuitTestBuilder};
     use eth_types::{bytecode, evm_types::Stack, Word, U256};
     use mock::TestContext;
 
@@ -243,6 +243,19 @@ mod test {
         );
     }
 
+    #[test]
+    fn mulmod_limits() {
+        // a * b = 2^512 - 2^257 + 1 overflows 2^256 by a full word
+        test(Word::MAX, Word::MAX, Word::MAX - 1, None, true);
+        test(Word::MAX, Word::MAX, Word::MAX, None, true);
+        test(Word::MAX, Word::MAX, 0.into(), None, true);
+    }
+
+    #[test]
+    fn mulmod_rand() {
+        test(rand_word(), rand_word(), rand_word(), None, true);
+    }
+
     #[test]
     fn mulmod_division_by_zero() {
         test_ok_u32(7, 1, 0, None);
@@ -26,7 +26,7 @@ use crate::{
 use bus_mapping::{circuit_input_builder::CopyDataType, evm::OpcodeId, state_db::CodeDB};
 use eth_types::{
     evm_types::{GasCost, INVALID_INIT_CODE_FIRST_BYTE},
-    Field, ToScalar, U256,
+    Field, ToScalar,
 };
 use halo2_proofs::{circuit::Value, plonk::Error};
 
@@ -329,10 +329,11 @@ impl<F: Field> ExecutionGadget<F> for ReturnRevertGadget<F> {
                 offset,
                 region.keccak_rlc(&values.iter().rev().cloned().collect::<Vec<u8>>()),
             )?;
-            let mut code_hash = CodeDB::hash(&values).to_fixed_bytes();
-            code_hash.reverse();
-            self.code_hash
-                .assign_u256(region, offset, U256::from_little_endian(&code_hash))?;
+            self.code_hash.assign_be_bytes(
+                region,
+                offset,
+                CodeDB::hash(&values).as_fixed_bytes(),
+            )?;
         }
 
         let copy_rw_increase = if call.is_create() && call.is_success {
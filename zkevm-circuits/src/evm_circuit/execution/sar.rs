@@ -9,7 +9,7 @@ use crate::{
                 ConstrainBuilderCommon, EVMConstraintBuilder, StepStateTransition,
                 Transition::Delta,
             },
-            math_gadget::{IsEqualGadget, IsZeroGadget, LtGadget},
+            math_gadget::{word_magnitude, IsEqualGadget, IsZeroGadget, LtGadget, SignWordGadget},
             select, sum, CachedRegion, Cell,
         },
         witness::{Block, Call, ExecStep, Transaction},
@@ -52,7 +52,7 @@ pub(crate) struct SarGadget<F> {
     // is_neg * (u64::MAX + 1 - p_hi)
     p_top: Cell<F>,
     // Identify if `a` is a negative word.
-    is_neg: LtGadget<F, 1>,
+    is_neg: SignWordGadget<F>,
     // Verify `shf_div64 < 4`.
     shf_div64_lt_4: LtGadget<F, 1>,
     // Verify `shf_mod64 < 64`.
@@ -99,7 +99,7 @@ impl<F: Field> ExecutionGadget<F> for SarGadget<F> {
         let p_lo = cb.query_cell();
         let p_hi = cb.query_cell();
         let p_top = cb.query_cell();
-        let is_neg = LtGadget::construct(cb, 127.expr(), a.limbs[31].expr());
+        let is_neg = SignWordGadget::construct(cb, &a);
         let shf_lt256 = IsZeroGadget::construct(cb, sum::expr(&shift.limbs[1..32]));
 
         for idx in 0..4 {
@@ -141,7 +141,7 @@ impl<F: Field> ExecutionGadget<F> for SarGadget<F> {
                 + (a64s_hi[1].expr() + a64s_lo[2].expr() * p_hi.expr()) * shf_div64_eq1.expr()
                 + (a64s_hi[2].expr() + a64s_lo[3].expr() * p_hi.expr()) * shf_div64_eq2.expr()
                 + (a64s_hi[3].expr() + p_top.expr()) * shf_div64_eq3.expr()
-                + is_neg.expr()
+                + is_neg.is_negative()
                     * u64::MAX.expr()
                     * (1.expr()
                         - shf_div64_eq0.expr()
@@ -155,7 +155,7 @@ impl<F: Field> ExecutionGadget<F> for SarGadget<F> {
             (a64s_hi[1].expr() + a64s_lo[2].expr() * p_hi.expr()) * shf_div64_eq0.expr()
                 + (a64s_hi[2].expr() + a64s_lo[3].expr() * p_hi.expr()) * shf_div64_eq1.expr()
                 + (a64s_hi[3].expr() + p_top.expr()) * shf_div64_eq2.expr()
-                + is_neg.expr()
+                + is_neg.is_negative()
                     * u64::MAX.expr()
                     * (1.expr()
                         - shf_div64_eq0.expr()
@@ -167,7 +167,7 @@ impl<F: Field> ExecutionGadget<F> for SarGadget<F> {
             b64s.limbs[2].expr(),
             (a64s_hi[2].expr() + a64s_lo[3].expr() * p_hi.expr()) * shf_div64_eq0.expr()
                 + (a64s_hi[3].expr() + p_top.expr()) * shf_div64_eq1.expr()
-                + is_neg.expr()
+                + is_neg.is_negative()
                     * u64::MAX.expr()
                     * (1.expr() - shf_div64_eq0.expr() - shf_div64_eq1.expr()),
         );
@@ -175,7 +175,7 @@ impl<F: Field> ExecutionGadget<F> for SarGadget<F> {
             "Constrain merged b64s[3] value",
             b64s.limbs[3].expr(),
             (a64s_hi[3].expr() + p_top.expr()) * shf_div64_eq0.expr()
-                + is_neg.expr() * u64::MAX.expr() * (1.expr() - shf_div64_eq0.expr()),
+                + is_neg.is_negative() * u64::MAX.expr() * (1.expr() - shf_div64_eq0.expr()),
         );
 
         // Shift constraint
@@ -190,14 +190,14 @@ impl<F: Field> ExecutionGadget<F> for SarGadget<F> {
         );
 
         // `is_neg` constraints
-        cb.require_boolean("is_neg is boolean", is_neg.expr());
+        cb.require_boolean("is_neg is boolean", is_neg.is_negative());
         cb.add_lookup(
             "SignByte lookup for a and is_neg",
             Lookup::Fixed {
                 tag: FixedTableTag::SignByte.expr(),
                 values: [
                     a.limbs[31].expr(),
-                    select::expr(is_neg.expr(), 255.expr(), 0.expr()),
+                    select::expr(is_neg.is_negative(), 255.expr(), 0.expr()),
                     0.expr(),
                 ],
             },
@@ -207,7 +207,7 @@ impl<F: Field> ExecutionGadget<F> for SarGadget<F> {
         cb.require_equal(
             "p_top == is_neg * (MAX_U64 + 1 - p_hi)",
             p_top.expr(),
-            is_neg.expr() * (u64::MAX.expr() + 1.expr() - p_hi.expr()),
+            is_neg.is_negative() * (u64::MAX.expr() + 1.expr() - p_hi.expr()),
         );
 
         // Constrain `p_lo == pow(2, shf_mod64)`.
@@ -334,12 +334,7 @@ impl<F: Field> ExecutionGadget<F> for SarGadget<F> {
             .assign(region, offset, Value::known(F::from_u128(p_hi)))?;
         self.p_top
             .assign(region, offset, Value::known(F::from_u128(p_top)))?;
-        self.is_neg.assign(
-            region,
-            offset,
-            127.into(),
-            u64::from(a.to_le_bytes()[31]).into(),
-        )?;
+        self.is_neg.assign(region, offset, a, word_magnitude(a))?;
         self.shf_div64_lt_4
             .assign(region, offset, F::from_u128(shf_div64), 4.into())?;
         self.shf_mod64_lt_64
@@ -371,8 +366,11 @@ impl<F: Field> ExecutionGadget<F> for SarGadget<F> {
 
 #[cfg(test)]
 mod test {
-    use crate::{evm_circuit::test::rand_word, test_util::CircuitTestBuilder};
-    use eth_types::{bytecode, U256};
+    use crate::{
+        evm_circuit::test::rand_word,
+        test_util::{constraint_failure_in_gate, CircuitTestBuilder},
+    };
+    use eth_types::{bytecode, evm_types::Stack, U256};
     use ethers_core::types::I256;
     use lazy_static::lazy_static;
     use mock::TestContext;
@@ -462,6 +460,23 @@ mod test {
         test_ok(0x100.into(), *MAX_POS);
     }
 
+    #[test]
+    fn test_sar_gadget_wrong_result() {
+        // Shifting a negative `a` by 256 or more gives -1, not 0
+        let bytecode = bytecode! {
+            PUSH32(*NEG_SIGN)
+            PUSH32(0x100)
+            SAR
+            STOP
+        };
+        let mut ctx = TestContext::<2, 1>::simple_ctx_with_bytecode(bytecode).unwrap();
+        let last = ctx.geth_traces[0].struct_logs.last_mut().unwrap();
+        assert_eq!(last.stack, Stack::from_vec(vec![*MAX_NEG]));
+        last.stack = Stack::from_vec(vec![U256::zero()]);
+        CircuitTestBuilder::new_from_test_ctx(ctx)
+            .expect_failure(constraint_failure_in_gate("SAR"));
+    }
+
     fn test_ok(shift: U256, a: U256) {
         let bytecode = bytecode! {
             PUSH32(a)
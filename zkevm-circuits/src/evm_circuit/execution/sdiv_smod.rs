@@ -9,7 +9,8 @@ use crate::{
                 Transition::Delta,
             },
             math_gadget::{
-                AbsWordGadget, IsZeroWordGadget, LtGadget, LtWordGadget, MulAddWordsGadget,
+                word_magnitude, AbsWordGadget, IsZeroWordGadget, LtGadget, LtWordGadget,
+                MulAddWordsGadget,
             },
             CachedRegion,
         },
@@ -98,7 +99,7 @@ impl<F: Field> ExecutionGadget<F> for SignedDivModGadget<F> {
             * (1.expr() - remainder_is_zero.expr()),
             |cb| cb.add_constraint(
                 "sign(dividend) == sign(remainder) when quotient, divisor and remainder are all non-zero",
-                dividend_abs.is_neg().expr() - remainder_abs.is_neg().expr(),
+                dividend_abs.is_neg() - remainder_abs.is_neg(),
             )
         );
 
@@ -120,9 +121,9 @@ impl<F: Field> ExecutionGadget<F> for SignedDivModGadget<F> {
             |cb| {
                 cb.add_constraint(
                     "sign(dividend) == sign(divisor) ^ sign(quotient)",
-                    quotient_abs.is_neg().expr() + divisor_abs.is_neg().expr()
-                        - dividend_abs.is_neg().expr()
-                        - 2.expr() * quotient_abs.is_neg().expr() * divisor_abs.is_neg().expr(),
+                    quotient_abs.is_neg() + divisor_abs.is_neg()
+                        - dividend_abs.is_neg()
+                        - 2.expr() * quotient_abs.is_neg() * divisor_abs.is_neg(),
                 )
             },
         );
@@ -162,9 +163,9 @@ impl<F: Field> ExecutionGadget<F> for SignedDivModGadget<F> {
     ) -> Result<(), Error> {
         self.same_context.assign_exec_step(region, offset, step)?;
         let [pop1, pop2, push] = [0, 1, 2].map(|idx| block.get_rws(step, idx).stack_value());
-        let pop1_abs = get_abs(pop1);
-        let pop2_abs = get_abs(pop2);
-        let push_abs = get_abs(push);
+        let pop1_abs = word_magnitude(pop1);
+        let pop2_abs = word_magnitude(pop2);
+        let push_abs = word_magnitude(push);
         let is_pop1_neg = is_neg(pop1);
         let is_pop2_neg = is_neg(pop2);
         let (quotient, divisor, remainder, dividend) = match step.opcode().unwrap() {
@@ -192,10 +193,10 @@ impl<F: Field> ExecutionGadget<F> for SignedDivModGadget<F> {
             ),
             _ => unreachable!(),
         };
-        let quotient_abs = get_abs(quotient);
-        let divisor_abs = get_abs(divisor);
-        let remainder_abs = get_abs(remainder);
-        let dividend_abs = get_abs(dividend);
+        let quotient_abs = word_magnitude(quotient);
+        let divisor_abs = word_magnitude(divisor);
+        let remainder_abs = word_magnitude(remainder);
+        let dividend_abs = word_magnitude(dividend);
         self.quotient_abs
             .assign(region, offset, quotient, quotient_abs)?;
         self.divisor_abs
@@ -227,15 +228,6 @@ impl<F: Field> ExecutionGadget<F> for SignedDivModGadget<F> {
     }
 }
 
-#[inline]
-fn get_abs(x: U256) -> U256 {
-    if is_neg(x) {
-        get_neg(x)
-    } else {
-        x
-    }
-}
-
 #[inline]
 fn get_neg(x: U256) -> U256 {
     if x.is_zero() {
@@ -302,6 +294,28 @@ mod test {
         );
     }
 
+    /// Two's complement encoding of `-n`
+    fn neg(n: u64) -> Word {
+        Word::zero().overflowing_sub(Word::from(n)).0
+    }
+
+    #[test]
+    fn sdiv_smod_gadget_negative() {
+        // 1 << 255, the minimum signed value
+        let mut min = [0u8; 32];
+        min[0] = 128u8;
+        let min = Word::from_big_endian(&min);
+        for opcode in [OpcodeId::SDIV, OpcodeId::SMOD] {
+            test_ok(opcode, neg(0xFFFFFF), 0xABC.into());
+            test_ok(opcode, 0xFFFFFF.into(), neg(0xABC));
+            test_ok(opcode, neg(0xFFFFFF), neg(0xABC));
+            test_ok(opcode, neg(0xABC), neg(0xFFFFFF));
+            test_ok(opcode, neg(0xABC), 0.into());
+            test_ok(opcode, min, neg(1));
+            test_ok(opcode, min, min);
+        }
+    }
+
     #[test]
     fn sdiv_gadget_rand() {
         let dividend = rand_word();
@@ -6,7 +6,9 @@ use crate::{
             common_gadget::SameContextGadget,
             constraint_builder::{EVMConstraintBuilder, StepStateTransition, Transition::Delta},
             from_bytes,
-            math_gadget::{ComparisonGadget, IsEqualGadget, LtGadget},
+            math_gadget::{
+                word_magnitude, ComparisonGadget, IsEqualGadget, LtGadget, SignWordGadget,
+            },
             select, CachedRegion, Cell,
         },
         witness::{Block, Call, ExecStep, Transaction},
@@ -28,8 +30,8 @@ pub(crate) struct SignedComparatorGadget<F> {
     a: Word32Cell<F>,
     b: Word32Cell<F>,
 
-    sign_check_a: LtGadget<F, 1>,
-    sign_check_b: LtGadget<F, 1>,
+    sign_check_a: SignWordGadget<F>,
+    sign_check_b: SignWordGadget<F>,
     lt_lo: LtGadget<F, 16>,
     comparison_hi: ComparisonGadget<F, 16>,
     a_lt_b: Cell<F>,
@@ -57,15 +59,12 @@ impl<F: Field> ExecutionGadget<F> for SignedComparatorGadget<F> {
         // (32 cells) integers. This means, the first bit denotes the sign
         // of the absolute value in the rest of the 255 bits. This means the
         // number is negative if the most significant cell >= 128
-        // (0b10000000). a and b being in the little-endian notation, the
-        // most-significant byte is the last byte.
-        let sign_check_a = LtGadget::construct(cb, a.limbs[31].expr(), 128.expr());
-        let sign_check_b = LtGadget::construct(cb, b.limbs[31].expr(), 128.expr());
+        // (0b10000000).
+        let sign_check_a = SignWordGadget::construct(cb, &a);
+        let sign_check_b = SignWordGadget::construct(cb, &b);
 
-        // sign_check_a_lt expression implies a is positive since its MSB < 2**7
-        // sign_check_b_lt expression implies b is positive since its MSB < 2**7
-        let a_pos = sign_check_a.expr();
-        let b_pos = sign_check_b.expr();
+        let a_pos = 1.expr() - sign_check_a.is_negative();
+        let b_pos = 1.expr() - sign_check_b.is_negative();
 
         // We require the comparison check only for the cases where:
         // (a < 0 && b < 0) || (a >= 0 && b >= 0).
@@ -168,20 +167,11 @@ impl<F: Field> ExecutionGadget<F> for SignedComparatorGadget<F> {
         let a_le_bytes = a.to_le_bytes();
         let b_le_bytes = b.to_le_bytes();
 
-        // Assign to the sign check gadgets. Since both a and b are in the
-        // little-endian form, the most significant byte is the last byte.
-        self.sign_check_a.assign(
-            region,
-            offset,
-            F::from(a_le_bytes[31] as u64),
-            F::from(128u64),
-        )?;
-        self.sign_check_b.assign(
-            region,
-            offset,
-            F::from(b_le_bytes[31] as u64),
-            F::from(128u64),
-        )?;
+        // Assign to the sign check gadgets.
+        self.sign_check_a
+            .assign(region, offset, a, word_magnitude(a))?;
+        self.sign_check_b
+            .assign(region, offset, b, word_magnitude(b))?;
 
         // Assign to the comparison gadgets. The first 16 bytes are assigned to
         // the `lo` less-than gadget while the last 16 bytes are assigned to
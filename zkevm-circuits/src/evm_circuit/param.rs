@@ -200,4 +200,9 @@ mod tests {
         // We show that the invalid tx feature affects none of the other execution state heights
         assert_eq!(map_invalid_tx, map_mainnet);
     }
+
+    #[test]
+    fn all_execution_states_covered() {
+        crate::test_util::assert_all_execution_states_covered();
+    }
 }
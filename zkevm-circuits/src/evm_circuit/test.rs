@@ -1,4 +1,7 @@
-use eth_types::Word;
+use super::{execution::ExecutionGadget, step::HasExecutionState};
+use crate::test_util::block_for_bytecode;
+use eth_types::{Bytecode, Word};
+use halo2_proofs::halo2curves::bn256::Fr;
 use rand::{
     distributions::uniform::{SampleRange, SampleUniform},
     random, thread_rng, Rng,
@@ -23,3 +26,31 @@ pub(crate) fn rand_bytes_array<const N: usize>() -> [u8; N] {
 pub(crate) fn rand_word() -> Word {
     Word::from_big_endian(&rand_bytes_array::<32>())
 }
+
+/// Assert that the rw counter and stack pointer deltas declared by the gadget `G`, see
+/// [`ExecutionGadget::RW_DELTA`] and [`ExecutionGadget::STACK_DELTA`], are the ones of the steps
+/// of its execution state in the witness of `code`, which must execute at least one of them.
+pub(crate) fn assert_declared_deltas<G: ExecutionGadget<Fr>>(code: Bytecode) {
+    let block = block_for_bytecode(code);
+    let steps: Vec<_> = block.iter_steps().map(|(_, _, step)| step).collect();
+    let mut num_checked = 0;
+    for pair in steps.windows(2) {
+        let (step, next) = (pair[0], pair[1]);
+        if step.execution_state() != G::EXECUTION_STATE {
+            continue;
+        }
+        if let Some(rw_delta) = G::RW_DELTA {
+            assert_eq!(step.rw_indices_len(), rw_delta, "rw delta of {}", G::NAME);
+        }
+        if let Some(stack_delta) = G::STACK_DELTA {
+            assert_eq!(
+                next.stack_pointer() as i32 - step.stack_pointer() as i32,
+                stack_delta,
+                "stack delta of {}",
+                G::NAME
+            );
+        }
+        num_checked += 1;
+    }
+    assert!(num_checked > 0, "the code executes no {} step", G::NAME);
+}
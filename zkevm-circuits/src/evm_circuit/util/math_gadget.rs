@@ -9,6 +9,9 @@ mod byte_size;
 mod cmp_words;
 mod comparison;
 mod constant_division;
+// Only built for its tests until the hash precompiles use it
+#[cfg(test)]
+mod hash_output;
 mod is_equal;
 mod is_equal_word;
 mod is_zero;
@@ -20,11 +23,13 @@ mod modulo;
 mod mul_add_words;
 mod mul_add_words512;
 mod mul_word_u64;
+mod one_hot;
 mod pair_select;
 mod range_check;
 mod rlp;
+mod sign_word;
 #[cfg(test)]
-mod test_util;
+pub(crate) mod test_util;
 
 pub(crate) use abs_word::AbsWordGadget;
 pub(crate) use add_words::AddWordsGadget;
@@ -40,13 +45,15 @@ pub(crate) use is_zero_word::IsZeroWordGadget;
 pub(crate) use lt::LtGadget;
 pub(crate) use lt_word::LtWordGadget;
 pub(crate) use min_max::MinMaxGadget;
-pub(crate) use modulo::ModGadget;
+pub(crate) use modulo::{ModGadget, ModuloGadget};
 pub(crate) use mul_add_words::MulAddWordsGadget;
 pub(crate) use mul_add_words512::MulAddWords512Gadget;
 pub(crate) use mul_word_u64::MulWordByU64Gadget;
+pub(crate) use one_hot::OneHotGadget;
 pub(crate) use pair_select::PairSelectGadget;
 pub(crate) use range_check::RangeCheckGadget;
 pub(crate) use rlp::ContractCreateGadget;
+pub(crate) use sign_word::{word_magnitude, SignWordGadget};
 
 // This function generates a Lagrange polynomial in the range [start, end) which
 // will be evaluated to 1 when `exp == value`, otherwise 0
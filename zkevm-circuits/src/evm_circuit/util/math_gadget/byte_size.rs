@@ -173,6 +173,11 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_bytesize_0xff_eq_1() {
+        try_test!(ByteSizeGadgetContainer<Fr, 1>, vec![Word::from(0xff)], true)
+    }
+
     #[test]
     fn test_bytesize_256_eq_2() {
         try_test!(ByteSizeGadgetContainer<Fr, 2>,
@@ -181,6 +186,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_bytesize_2pow255_eq_32() {
+        try_test!(ByteSizeGadgetContainer<Fr, 32>, vec![Word::one() << 255], true)
+    }
+
+    #[test]
+    fn test_bytesize_2pow255_neq_31() {
+        try_test!(ByteSizeGadgetContainer<Fr, 31>,
+            vec![Word::one() << 255],
+            false
+        );
+    }
+
     #[test]
     fn test_bytesize_wordmax_eq_32() {
         try_test!(ByteSizeGadgetContainer<Fr, 32>, vec![Word::MAX], true)
@@ -1,5 +1,5 @@
 use crate::evm_circuit::util::{
-    constraint_builder::EVMConstraintBuilder, math_gadget::*, sum, CachedRegion,
+    constraint_builder::EVMConstraintBuilder, math_gadget::*, sum, CachedRegion, Cell,
 };
 use eth_types::Field;
 use halo2_proofs::plonk::{Error, Expression};
@@ -31,6 +31,10 @@ impl<F: Field, const N_BYTES: usize> ComparisonGadget<F, N_BYTES> {
         (self.lt.expr(), self.eq.expr())
     }
 
+    pub(crate) fn diff_bytes(&self) -> Vec<Cell<F>> {
+        self.lt.diff_bytes()
+    }
+
     pub(crate) fn assign(
         &self,
         region: &mut CachedRegion<'_, '_, F>,
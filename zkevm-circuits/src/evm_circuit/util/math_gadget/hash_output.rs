@@ -0,0 +1,155 @@
+use crate::{
+    evm_circuit::util::{
+        constraint_builder::{ConstrainBuilderCommon, EVMConstraintBuilder},
+        CachedRegion,
+    },
+    util::{
+        word::{Word32Cell, WordExpr, WordLoHi},
+        Expr,
+    },
+};
+use eth_types::Field;
+use halo2_proofs::plonk::{Error, Expression};
+
+/// Holds the 32 byte output of a hash, for example the digest of the SHA256 or the address
+/// returned by the ECRECOVER precompile, and requires the word it encodes in big-endian to be
+/// equal to `expected`.
+#[derive(Clone, Debug)]
+pub struct HashOutputGadget<F> {
+    output: Word32Cell<F>,
+}
+
+impl<F: Field> HashOutputGadget<F> {
+    pub(crate) fn construct(
+        cb: &mut EVMConstraintBuilder<F>,
+        expected: &WordLoHi<Expression<F>>,
+    ) -> Self {
+        let output = cb.query_word32();
+        cb.require_equal_word(
+            "hash output is equal to the expected word",
+            output.to_word(),
+            expected.clone(),
+        );
+
+        Self { output }
+    }
+
+    /// The output bytes in the order produced by the hash (big-endian)
+    pub(crate) fn bytes(&self) -> Vec<Expression<F>> {
+        self.output
+            .limbs
+            .iter()
+            .rev()
+            .map(|byte| byte.expr())
+            .collect()
+    }
+
+    /// The output as a word
+    pub(crate) fn word(&self) -> WordLoHi<Expression<F>> {
+        self.output.to_word()
+    }
+
+    pub(crate) fn assign(
+        &self,
+        region: &mut CachedRegion<'_, '_, F>,
+        offset: usize,
+        output: &[u8; 32],
+    ) -> Result<(), Error> {
+        self.output.assign_be_bytes(region, offset, output)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::test_util::*, *};
+    use crate::{evm_circuit::util::Cell, util::word::WordLoHiCell};
+    use eth_types::Word;
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    #[derive(Clone)]
+    /// HashOutputTestContainer: require(output as big-endian word == expected && output[0] ==
+    /// first_byte)
+    struct HashOutputTestContainer<F> {
+        hash_output_gadget: HashOutputGadget<F>,
+        expected: WordLoHiCell<F>,
+        first_byte: Cell<F>,
+    }
+
+    impl<F: Field> MathGadgetContainer<F> for HashOutputTestContainer<F> {
+        fn configure_gadget_container(cb: &mut EVMConstraintBuilder<F>) -> Self {
+            let expected = cb.query_word_unchecked();
+            let first_byte = cb.query_cell();
+            let hash_output_gadget = HashOutputGadget::construct(cb, &expected.to_word());
+            cb.require_equal(
+                "first byte of the output",
+                hash_output_gadget.bytes()[0].clone(),
+                first_byte.expr(),
+            );
+
+            HashOutputTestContainer {
+                hash_output_gadget,
+                expected,
+                first_byte,
+            }
+        }
+
+        fn assign_gadget_container(
+            &self,
+            witnesses: &[Word],
+            region: &mut CachedRegion<'_, '_, F>,
+        ) -> Result<(), Error> {
+            let offset = 0;
+            let mut output = [0u8; 32];
+            witnesses[0].to_big_endian(&mut output);
+            self.hash_output_gadget.assign(region, offset, &output)?;
+            self.expected.assign_u256(region, offset, witnesses[1])?;
+            self.first_byte
+                .assign(region, offset, Value::known(F::from(witnesses[2].as_u64())))?;
+
+            Ok(())
+        }
+    }
+
+    // sha256("abc")
+    const DIGEST: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+    #[test]
+    fn test_hash_output_equal() {
+        let digest = Word::from_str_radix(DIGEST, 16).unwrap();
+        try_test!(
+            HashOutputTestContainer<Fr>,
+            vec![digest, digest, Word::from(0xba)],
+            true,
+        );
+        try_test!(
+            HashOutputTestContainer<Fr>,
+            vec![Word::zero(), Word::zero(), Word::zero()],
+            true,
+        );
+        // The first byte produced by the hash is the most significant one
+        try_test!(
+            HashOutputTestContainer<Fr>,
+            vec![digest, digest, Word::from(0xad)],
+            false,
+        );
+    }
+
+    #[test]
+    fn test_hash_output_mismatch() {
+        let digest = Word::from_str_radix(DIGEST, 16).unwrap();
+        try_test!(
+            HashOutputTestContainer<Fr>,
+            vec![digest, digest ^ Word::one(), Word::from(0xba)],
+            false,
+        );
+        // The output is read in big-endian, the little-endian word is a different word
+        let mut bytes = [0u8; 32];
+        digest.to_little_endian(&mut bytes);
+        try_test!(
+            HashOutputTestContainer<Fr>,
+            vec![digest, Word::from_big_endian(&bytes), Word::from(0xba)],
+            false,
+        );
+    }
+}
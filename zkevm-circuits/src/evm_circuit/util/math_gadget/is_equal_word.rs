@@ -80,4 +80,101 @@ impl<F: Field, T1: WordExpr<F>, T2: WordExpr<F>> IsEqualWordGadget<F, T1, T2> {
     }
 }
 
-// TODO add unittest
+#[cfg(test)]
+mod tests {
+    use super::{super::test_util::*, *};
+    use crate::{
+        evm_circuit::util::constraint_builder::ConstrainBuilderCommon,
+        util::word::{WordExpr, WordLoHiCell},
+    };
+    use eth_types::{Field, Word};
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    #[derive(Clone)]
+    /// EqTestContainer: require(EQ(a, b) == result)
+    struct EqTestContainer<F> {
+        eq_gadget: IsEqualWordGadget<F, WordLoHi<Expression<F>>, WordLoHi<Expression<F>>>,
+        a: WordLoHiCell<F>,
+        b: WordLoHiCell<F>,
+        result: WordLoHiCell<F>,
+    }
+
+    impl<F: Field> MathGadgetContainer<F> for EqTestContainer<F> {
+        fn configure_gadget_container(cb: &mut EVMConstraintBuilder<F>) -> Self {
+            let a = cb.query_word_unchecked();
+            let b = cb.query_word_unchecked();
+            let result = cb.query_word_unchecked();
+            let eq_gadget = a.to_word().is_equal(cb, &b.to_word());
+            cb.require_equal_word(
+                "result is EQ(a, b)",
+                WordLoHi::from_lo_unchecked(eq_gadget.expr()),
+                result.to_word(),
+            );
+
+            EqTestContainer {
+                eq_gadget,
+                a,
+                b,
+                result,
+            }
+        }
+
+        fn assign_gadget_container(
+            &self,
+            witnesses: &[Word],
+            region: &mut CachedRegion<'_, '_, F>,
+        ) -> Result<(), Error> {
+            let offset = 0;
+            self.a.assign_u256(region, offset, witnesses[0])?;
+            self.b.assign_u256(region, offset, witnesses[1])?;
+            self.result.assign_u256(region, offset, witnesses[2])?;
+            self.eq_gadget
+                .assign_u256(region, offset, witnesses[0], witnesses[1])?;
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_eq_equal() {
+        try_test!(
+            EqTestContainer<Fr>,
+            vec![Word::from(0), Word::from(0), Word::from(1)],
+            true,
+        );
+        try_test!(
+            EqTestContainer<Fr>,
+            vec![Word::MAX, Word::MAX, Word::from(1)],
+            true,
+        );
+        try_test!(
+            EqTestContainer<Fr>,
+            vec![Word::from(1), Word::from(1), Word::from(0)],
+            false,
+        );
+    }
+
+    #[test]
+    fn test_eq_unequal() {
+        try_test!(
+            EqTestContainer<Fr>,
+            vec![Word::from(1), Word::from(2), Word::from(0)],
+            true,
+        );
+        // Words differing only in the high limb
+        try_test!(
+            EqTestContainer<Fr>,
+            vec![
+                Word::from(1),
+                Word::from(1) + (Word::from(1) << 128),
+                Word::from(0)
+            ],
+            true,
+        );
+        try_test!(
+            EqTestContainer<Fr>,
+            vec![Word::from(1), Word::from(2), Word::from(1)],
+            false,
+        );
+    }
+}
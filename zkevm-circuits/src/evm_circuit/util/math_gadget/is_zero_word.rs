@@ -104,4 +104,62 @@ impl<F: Field, T: WordExpr<F>> IsZeroWordGadget<F, T> {
     }
 }
 
-// TODO adding unittest
+#[cfg(test)]
+mod tests {
+    use super::{super::test_util::*, *};
+    use crate::util::word::Word32Cell;
+    use eth_types::Word;
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    #[derive(Clone)]
+    /// IsZeroWordGadgetTestContainer: require(is_zero(x) == IS_ZERO)
+    struct IsZeroWordGadgetTestContainer<F, const IS_ZERO: bool> {
+        z_gadget: IsZeroWordGadget<F, Word32Cell<F>>,
+        x: Word32Cell<F>,
+    }
+
+    impl<F: Field, const IS_ZERO: bool> MathGadgetContainer<F>
+        for IsZeroWordGadgetTestContainer<F, IS_ZERO>
+    {
+        fn configure_gadget_container(cb: &mut EVMConstraintBuilder<F>) -> Self {
+            let x = cb.query_word32();
+            let z_gadget = IsZeroWordGadget::construct(cb, &x);
+            cb.require_equal("is_zero is correct", z_gadget.expr(), IS_ZERO.expr());
+
+            IsZeroWordGadgetTestContainer { z_gadget, x }
+        }
+
+        fn assign_gadget_container(
+            &self,
+            witnesses: &[Word],
+            region: &mut CachedRegion<'_, '_, F>,
+        ) -> Result<(), Error> {
+            let offset = 0;
+            self.x.assign_u256(region, offset, witnesses[0])?;
+            self.z_gadget.assign_u256(region, offset, witnesses[0])?;
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_is_zero_word_zero() {
+        try_test!(IsZeroWordGadgetTestContainer<Fr, true>, [Word::zero()], true);
+        try_test!(IsZeroWordGadgetTestContainer<Fr, false>, [Word::zero()], false);
+    }
+
+    #[test]
+    fn test_is_zero_word_non_zero() {
+        // Only the low limb, only the high limb (a value >= 2^128) and both limbs are non-zero
+        for x in [
+            Word::one(),
+            WORD_LOW_MAX,
+            Word::one() << 128,
+            WORD_HIGH_MAX,
+            Word::MAX,
+        ] {
+            try_test!(IsZeroWordGadgetTestContainer<Fr, false>, [x], true);
+            try_test!(IsZeroWordGadgetTestContainer<Fr, true>, [x], false);
+        }
+    }
+}
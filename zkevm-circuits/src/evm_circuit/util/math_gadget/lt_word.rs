@@ -1,3 +1,5 @@
+#[cfg(test)]
+use crate::evm_circuit::util::from_bytes;
 use crate::{
     evm_circuit::util::{
         constraint_builder::EVMConstraintBuilder, math_gadget::*, split_u256, CachedRegion,
@@ -36,6 +38,20 @@ impl<F: Field> LtWordGadget<F> {
         hi_lt + hi_eq * self.lt_lo.expr()
     }
 
+    /// Difference of the low limbs: `lhs_lo - rhs_lo`, plus `2^128` when `lhs_lo < rhs_lo`.
+    /// The limbs are compared independently, the borrow of the low limbs is not subtracted from
+    /// the high limbs.
+    #[cfg(test)]
+    pub(crate) fn diff_lo(&self) -> Expression<F> {
+        from_bytes::expr(&self.lt_lo.diff_bytes())
+    }
+
+    /// Difference of the high limbs: `lhs_hi - rhs_hi`, plus `2^128` when `lhs_hi < rhs_hi`.
+    #[cfg(test)]
+    pub(crate) fn diff_hi(&self) -> Expression<F> {
+        from_bytes::expr(&self.comparison_hi.diff_bytes())
+    }
+
     pub(crate) fn assign(
         &self,
         region: &mut CachedRegion<'_, '_, F>,
@@ -69,7 +85,10 @@ mod tests {
     };
 
     use super::{test_util::*, *};
-    use halo2_proofs::{halo2curves::bn256::Fr, plonk::Error};
+    use crate::evm_circuit::util::{pow_of_two, Cell};
+    use halo2_proofs::{circuit::Value, halo2curves::bn256::Fr, plonk::Error};
+    use rand::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
 
     #[derive(Clone)]
     /// LtWordTestContainer: require(a < b)
@@ -109,6 +128,68 @@ mod tests {
         }
     }
 
+    #[derive(Clone)]
+    /// LtWordDiffTestContainer: require(a + borrow * 2^128 == b + diff) on each limb
+    struct LtWordDiffTestContainer<F> {
+        ltword_gadget: LtWordGadget<F>,
+        a: Word32Cell<F>,
+        b: Word32Cell<F>,
+        borrow_lo: Cell<F>,
+        borrow_hi: Cell<F>,
+    }
+
+    impl<F: Field> MathGadgetContainer<F> for LtWordDiffTestContainer<F> {
+        fn configure_gadget_container(cb: &mut EVMConstraintBuilder<F>) -> Self {
+            let a = cb.query_word32();
+            let b = cb.query_word32();
+            let borrow_lo = cb.query_bool();
+            let borrow_hi = cb.query_bool();
+            let ltword_gadget = LtWordGadget::<F>::construct(cb, &a.to_word(), &b.to_word());
+            let (a_lo, a_hi) = a.to_word().to_lo_hi();
+            let (b_lo, b_hi) = b.to_word().to_lo_hi();
+            let range = Expression::Constant(pow_of_two::<F>(128));
+            cb.require_equal(
+                "a_lo + borrow_lo * 2^128 == b_lo + diff_lo",
+                a_lo + borrow_lo.expr() * range.clone(),
+                b_lo + ltword_gadget.diff_lo(),
+            );
+            cb.require_equal(
+                "a_hi + borrow_hi * 2^128 == b_hi + diff_hi",
+                a_hi + borrow_hi.expr() * range,
+                b_hi + ltword_gadget.diff_hi(),
+            );
+            LtWordDiffTestContainer {
+                ltword_gadget,
+                a,
+                b,
+                borrow_lo,
+                borrow_hi,
+            }
+        }
+
+        fn assign_gadget_container(
+            &self,
+            witnesses: &[Word],
+            region: &mut CachedRegion<'_, '_, F>,
+        ) -> Result<(), Error> {
+            let a = witnesses[0];
+            let b = witnesses[1];
+            let (a_lo, a_hi) = split_u256(&a);
+            let (b_lo, b_hi) = split_u256(&b);
+            let offset = 0;
+
+            self.a.assign_u256(region, offset, a)?;
+            self.b.assign_u256(region, offset, b)?;
+            self.borrow_lo
+                .assign(region, offset, Value::known(F::from((a_lo < b_lo) as u64)))?;
+            self.borrow_hi
+                .assign(region, offset, Value::known(F::from((a_hi < b_hi) as u64)))?;
+            self.ltword_gadget.assign(region, 0, a, b)?;
+
+            Ok(())
+        }
+    }
+
     #[test]
     fn test_ltword_expect() {
         try_test!(
@@ -153,4 +234,28 @@ mod tests {
             false,
         );
     }
+
+    #[test]
+    fn test_ltword_diff() {
+        let mut rng = XorShiftRng::seed_from_u64(1);
+        let mut rand_word = || {
+            let mut bytes = [0u8; 32];
+            rng.fill_bytes(&mut bytes);
+            Word::from_big_endian(&bytes)
+        };
+        for _ in 0..8 {
+            let (a, b) = (rand_word(), rand_word());
+            try_test!(LtWordDiffTestContainer<Fr>, vec![a, b], true);
+        }
+        try_test!(
+            LtWordDiffTestContainer<Fr>,
+            vec![WORD_LOW_MAX, WORD_HIGH_MAX],
+            true,
+        );
+        try_test!(
+            LtWordDiffTestContainer<Fr>,
+            vec![Word::MAX, Word::MAX],
+            true
+        );
+    }
 }
@@ -10,7 +10,10 @@ use crate::{
     },
 };
 use eth_types::{Field, Word};
-use halo2_proofs::{circuit::Value, plonk::Error};
+use halo2_proofs::{
+    circuit::Value,
+    plonk::{Error, Expression},
+};
 
 /// Constraints for the words a, n, r:
 /// a mod n = r, if n!=0
@@ -99,6 +102,67 @@ impl<F: Field> ModGadget<F> {
     }
 }
 
+/// Constraints for the words q, n, r, a of the DIV and MOD opcodes:
+/// q * n + r = a and r < n, if n!=0
+/// r = a,                   if n==0
+///
+/// When n==0 the quotient q is not constrained, the opcodes push 0 instead of q or r (see
+/// `n_is_zero`). Unlike [`ModGadget`] no auxiliary word is needed, as the equation
+/// q * n + r = a without overflow already forces r = a when n==0.
+/// The constraints on r and on the overflow only apply when `is_modulo` is 1, otherwise only
+/// q * n + r = a (mod 2^256) holds, so that MUL can share the multiplication.
+#[derive(Clone, Debug)]
+pub(crate) struct ModuloGadget<F> {
+    mul_add_words: MulAddWordsGadget<F>,
+    n_is_zero: IsZeroWordGadget<F, Word32Cell<F>>,
+    lt: LtWordGadget<F>,
+}
+impl<F: Field> ModuloGadget<F> {
+    pub(crate) fn construct(
+        cb: &mut EVMConstraintBuilder<F>,
+        words: [&Word32Cell<F>; 4],
+        is_modulo: Expression<F>,
+    ) -> Self {
+        let (n, r) = (words[1], words[2]);
+        let mul_add_words = MulAddWordsGadget::construct(cb, words);
+        let n_is_zero = IsZeroWordGadget::construct(cb, n);
+        let lt = LtWordGadget::construct(cb, &r.to_word(), &n.to_word());
+
+        cb.condition(is_modulo, |cb| {
+            cb.add_constraint(
+                "r < n when n != 0",
+                (1.expr() - lt.expr()) * (1.expr() - n_is_zero.expr()),
+            );
+            cb.require_zero("overflow == 0 for q * n + r", mul_add_words.overflow());
+        });
+
+        Self {
+            mul_add_words,
+            n_is_zero,
+            lt,
+        }
+    }
+
+    /// `1` when the divisor n is zero, `0` otherwise
+    pub(crate) fn n_is_zero(&self) -> Expression<F> {
+        self.n_is_zero.expr()
+    }
+
+    pub(crate) fn assign(
+        &self,
+        region: &mut CachedRegion<'_, '_, F>,
+        offset: usize,
+        words: [Word; 4],
+    ) -> Result<(), Error> {
+        let [_, n, r, _] = words;
+        self.mul_add_words.assign(region, offset, words)?;
+        self.n_is_zero.assign(region, offset, WordLoHi::from(n))?;
+        self.lt.assign(region, offset, r, n)?;
+
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{test_util::*, *};
@@ -238,4 +302,129 @@ mod tests {
             false,
         );
     }
+
+    #[derive(Clone)]
+    /// ModuloGadgetTestContainer: require(q * n + r == a && (r < n || n == 0))
+    struct ModuloGadgetTestContainer<F> {
+        modulo_gadget: ModuloGadget<F>,
+        words: [Word32Cell<F>; 4],
+    }
+
+    impl<F: Field> MathGadgetContainer<F> for ModuloGadgetTestContainer<F> {
+        fn configure_gadget_container(cb: &mut EVMConstraintBuilder<F>) -> Self {
+            let words = [(); 4].map(|_| cb.query_word32());
+            let modulo_gadget = ModuloGadget::<F>::construct(
+                cb,
+                [&words[0], &words[1], &words[2], &words[3]],
+                1.expr(),
+            );
+            ModuloGadgetTestContainer {
+                modulo_gadget,
+                words,
+            }
+        }
+
+        fn assign_gadget_container(
+            &self,
+            witnesses: &[Word],
+            region: &mut CachedRegion<'_, '_, F>,
+        ) -> Result<(), Error> {
+            let words: [Word; 4] = witnesses.try_into().unwrap();
+            let offset = 0;
+
+            for (cell, value) in self.words.iter().zip(words) {
+                cell.assign_u256(region, offset, value)?;
+            }
+            self.modulo_gadget.assign(region, offset, words)
+        }
+    }
+
+    #[test]
+    fn test_modulo_expected_rem() {
+        // q * n + r = a
+        for (q, n, r, a) in [(10, 50, 48, 548), (0, 50, 30, 30), (1, 1, 0, 1)] {
+            try_test!(
+                ModuloGadgetTestContainer<Fr>,
+                [q, n, r, a].map(Word::from),
+                true,
+            );
+        }
+        try_test!(
+            ModuloGadgetTestContainer<Fr>,
+            vec![
+                WORD_LOW_MAX / 1024,
+                Word::from(1024),
+                Word::from(1023),
+                WORD_LOW_MAX
+            ],
+            true,
+        );
+        try_test!(
+            ModuloGadgetTestContainer<Fr>,
+            vec![Word::one(), Word::MAX, Word::zero(), Word::MAX],
+            true,
+        );
+        // The divisor is not zero when only its high limb is
+        try_test!(
+            ModuloGadgetTestContainer<Fr>,
+            vec![
+                Word::from(3),
+                Word::one() << 128,
+                Word::from(5),
+                (Word::from(3) << 128) + 5
+            ],
+            true,
+        );
+        // A zero divisor leaves the dividend as the residue, whatever the quotient
+        for q in [Word::zero(), Word::from(7)] {
+            try_test!(
+                ModuloGadgetTestContainer<Fr>,
+                vec![q, Word::zero(), WORD_HIGH_MAX, WORD_HIGH_MAX],
+                true,
+            );
+        }
+    }
+
+    #[test]
+    fn test_modulo_unexpected_rem() {
+        // The residue is not smaller than the divisor
+        try_test!(
+            ModuloGadgetTestContainer<Fr>,
+            [9, 50, 98, 548].map(Word::from),
+            false,
+        );
+        try_test!(
+            ModuloGadgetTestContainer<Fr>,
+            [0, 1, 1, 1].map(Word::from),
+            false,
+        );
+        // A divisor >= 2^128 is not zero, the residue has to be smaller than it
+        try_test!(
+            ModuloGadgetTestContainer<Fr>,
+            vec![
+                Word::zero(),
+                Word::one() << 128,
+                WORD_HIGH_MAX,
+                WORD_HIGH_MAX
+            ],
+            false,
+        );
+        // A zero divisor with a residue other than the dividend
+        try_test!(
+            ModuloGadgetTestContainer<Fr>,
+            [0, 0, 0, 1].map(Word::from),
+            false,
+        );
+        // 2 = ((2^256 + 2) / 3) * 3 + 0 (mod 2^256), rejected by the overflow
+        try_test!(
+            ModuloGadgetTestContainer<Fr>,
+            vec![
+                U256::try_from(U512([2, 0, 0, 0, 1, 0, 0, 0]) / U512::from(3)).unwrap(),
+                Word::from(3),
+                Word::from(0),
+                Word::from(2),
+            ],
+            false,
+        );
+    }
 }
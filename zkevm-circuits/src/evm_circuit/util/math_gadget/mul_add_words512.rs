@@ -1,3 +1,5 @@
+#[cfg(test)]
+use crate::util::word::WordLoHi;
 use crate::{
     evm_circuit::util::{
         constraint_builder::{ConstrainBuilderCommon, EVMConstraintBuilder},
@@ -50,6 +52,8 @@ pub(crate) struct MulAddWords512Gadget<F> {
     carry_0: [Cell<F>; 9],
     carry_1: [Cell<F>; 9],
     carry_2: [Cell<F>; 9],
+    #[cfg(test)]
+    carry_word: WordLoHi<Expression<F>>,
 }
 
 impl<F: Field> MulAddWords512Gadget<F> {
@@ -77,7 +81,8 @@ impl<F: Field> MulAddWords512Gadget<F> {
             b_limbs.push(word4_b.limbs[i].expr());
         }
 
-        let (d_lo, d_hi) = words[2].to_word().to_lo_hi();
+        let carry_word = words[2].to_word();
+        let (d_lo, d_hi) = carry_word.to_lo_hi();
         let (e_lo, e_hi) = words[3].to_word().to_lo_hi();
 
         // Limb multiplication
@@ -136,9 +141,17 @@ impl<F: Field> MulAddWords512Gadget<F> {
             carry_0,
             carry_1,
             carry_2,
+            #[cfg(test)]
+            carry_word,
         }
     }
 
+    /// The high 256 bits `d` of `a * b + c`
+    #[cfg(test)]
+    pub(crate) fn carry_word(&self) -> WordLoHi<Expression<F>> {
+        self.carry_word.clone()
+    }
+
     pub(crate) fn assign(
         &self,
         region: &mut CachedRegion<'_, '_, F>,
@@ -212,6 +225,7 @@ mod tests {
         d: Word32Cell<F>,
         e: Word32Cell<F>,
         addend: Word32Cell<F>,
+        carry: Word32Cell<F>,
     }
 
     impl<F: Field> MathGadgetContainer<F> for MulAddWords512GadgetContainer<F> {
@@ -221,8 +235,14 @@ mod tests {
             let d = cb.query_word32();
             let e = cb.query_word32();
             let addend = cb.query_word32();
+            let carry = cb.query_word32();
             let math_gadget =
                 MulAddWords512Gadget::<F>::construct(cb, [&a, &b, &d, &e], Some(&addend));
+            cb.require_equal_word(
+                "carry_word is the high word",
+                math_gadget.carry_word(),
+                carry.to_word(),
+            );
             MulAddWords512GadgetContainer {
                 math_gadget,
                 a,
@@ -230,6 +250,7 @@ mod tests {
                 d,
                 e,
                 addend,
+                carry,
             }
         }
 
@@ -244,6 +265,7 @@ mod tests {
             self.d.assign_u256(region, offset, witnesses[2])?;
             self.e.assign_u256(region, offset, witnesses[3])?;
             self.addend.assign_u256(region, offset, witnesses[4])?;
+            self.carry.assign_u256(region, offset, witnesses[2])?;
             self.math_gadget.assign(
                 region,
                 offset,
@@ -356,4 +378,44 @@ mod tests {
             false,
         );
     }
+
+    #[test]
+    fn test_muladd512_max_carry_word() {
+        // max * max + 0 == (max - 1) * 2**256 + 1
+        try_test!(
+            MulAddWords512GadgetContainer<Fr>,
+            vec![
+                Word::MAX,
+                Word::MAX,
+                Word::MAX - 1,
+                Word::from(1),
+                Word::from(0)
+            ],
+            true,
+        );
+        // max * max + max == max * 2**256 + 0
+        try_test!(
+            MulAddWords512GadgetContainer<Fr>,
+            vec![Word::MAX, Word::MAX, Word::MAX, Word::from(0), Word::MAX],
+            true,
+        );
+        // max * max + 0 != max * 2**256 + 1
+        try_test!(
+            MulAddWords512GadgetContainer<Fr>,
+            vec![
+                Word::MAX,
+                Word::MAX,
+                Word::MAX,
+                Word::from(1),
+                Word::from(0)
+            ],
+            false,
+        );
+        // max * max + max != (max - 1) * 2**256 + max
+        try_test!(
+            MulAddWords512GadgetContainer<Fr>,
+            vec![Word::MAX, Word::MAX, Word::MAX - 1, Word::MAX, Word::MAX],
+            false,
+        );
+    }
 }
@@ -0,0 +1,160 @@
+use crate::{
+    evm_circuit::util::{
+        constraint_builder::{ConstrainBuilderCommon, EVMConstraintBuilder},
+        sum,
+    },
+    util::Expr,
+};
+use eth_types::Field;
+use halo2_proofs::plonk::Expression;
+
+/// Requires `selectors` to be a one-hot vector: every selector is `0` or `1`
+/// and exactly one of them is `1`. Returns the index of the selector that is
+/// set.
+#[derive(Clone, Debug)]
+pub struct OneHotGadget<F, const N: usize> {
+    selectors: [Expression<F>; N],
+}
+
+impl<F: Field, const N: usize> OneHotGadget<F, N> {
+    pub(crate) fn construct(
+        cb: &mut EVMConstraintBuilder<F>,
+        selectors: [Expression<F>; N],
+    ) -> Self {
+        for selector in selectors.iter() {
+            cb.require_boolean("selector is boolean", selector.clone());
+        }
+        cb.require_equal(
+            "exactly one selector is set",
+            sum::expr(&selectors),
+            1.expr(),
+        );
+
+        Self { selectors }
+    }
+
+    /// Index of the selector that is set
+    #[cfg(test)]
+    pub(crate) fn expr(&self) -> Expression<F> {
+        sum::expr(
+            self.selectors
+                .iter()
+                .enumerate()
+                .map(|(idx, selector)| idx.expr() * selector.clone()),
+        )
+    }
+
+    pub(crate) fn selectors(&self) -> [Expression<F>; N] {
+        self.selectors.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::test_util::*, *};
+    use crate::evm_circuit::util::{CachedRegion, Cell};
+    use eth_types::*;
+    use halo2_proofs::{halo2curves::bn256::Fr, plonk::Error};
+
+    #[derive(Clone)]
+    /// OneHotTestContainer: require(selectors is one-hot && index == expected)
+    struct OneHotTestContainer<F, const N: usize> {
+        one_hot_gadget: OneHotGadget<F, N>,
+        selectors: [Cell<F>; N],
+        index: Cell<F>,
+    }
+
+    impl<F: Field, const N: usize> MathGadgetContainer<F> for OneHotTestContainer<F, N> {
+        fn configure_gadget_container(cb: &mut EVMConstraintBuilder<F>) -> Self {
+            let selectors = [(); N].map(|_| cb.query_cell());
+            let index = cb.query_cell();
+            let one_hot_gadget =
+                OneHotGadget::<F, N>::construct(cb, selectors.clone().map(|cell| cell.expr()));
+            cb.require_equal("index is correct", one_hot_gadget.expr(), index.expr());
+
+            OneHotTestContainer {
+                one_hot_gadget,
+                selectors,
+                index,
+            }
+        }
+
+        fn assign_gadget_container(
+            &self,
+            witnesses: &[Word],
+            region: &mut CachedRegion<'_, '_, F>,
+        ) -> Result<(), Error> {
+            let offset = 0;
+            for (cell, value) in self.selectors.iter().zip(witnesses.iter()) {
+                cell.assign(region, offset, Value::known(value.to_scalar().unwrap()))?;
+            }
+            self.index.assign(
+                region,
+                offset,
+                Value::known(witnesses[N].to_scalar().unwrap()),
+            )?;
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_onehot_valid() {
+        try_test!(
+            OneHotTestContainer<Fr, 3>,
+            vec![Word::from(1), Word::from(0), Word::from(0), Word::from(0)],
+            true,
+        );
+        try_test!(
+            OneHotTestContainer<Fr, 3>,
+            vec![Word::from(0), Word::from(1), Word::from(0), Word::from(1)],
+            true,
+        );
+        try_test!(
+            OneHotTestContainer<Fr, 3>,
+            vec![Word::from(0), Word::from(0), Word::from(1), Word::from(2)],
+            true,
+        );
+    }
+
+    #[test]
+    fn test_onehot_wrong_index() {
+        try_test!(
+            OneHotTestContainer<Fr, 3>,
+            vec![Word::from(0), Word::from(1), Word::from(0), Word::from(2)],
+            false,
+        );
+    }
+
+    #[test]
+    fn test_onehot_all_zero() {
+        try_test!(
+            OneHotTestContainer<Fr, 3>,
+            vec![Word::from(0), Word::from(0), Word::from(0), Word::from(0)],
+            false,
+        );
+    }
+
+    #[test]
+    fn test_onehot_two_hot() {
+        try_test!(
+            OneHotTestContainer<Fr, 3>,
+            vec![Word::from(1), Word::from(1), Word::from(0), Word::from(1)],
+            false,
+        );
+        try_test!(
+            OneHotTestContainer<Fr, 3>,
+            vec![Word::from(0), Word::from(1), Word::from(1), Word::from(3)],
+            false,
+        );
+    }
+
+    #[test]
+    fn test_onehot_not_boolean() {
+        try_test!(
+            OneHotTestContainer<Fr, 3>,
+            vec![Word::from(2), Word::from(0), Word::from(0), Word::from(0)],
+            false,
+        );
+    }
+}
@@ -0,0 +1,187 @@
+use crate::{
+    evm_circuit::{
+        param::N_BYTES_WORD,
+        util::{
+            constraint_builder::{ConstrainBuilderCommon, EVMConstraintBuilder},
+            math_gadget::{AddWordsGadget, LtGadget},
+            CachedRegion,
+        },
+    },
+    util::{
+        word::{Word32Cell, WordExpr},
+        Expr,
+    },
+};
+use eth_types::{Field, ToLittleEndian, Word};
+use gadgets::util::sum;
+use halo2_proofs::plonk::{Error, Expression};
+
+/// Sign and magnitude of a 256-bit word in two's complement: the word is negative when its most
+/// significant byte is at least 128, and its magnitude is then `2^256 - x`. Shared by the opcodes
+/// operating on signed words.
+/// The magnitude of `-2^255` is `2^255`, which is `-2^255` again as a word.
+#[derive(Clone, Debug)]
+pub(crate) struct SignWordGadget<F> {
+    is_neg: LtGadget<F, 1>,
+    magnitude: Word32Cell<F>,
+    sum: Word32Cell<F>,
+    add_words: AddWordsGadget<F, 2, false>,
+}
+
+impl<F: Field> SignWordGadget<F> {
+    pub(crate) fn construct(cb: &mut EVMConstraintBuilder<F>, x: &Word32Cell<F>) -> Self {
+        // The limbs are little-endian, the most significant byte is the last one
+        let is_neg = LtGadget::construct(cb, 127.expr(), x.limbs[N_BYTES_WORD - 1].expr());
+        let magnitude = cb.query_word32();
+        let sum = cb.query_word32();
+        let (x_lo, x_hi) = x.to_word().to_lo_hi();
+        let (magnitude_lo, magnitude_hi) = magnitude.to_word().to_lo_hi();
+
+        cb.add_constraint(
+            "magnitude_lo == x_lo when x >= 0",
+            (1.expr() - is_neg.expr()) * (magnitude_lo.expr() - x_lo.expr()),
+        );
+        cb.add_constraint(
+            "magnitude_hi == x_hi when x >= 0",
+            (1.expr() - is_neg.expr()) * (magnitude_hi.expr() - x_hi.expr()),
+        );
+
+        // When `is_neg`, constrain `sum == 0` and `carry == 1`. Since the final
+        // result is `1 << 256`.
+        let add_words = AddWordsGadget::construct(cb, [x.clone(), magnitude.clone()], sum.clone());
+        cb.add_constraint(
+            "sum == 0 when x < 0",
+            is_neg.expr() * sum::expr(add_words.sum().to_word_n::<N_BYTES_WORD>().limbs),
+        );
+        cb.add_constraint(
+            "carry_hi == 1 when x < 0",
+            is_neg.expr() * (1.expr() - add_words.carry().as_ref().unwrap().expr()),
+        );
+
+        Self {
+            is_neg,
+            magnitude,
+            sum,
+            add_words,
+        }
+    }
+
+    /// `1` when the word is negative, `0` otherwise
+    pub(crate) fn is_negative(&self) -> Expression<F> {
+        self.is_neg.expr()
+    }
+
+    /// The word when it's non-negative, `2^256 - x` when it's negative
+    pub(crate) fn magnitude(&self) -> &Word32Cell<F> {
+        &self.magnitude
+    }
+
+    pub(crate) fn assign(
+        &self,
+        region: &mut CachedRegion<'_, '_, F>,
+        offset: usize,
+        x: Word,
+        magnitude: Word,
+    ) -> Result<(), Error> {
+        self.is_neg.assign(
+            region,
+            offset,
+            127.into(),
+            u64::from(x.to_le_bytes()[N_BYTES_WORD - 1]).into(),
+        )?;
+        self.magnitude.assign_u256(region, offset, magnitude)?;
+        let sum = x.overflowing_add(magnitude).0;
+        self.sum.assign_u256(region, offset, sum)?;
+        self.add_words.assign(region, offset, [x, magnitude], sum)
+    }
+}
+
+/// Magnitude of `x` in two's complement, the value of [`SignWordGadget::magnitude`]
+pub(crate) fn word_magnitude(x: Word) -> Word {
+    if 127 < x.to_le_bytes()[N_BYTES_WORD - 1] {
+        (!x).overflowing_add(Word::one()).0
+    } else {
+        x
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::test_util::*, *};
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    #[derive(Clone)]
+    /// SignWordTestContainer: require(is_negative(x) == IS_NEG)
+    struct SignWordTestContainer<F, const IS_NEG: bool> {
+        sign_word_gadget: SignWordGadget<F>,
+        x: Word32Cell<F>,
+    }
+
+    impl<F: Field, const IS_NEG: bool> MathGadgetContainer<F> for SignWordTestContainer<F, IS_NEG> {
+        fn configure_gadget_container(cb: &mut EVMConstraintBuilder<F>) -> Self {
+            let x = cb.query_word32();
+            let sign_word_gadget = SignWordGadget::construct(cb, &x);
+            cb.require_equal(
+                "is_negative is correct",
+                sign_word_gadget.is_negative(),
+                IS_NEG.expr(),
+            );
+
+            SignWordTestContainer {
+                sign_word_gadget,
+                x,
+            }
+        }
+
+        fn assign_gadget_container(
+            &self,
+            witnesses: &[Word],
+            region: &mut CachedRegion<'_, '_, F>,
+        ) -> Result<(), Error> {
+            let offset = 0;
+            let x = witnesses[0];
+            let magnitude = witnesses
+                .get(1)
+                .copied()
+                .unwrap_or_else(|| word_magnitude(x));
+            self.x.assign_u256(region, offset, x)?;
+            self.sign_word_gadget.assign(region, offset, x, magnitude)?;
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_sign_word_positive() {
+        for x in [Word::zero(), Word::one(), WORD_LOW_MAX, WORD_SIGNED_MAX] {
+            try_test!(SignWordTestContainer<Fr, false>, [x], true);
+            try_test!(SignWordTestContainer<Fr, true>, [x], false);
+        }
+    }
+
+    #[test]
+    fn test_sign_word_negative() {
+        for x in [Word::MAX, WORD_HIGH_MAX, WORD_SIGNED_MIN] {
+            try_test!(SignWordTestContainer<Fr, true>, [x], true);
+            try_test!(SignWordTestContainer<Fr, false>, [x], false);
+        }
+    }
+
+    #[test]
+    fn test_sign_word_magnitude() {
+        try_test!(SignWordTestContainer<Fr, false>, [Word::zero(), Word::zero()], true);
+        try_test!(SignWordTestContainer<Fr, false>, [WORD_SIGNED_MAX, WORD_SIGNED_MAX], true);
+        // -2^255 has no positive counterpart, its magnitude is -2^255 again
+        try_test!(SignWordTestContainer<Fr, true>, [WORD_SIGNED_MIN, WORD_SIGNED_MIN], true);
+        try_test!(SignWordTestContainer<Fr, true>, [Word::MAX, Word::one()], true);
+    }
+
+    #[test]
+    fn test_sign_word_wrong_magnitude() {
+        try_test!(SignWordTestContainer<Fr, false>, [Word::one(), Word::from(2)], false);
+        try_test!(SignWordTestContainer<Fr, false>, [WORD_SIGNED_MAX, Word::zero()], false);
+        // The magnitude of -1 is 1, not the word itself
+        try_test!(SignWordTestContainer<Fr, true>, [Word::MAX, Word::MAX], false);
+        try_test!(SignWordTestContainer<Fr, true>, [WORD_SIGNED_MIN, Word::zero()], false);
+    }
+}
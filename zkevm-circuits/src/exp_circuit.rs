@@ -493,6 +493,17 @@ impl<F: Field> ExpCircuit<F> {
             _marker: PhantomData::default(),
         }
     }
+
+    /// Return a new ExpCircuit proving the exponentiations `base ^ exponent (mod 2^256)` of
+    /// `pairs`, without an EVM trace. The events are identified by their position in `pairs`.
+    pub fn from_pairs(pairs: Vec<(U256, U256)>, max_exp_steps: usize) -> Self {
+        let exp_events = pairs
+            .into_iter()
+            .enumerate()
+            .map(|(idx, (base, exponent))| ExpEvent::new(idx + 1, base, exponent))
+            .collect();
+        Self::new(exp_events, max_exp_steps)
+    }
 }
 
 impl<F: Field> SubCircuit<F> for ExpCircuit<F> {
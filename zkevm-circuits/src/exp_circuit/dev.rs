@@ -1,5 +1,7 @@
 pub use super::ExpCircuit;
 
+#[cfg(feature = "stats")]
+use crate::util::circuit_stats_json;
 use crate::{
     exp_circuit::ExpCircuitConfig,
     table::ExpTable,
@@ -35,3 +37,14 @@ impl<F: Field> Circuit<F> for ExpCircuit<F> {
         self.synthesize_sub(&config, &challenges, &mut layouter)
     }
 }
+
+impl<F: Field> ExpCircuit<F> {
+    /// Returns the statistics of the circuit configuration as JSON, estimating the rows from the
+    /// exp events of the circuit.
+    #[cfg(feature = "stats")]
+    pub fn circuit_stats_json(&self) -> serde_json::Value {
+        let num_rows =
+            ExpCircuitConfig::<F>::min_num_rows(&self.exp_events) + Self::unusable_rows();
+        circuit_stats_json::<F, Self>((), num_rows)
+    }
+}
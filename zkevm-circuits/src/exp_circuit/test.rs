@@ -1,6 +1,7 @@
 use crate::{
     evm_circuit::witness::{block_convert, Block},
     exp_circuit::ExpCircuit,
+    test_util::block_for_bytecode,
     util::{unusable_rows, SubCircuit},
 };
 use bus_mapping::{
@@ -19,6 +20,25 @@ fn exp_circuit_unusable_rows() {
     )
 }
 
+#[cfg(feature = "stats")]
+#[test]
+fn exp_circuit_stats_json() {
+    let block = block_for_bytecode(gen_code_single(2.into(), 2.into()));
+    let circuit = ExpCircuit::<Fr>::new_from_block(&block);
+    let stats = circuit.circuit_stats_json();
+    for key in [
+        "gates",
+        "lookups",
+        "advice_columns",
+        "fixed_columns",
+        "degree",
+        "rows",
+    ] {
+        assert!(stats.get(key).is_some(), "missing key {}", key);
+    }
+    assert!(stats["rows"].as_u64().unwrap() > ExpCircuit::<Fr>::unusable_rows() as u64);
+}
+
 /// Test exponentiation circuit with the provided block witness
 pub fn test_exp_circuit<F: Field>(k: u32, block: Block<F>) {
     let circuit = ExpCircuit::<F>::new(
@@ -47,38 +67,26 @@ fn gen_code_multiple(args: Vec<(Word, Word)>) -> Bytecode {
     code
 }
 
-fn gen_data(code: Bytecode, default_params: bool) -> CircuitInputBuilder<FixedCParams> {
+// Needs default parameters for variadic size test
+fn gen_data_with_default_params(code: Bytecode) -> CircuitInputBuilder<FixedCParams> {
     let test_ctx = TestContext::<2, 1>::simple_ctx_with_bytecode(code).unwrap();
     let block: GethData = test_ctx.into();
-    // Needs default parameters for variadic size test
-    let builder = if default_params {
-        let mut builder =
-            BlockData::new_from_geth_data_with_params(block.clone(), FixedCParams::default())
-                .new_circuit_input_builder();
-        builder
-            .handle_block(&block.eth_block, &block.geth_traces)
-            .unwrap();
-        builder
-    } else {
-        let builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
-        builder
-            .handle_block(&block.eth_block, &block.geth_traces)
-            .unwrap()
-    };
+    let mut builder =
+        BlockData::new_from_geth_data_with_params(block.clone(), FixedCParams::default())
+            .new_circuit_input_builder();
+    builder
+        .handle_block(&block.eth_block, &block.geth_traces)
+        .unwrap();
     builder
 }
 
 fn test_ok(base: Word, exponent: Word, k: Option<u32>) {
-    let code = gen_code_single(base, exponent);
-    let builder = gen_data(code, false);
-    let block = block_convert::<Fr>(&builder).unwrap();
+    let block = block_for_bytecode(gen_code_single(base, exponent));
     test_exp_circuit(k.unwrap_or(18), block);
 }
 
 fn test_ok_multiple(args: Vec<(Word, Word)>) {
-    let code = gen_code_multiple(args);
-    let builder = gen_data(code, false);
-    let block = block_convert::<Fr>(&builder).unwrap();
+    let block = block_for_bytecode(gen_code_multiple(args));
     test_exp_circuit(20, block);
 }
 
@@ -103,6 +111,79 @@ fn exp_circuit_big() {
     );
 }
 
+#[test]
+fn exp_circuit_wrapping() {
+    // (base, exponent, base^exponent mod 2^256), the true results exceed 2^256
+    let two_pow_255 = Word::one() << 255;
+    for (base, exponent, result) in [
+        (Word::from(2), Word::from(256), Word::zero()),
+        (Word::from(2), Word::from(257), Word::zero()),
+        (Word::one() << 128, Word::from(2), Word::zero()),
+        (Word::MAX, Word::from(2), Word::one()),
+        (Word::MAX, Word::from(3), Word::MAX),
+        (
+            Word::from(3),
+            Word::from(200),
+            Word::from(3).overflowing_pow(200.into()).0,
+        ),
+        (two_pow_255 + 1, Word::from(2), Word::one()),
+    ] {
+        let block = block_for_bytecode(gen_code_single(base, exponent));
+        assert_eq!(block.exp_events[0].exponentiation, result);
+        test_exp_circuit(18, block);
+    }
+
+    // The result of the last multiplication has to be reduced modulo 2^256
+    let mut block = block_for_bytecode(gen_code_single(3.into(), 200.into()));
+    let result = block.exp_events[0].exponentiation;
+    let step = block.exp_events[0]
+        .steps
+        .iter_mut()
+        .find(|step| step.d == result)
+        .unwrap();
+    step.d = result + 1;
+    let circuit = ExpCircuit::<Fr>::new(
+        block.exp_events.clone(),
+        block.circuits_params.max_exp_steps,
+    );
+    let prover = MockProver::<Fr>::run(18, &circuit, vec![]).unwrap();
+    assert!(prover.verify().is_err());
+}
+
+#[test]
+fn exp_circuit_word_boundary() {
+    // (base, exponent, base^exponent mod 2^256) around the first power exceeding 2^256
+    let two_pow_128 = Word::one() << 128;
+    let two_pow_255 = Word::one() << 255;
+    let cases = [
+        (Word::from(2), Word::from(255), two_pow_255),
+        (Word::from(2), Word::from(256), Word::zero()),
+        (two_pow_255, Word::one(), two_pow_255),
+        (two_pow_255, Word::from(2), Word::zero()),
+        (Word::one() << 85, Word::from(3), two_pow_255),
+        (Word::one() << 86, Word::from(3), Word::zero()),
+        (two_pow_128, Word::from(2), Word::zero()),
+        // 2^256 - 2^129 + 1, the largest square below 2^256
+        (
+            two_pow_128 - 1,
+            Word::from(2),
+            Word::MAX - (Word::one() << 129) + 2,
+        ),
+    ];
+    let block = block_for_bytecode(gen_code_multiple(
+        cases
+            .iter()
+            .map(|(base, exponent, _)| (*base, *exponent))
+            .collect(),
+    ));
+    assert_eq!(block.exp_events.len(), cases.len());
+    for (event, (base, exponent, result)) in block.exp_events.iter().zip(cases) {
+        assert_eq!((event.base, event.exponent), (base, exponent));
+        assert_eq!(event.exponentiation, result, "{base:#x}^{exponent}");
+    }
+    test_exp_circuit(20, block);
+}
+
 #[test]
 fn exp_circuit_multiple() {
     test_ok_multiple(vec![
@@ -116,6 +197,40 @@ fn exp_circuit_multiple() {
     ]);
 }
 
+#[test]
+fn exp_circuit_from_pairs() {
+    let pairs: Vec<(Word, Word)> = vec![
+        (3.into(), 7.into()),
+        (5.into(), 11.into()),
+        (7.into(), 13.into()),
+        (11.into(), 17.into()),
+        (13.into(), 23.into()),
+        (29.into(), 43.into()),
+        (41.into(), 259.into()),
+    ];
+    let block = block_for_bytecode(gen_code_multiple(pairs.clone()));
+    let max_exp_steps = block.circuits_params.max_exp_steps;
+    let circuit = ExpCircuit::<Fr>::from_pairs(pairs, max_exp_steps);
+
+    // Same events as the trace, up to the identifiers
+    assert_eq!(circuit.exp_events.len(), block.exp_events.len());
+    for (event, traced) in circuit.exp_events.iter().zip(block.exp_events.iter()) {
+        assert_eq!(
+            (event.base, event.exponent, event.exponentiation),
+            (traced.base, traced.exponent, traced.exponentiation)
+        );
+        assert_eq!(event.steps, traced.steps);
+    }
+
+    let k = 20;
+    let prover = MockProver::<Fr>::run(k, &circuit, vec![]).unwrap();
+    prover.assert_satisfied();
+    let traced_circuit = ExpCircuit::<Fr>::new(block.exp_events, max_exp_steps);
+    let traced_prover = MockProver::<Fr>::run(k, &traced_circuit, vec![]).unwrap();
+    assert_eq!(prover.fixed(), traced_prover.fixed());
+    assert_eq!(prover.permutation(), traced_prover.permutation());
+}
+
 #[test]
 fn variadic_size_check() {
     let k = 13;
@@ -146,7 +261,7 @@ fn variadic_size_check() {
         EXP
         STOP
     };
-    let builder = gen_data(code, true);
+    let builder = gen_data_with_default_params(code);
     let block = block_convert::<Fr>(&builder).unwrap();
     let circuit = ExpCircuit::<Fr>::new(
         block.exp_events.clone(),
@@ -1017,13 +1017,8 @@ impl<F: Field> SubCircuit<F> for KeccakCircuit<F> {
 
     /// Return the minimum number of rows required to prove the block
     fn min_num_rows_block(block: &witness::Block<F>) -> (usize, usize) {
-        let rows_per_chunk = (NUM_ROUNDS + 1) * get_num_rows_per_round();
         (
-            block
-                .keccak_inputs
-                .iter()
-                .map(|bytes| (bytes.len() as f64 / 136.0).ceil() as usize * rows_per_chunk)
-                .sum(),
+            Self::num_rows_for_inputs(&block.keccak_inputs),
             block.circuits_params.max_keccak_rows,
         )
     }
@@ -1061,6 +1056,19 @@ impl<F: Field> KeccakCircuit<F> {
         }
     }
 
+    /// The rows of the keccak_f's hashing `inputs`, every input being padded to a multiple of
+    /// `RATE` bytes
+    fn num_rows_for_inputs(inputs: &[Vec<u8>]) -> usize {
+        let num_keccak_f: usize = inputs.iter().map(|bytes| bytes.len() / RATE + 1).sum();
+        num_keccak_f * ((NUM_ROUNDS + 1) * get_num_rows_per_round())
+    }
+
+    /// The minimum number of rows needed for the capacity to hash `inputs`
+    pub fn min_num_rows(inputs: &[Vec<u8>]) -> usize {
+        // Add two for unusable rows
+        Self::num_rows_for_inputs(inputs) + 2 * ((NUM_ROUNDS + 1) * get_num_rows_per_round())
+    }
+
     /// Sets the witness using the data to be hashed
     pub(crate) fn generate_witness(&self, challenges: Challenges<Value<F>>) -> Vec<KeccakRow<F>> {
         multi_keccak(self.inputs.as_slice(), challenges, self.capacity())
@@ -1,8 +1,10 @@
 //! The MPT circuit implementation.
-use eth_types::Field;
+use eth_types::{Field, H256, U256};
 use gadgets::{impl_expr, util::Scalar};
+#[cfg(any(test, feature = "test-circuits"))]
+use halo2_proofs::dev::{CellValue, MockProver};
 use halo2_proofs::{
-    circuit::{Layouter, SimpleFloorPlanner, Value},
+    circuit::{Layouter, Region, SimpleFloorPlanner, Value},
     plonk::{
         Advice, Circuit, Column, ConstraintSystem, Error, Expression, Fixed, SecondPhase,
         VirtualCells,
@@ -23,18 +25,20 @@ mod param;
 mod rlp_gadgets;
 mod start;
 mod storage_leaf;
+/// Reference MPT proof verifier
+pub mod verifier;
 /// MPT witness row
 pub mod witness_row;
 
 use self::{
     account_leaf::AccountLeafConfig,
-    helpers::RLPItemView,
-    param::RLP_UNIT_NUM_BYTES,
+    helpers::{rlp_decode_len, RLPItemView},
+    param::{EMPTY_CODE_HASH, RLP_SHORT, RLP_UNIT_NUM_BYTES},
     rlp_gadgets::decode_rlp,
     witness_row::{
-        AccountRowType, ExtensionBranchRowType, Node, StartRowType, StorageRowType,
-        NODE_RLP_TYPES_ACCOUNT, NODE_RLP_TYPES_BRANCH, NODE_RLP_TYPES_START,
-        NODE_RLP_TYPES_STORAGE,
+        key_row_nibbles, leaf_key, rlp_payload, AccountRowType, ExtensionBranchRowType, Node,
+        StartRowType, StorageRowType, NODE_RLP_TYPES_ACCOUNT, NODE_RLP_TYPES_BRANCH,
+        NODE_RLP_TYPES_START, NODE_RLP_TYPES_STORAGE,
     },
 };
 use crate::{
@@ -45,12 +49,13 @@ use crate::{
         memory::{Memory, RwBank},
     },
     mpt_circuit::{
-        helpers::{MPTConstraintBuilder, MainRLPGadget, MptCellType, MptTableType},
+        helpers::{Indexable, MPTConstraintBuilder, MainRLPGadget, MptCellType, MptTableType},
         start::StartConfig,
         storage_leaf::StorageLeafConfig,
     },
     table::{KeccakTable, MPTProofType, MptTable},
-    util::Challenges,
+    util::{Challenges, SubCircuit, SubCircuitConfig},
+    witness,
 };
 
 use extension_branch::ExtensionBranchConfig;
@@ -131,6 +136,16 @@ impl<F: Field> StateMachineConfig<F> {
 
 type MptMemory<F> = Memory<F, MptCellType, RwBank<F, MptCellType>>;
 
+/// Height of the cell manager of the state machine, all its columns are queried at this many
+/// rotations.
+const STATE_HEIGHT: usize = 50;
+
+/// log2 of the height of the MPT circuits proven with the `MockProver`, by the tests and by
+/// [`verifier::verify_account_proof`]
+pub(crate) const MOCK_DEGREE: usize = 15;
+/// Maximal number of nodes of the MPT circuits proven with the `MockProver`
+pub(crate) const MOCK_MAX_NODES: usize = 520;
+
 /// Merkle Patricia Trie context
 #[derive(Clone, Debug)]
 pub struct MPTContext<F: Field> {
@@ -167,6 +182,18 @@ impl<F: Field> MPTContext<F> {
     ) -> RLPItemView<F> {
         self.rlp_item.create_view(meta, cb, idx, item_type)
     }
+
+    /// The RLP item on the `row` row of a storage leaf node, with the item type of that row in
+    /// the storage leaf layout (`NODE_RLP_TYPES_STORAGE`).
+    pub(crate) fn storage_item(
+        &self,
+        meta: &mut VirtualCells<F>,
+        cb: &mut MPTConstraintBuilder<F>,
+        row: StorageRowType,
+    ) -> RLPItemView<F> {
+        let idx = row as usize;
+        self.rlp_item(meta, cb, idx, NODE_RLP_TYPES_STORAGE[idx])
+    }
 }
 
 /// Merkle Patricia Trie config.
@@ -208,14 +235,33 @@ pub enum FixedTableTag {
 }
 impl_expr!(FixedTableTag);
 
-impl<F: Field> MPTConfig<F> {
+/// Circuit configuration arguments
+pub struct MPTConfigArgs<F: Field> {
+    /// KeccakTable
+    pub keccak_table: KeccakTable,
+    /// Challenges randomness
+    pub challenges: Challenges<Expression<F>>,
+    /// Circuit parameters
+    pub params: MPTCircuitParams,
+}
+
+impl<F: Field> SubCircuitConfig<F> for MPTConfig<F> {
+    type ConfigArgs = MPTConfigArgs<F>;
+
     /// Configure MPT Circuit
-    pub fn new(
+    fn new(
         meta: &mut ConstraintSystem<F>,
-        challenges: Challenges<Expression<F>>,
-        keccak_table: KeccakTable,
-        params: MPTCircuitParams,
+        MPTConfigArgs {
+            keccak_table,
+            challenges,
+            params,
+        }: Self::ConfigArgs,
     ) -> Self {
+        assert!(
+            params.randomness.is_allowed(MPTRandomness::FIXED_ALLOWED),
+            "a fixed MPT randomness is only allowed in tests"
+        );
+
         let q_enable = meta.fixed_column();
         let q_first = meta.fixed_column();
 
@@ -251,7 +297,7 @@ impl<F: Field> MPTConfig<F> {
         rlp_cm.add_columns(meta, &mut cb.base, lu(MptTableType::Fixed), 2, false, 4);
         rlp_cm.add_columns(meta, &mut cb.base, lu(MptTableType::Mult), 2, false, 2);
 
-        let mut state_cm = CellManager::new(50, 0);
+        let mut state_cm = CellManager::new(STATE_HEIGHT, 0);
         state_cm.add_columns(meta, &mut cb.base, MptCellType::StoragePhase1, 0, false, 20);
         state_cm.add_columns(meta, &mut cb.base, MptCellType::StoragePhase2, 1, false, 6);
         state_cm.add_columns(meta, &mut cb.base, MptCellType::StoragePhase3, 2, false, 5);
@@ -309,6 +355,10 @@ impl<F: Field> MPTConfig<F> {
                     ifx! {f!(q_first) => {
                         require!(a!(state_machine.is_start) => true);
                     }};
+                    // Storage leaves are not allowed when the storage leaf constraints are omitted
+                    if !params.is_storage_enabled() {
+                        require!(a!(state_machine.is_storage) => false);
+                    }
                     // Main state machine
                     matchx! {(
                         a!(state_machine.is_start) => {
@@ -335,7 +385,9 @@ impl<F: Field> MPTConfig<F> {
                         a!(state_machine.is_storage) => {
                             state_machine.step_constraints(meta, &mut cb, StorageRowType::Count as usize);
                             cb.base.push_region(MPTRegion::Storage as usize, StorageRowType::Count as usize);
-                            state_machine.storage_config = StorageLeafConfig::configure(meta, &mut cb, &mut ctx);
+                            if params.is_storage_enabled() {
+                                state_machine.storage_config = StorageLeafConfig::configure(meta, &mut cb, &mut ctx);
+                            }
                             ctx.memory.build_constraints(&mut cb.base, f!(q_first));
                             cb.base.pop_region();
                         },
@@ -380,7 +432,9 @@ impl<F: Field> MPTConfig<F> {
             cb,
         }
     }
+}
 
+impl<F: Field> MPTConfig<F> {
     /// Make the assignments to the MPTCircuit
     pub fn assign(
         &self,
@@ -390,129 +444,136 @@ impl<F: Field> MPTConfig<F> {
     ) -> Result<(), Error> {
         layouter.assign_region(
             || "MPT",
-            |mut region| {
-                let mut keccak_r = F::ZERO;
-                challenges.keccak_input().map(|v| keccak_r = v);
-
-                let mut memory = self.memory.clone();
-
-                let mut offset = 0;
-                for node in nodes.iter() {
-                    //println!("offset: {}", offset);
-                    let mut cached_region = CachedRegion::new(
-                        &mut region,
-                        keccak_r,
-                    );
-                    cached_region.annotate_columns(&self.cell_columns);
-
-                    let item_types = if node.start.is_some() {
-                        NODE_RLP_TYPES_START.to_vec()
-                    } else if node.extension_branch.is_some() {
-                        NODE_RLP_TYPES_BRANCH.to_vec()
-                    } else if node.account.is_some() {
-                        NODE_RLP_TYPES_ACCOUNT.to_vec()
-                    } else if node.storage.is_some() {
-                        NODE_RLP_TYPES_STORAGE.to_vec()
-                    } else {
-                        unreachable!()
-                    };
+            |mut region| self.assign_nodes(&mut region, nodes, challenges),
+        )
+    }
 
-                    // Assign bytes
-                    let mut rlp_values = Vec::new();
-                    // Decompose RLP
-                    for (idx, (bytes, item_type)) in node.values.iter().zip(item_types.iter()).enumerate() {
-                        cached_region.push_region(offset + idx, MPTRegion::RLP as usize);
-                        let rlp_value = self.rlp_item.assign(
-                            &mut cached_region,
-                            offset + idx,
-                            bytes,
-                            *item_type,
-                        )?;
-                        rlp_values.push(rlp_value);
-                        cached_region.pop_region();
-                    }
+    /// Assigns `nodes` to `region` from its first row, together with the selectors of the
+    /// `max_nodes` rows of the circuit
+    fn assign_nodes(
+        &self,
+        region: &mut Region<'_, F>,
+        nodes: &[Node],
+        challenges: &Challenges<Value<F>>,
+    ) -> Result<(), Error> {
+        let mut keccak_r = F::ZERO;
+        challenges.keccak_input().map(|v| keccak_r = v);
+
+        let mut memory = self.memory.clone();
+
+        let mut offset = 0;
+        for node in nodes.iter() {
+            // println!("offset: {}", offset);
+            let mut cached_region = CachedRegion::new(region, keccak_r);
+            cached_region.annotate_columns(&self.cell_columns);
+
+            let item_types = if node.start.is_some() {
+                NODE_RLP_TYPES_START.to_vec()
+            } else if node.extension_branch.is_some() {
+                NODE_RLP_TYPES_BRANCH.to_vec()
+            } else if node.account.is_some() {
+                NODE_RLP_TYPES_ACCOUNT.to_vec()
+            } else if node.storage.is_some() {
+                NODE_RLP_TYPES_STORAGE.to_vec()
+            } else {
+                unreachable!()
+            };
+
+            // Assign bytes
+            let mut rlp_values = Vec::new();
+            // Decompose RLP
+            for (idx, (bytes, item_type)) in node.values.iter().zip(item_types.iter()).enumerate() {
+                cached_region.push_region(offset + idx, MPTRegion::RLP as usize);
+                let rlp_value =
+                    self.rlp_item
+                        .assign(&mut cached_region, offset + idx, bytes, *item_type)?;
+                rlp_values.push(rlp_value);
+                cached_region.pop_region();
+            }
 
-                    // Assign nodes
-                    if node.start.is_some() {
-                        //println!("{}: start", offset);
-                        cached_region.push_region(offset, MPTRegion::Start as usize);
-                        assign!(cached_region, (self.state_machine.is_start, offset) => "is_start", true.scalar())?;
-                        self.state_machine.start_config.assign(
-                            &mut cached_region,
-                            self,
-                            &mut memory,
-                            offset,
-                            node,
-                            &rlp_values,
-                        )?;
-                        cached_region.pop_region();
-                    } else if node.extension_branch.is_some() {
-                        //println!("{}: branch", offset);
-                        cached_region.push_region(offset, MPTRegion::Branch as usize);
-                        assign!(cached_region, (self.state_machine.is_branch, offset) => "is_branch", true.scalar())?;
-                        self.state_machine.branch_config.assign(
-                            &mut cached_region,
-                            self,
-                            &mut memory,
-                            offset,
-                            node,
-                            &rlp_values,
-                        )?;
-                        cached_region.pop_region();
-                    } else if node.account.is_some() {
-                        //println!("{}: account", offset);
-                        cached_region.push_region(offset, MPTRegion::Account as usize);
-                        assign!(cached_region, (self.state_machine.is_account, offset) => "is_account", true.scalar())?;
-                        self.state_machine.account_config.assign(
-                            &mut cached_region,
-                            self,
-                            &mut memory,
-                            offset,
-                            node,
-                            &rlp_values,
-                        )?;
-                        cached_region.pop_region();
-                    } else if node.storage.is_some() {
-                        //println!("{}: storage", offset);
-                        cached_region.push_region(offset, MPTRegion::Storage as usize);
-                        assign!(cached_region, (self.state_machine.is_storage, offset) => "is_storage", true.scalar())?;
-                        self.state_machine.storage_config.assign(
-                            &mut cached_region,
-                            self,
-                            &mut memory,
-                            offset,
-                            node,
-                            &rlp_values,
-                        )?;
-                        cached_region.pop_region();
-                    }
+            // Assign nodes
+            if node.start.is_some() {
+                // println!("{}: start", offset);
+                cached_region.push_region(offset, MPTRegion::Start as usize);
+                assign!(cached_region, (self.state_machine.is_start, offset) => "is_start", true.scalar())?;
+                self.state_machine.start_config.assign(
+                    &mut cached_region,
+                    self,
+                    &mut memory,
+                    offset,
+                    node,
+                    &rlp_values,
+                )?;
+                cached_region.pop_region();
+            } else if node.extension_branch.is_some() {
+                // println!("{}: branch", offset);
+                cached_region.push_region(offset, MPTRegion::Branch as usize);
+                assign!(cached_region, (self.state_machine.is_branch, offset) => "is_branch", true.scalar())?;
+                self.state_machine.branch_config.assign(
+                    &mut cached_region,
+                    self,
+                    &mut memory,
+                    offset,
+                    node,
+                    &rlp_values,
+                )?;
+                cached_region.pop_region();
+            } else if node.account.is_some() {
+                // println!("{}: account", offset);
+                cached_region.push_region(offset, MPTRegion::Account as usize);
+                assign!(cached_region, (self.state_machine.is_account, offset) => "is_account", true.scalar())?;
+                self.state_machine.account_config.assign(
+                    &mut cached_region,
+                    self,
+                    &mut memory,
+                    offset,
+                    node,
+                    &rlp_values,
+                )?;
+                cached_region.pop_region();
+            } else if node.storage.is_some() {
+                // println!("{}: storage", offset);
+                assert!(
+                    self.params.is_storage_enabled(),
+                    "Storage proofs are not supported when disable_storage is set"
+                );
+                cached_region.push_region(offset, MPTRegion::Storage as usize);
+                assign!(cached_region, (self.state_machine.is_storage, offset) => "is_storage", true.scalar())?;
+                self.state_machine.storage_config.assign(
+                    &mut cached_region,
+                    self,
+                    &mut memory,
+                    offset,
+                    node,
+                    &rlp_values,
+                )?;
+                cached_region.pop_region();
+            }
 
-                    offset += node.values.len();
+            offset += node.values.len();
 
-                    memory.assign(&mut cached_region, offset)?;
+            memory.assign(&mut cached_region, offset)?;
 
-                    cached_region.assign_stored_expressions(&self.cb.base, challenges)?;
-                }
+            cached_region.assign_stored_expressions(&self.cb.base, challenges)?;
+        }
 
-                assert!( self.params.max_nodes >= (2 * HASH_WIDTH + 1),
-                        "The parameter max_nodes is set too low for the mult table: {}, mult table height: {}",
-                        self.params.max_nodes,
-                        2 * HASH_WIDTH + 1,
-                    );
-                assert!( offset <= self.params.max_nodes,
-                        "The parameter max_nodes is set too low, max_nodes: {}, offset: {}",
-                        self.params.max_nodes,
-                        offset,
-                    );
-
-                for offset in 0..self.params.max_nodes {
-                    assignf!(region, (self.q_enable, offset) => true.scalar())?;
-                    assignf!(region, (self.q_first, offset) => (offset == 0).scalar())?;
-                }
+        assert!(
+            self.params.max_nodes >= (2 * HASH_WIDTH + 1),
+            "The parameter max_nodes is set too low for the mult table: {}, mult table height: {}",
+            self.params.max_nodes,
+            2 * HASH_WIDTH + 1,
+        );
+        assert!(
+            offset <= self.params.max_nodes,
+            "The parameter max_nodes is set too low, max_nodes: {}, offset: {}",
+            self.params.max_nodes,
+            offset,
+        );
 
-                Ok(())
-            },
-        )?;
+        for offset in 0..self.params.max_nodes {
+            assignf!(region, (self.q_enable, offset) => true.scalar())?;
+            assignf!(region, (self.q_first, offset) => (offset == 0).scalar())?;
+        }
 
         Ok(())
     }
@@ -658,12 +719,16 @@ pub struct MPTCircuit<F: Field> {
     /// Can be used to test artificially created tests with keys without known their known
     /// preimage. ONLY ENABLE FOR TESTS!
     pub disable_preimage_check: bool,
+    /// Omits the storage leaf constraints, for witnesses that only contain account proofs.
+    pub disable_storage: bool,
+    /// Source of the randomness used for the RLCs
+    pub randomness: MPTRandomness,
     /// Marker
     pub _marker: PhantomData<F>,
 }
 
 /// MPT Circuit configuration parameters
-#[derive(Copy, Clone, Debug, Default)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
 pub struct MPTCircuitParams {
     ///
     pub degree: usize,
@@ -671,6 +736,10 @@ pub struct MPTCircuitParams {
     pub disable_preimage_check: bool,
     /// Maximal number of nodes MPT can prove (for example, one branch has 16 nodes)
     pub max_nodes: usize,
+    /// Omits the storage leaf constraints
+    pub disable_storage: bool,
+    /// Source of the randomness used for the RLCs
+    pub randomness: MPTRandomness,
 }
 
 impl MPTCircuitParams {
@@ -683,6 +752,207 @@ impl MPTCircuitParams {
     fn is_preimage_check_enabled(&self) -> bool {
         !self.disable_preimage_check
     }
+
+    fn is_storage_enabled(&self) -> bool {
+        !self.disable_storage
+    }
+
+    /// Returns the number of rows of the fixed table, see `MPTConfig::load_fixed_table`.
+    fn fixed_table_num_rows(&self) -> usize {
+        let max_length = RLP_UNIT_NUM_BYTES as i32;
+        let byte_range_with_length: usize = [(256, 1), (16, 16)]
+            .into_iter()
+            .map(|(range, out_of_range)| {
+                let get_range = |n: i32| if n <= 0 { out_of_range } else { range };
+                (-max_length..=max_length)
+                    .map(|idx| {
+                        if self.is_two_byte_lookup_enabled() {
+                            get_range(idx) * get_range(idx - 1)
+                        } else {
+                            // 0 is not in the table at index 1 for the msb non-zero check
+                            get_range(idx) * 2 - usize::from(idx == 1)
+                        }
+                    })
+                    .sum::<usize>()
+            })
+            .sum();
+        // Zero lookup, byte range, nibble range, byte range with length, odd extension key, RLP
+        1 + 256 + 16 + byte_range_with_length + 17 + 255
+    }
+}
+
+/// Source of the randomness `r` the MPT circuit uses for its RLCs.
+///
+/// An RLC only binds the bytes it accumulates when `r` is unknown to the prover at the time the
+/// witness is fixed. With a known `r` the prover can construct two different byte strings with the
+/// same RLC, and pass the keccak lookups and RLC equalities with nodes that don't match the hashed
+/// data. `r` thus has to be a Fiat-Shamir challenge, derived after the witness has been committed.
+/// A fixed `r` is only allowed in tests.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum MPTRandomness {
+    /// `r` is a challenge of the halo2 challenge API
+    #[default]
+    Challenge,
+    /// `r` is the given value. ONLY FOR TESTS!
+    Fixed(u64),
+}
+
+impl MPTRandomness {
+    /// A fixed randomness is only accepted by test builds
+    const FIXED_ALLOWED: bool = cfg!(any(test, feature = "test-circuits"));
+
+    /// Returns if the randomness can be used, `fixed_allowed` being false in production.
+    fn is_allowed(&self, fixed_allowed: bool) -> bool {
+        fixed_allowed || *self == Self::Challenge
+    }
+
+    /// Returns the expressions of the randomness used in the constraints.
+    pub fn exprs<F: Field>(
+        &self,
+        meta: &mut ConstraintSystem<F>,
+        challenges: &Challenges,
+    ) -> Challenges<Expression<F>> {
+        match self {
+            Self::Challenge => challenges.exprs(meta),
+            Self::Fixed(r) => {
+                let r = F::from(*r);
+                Challenges::mock(Expression::Constant(r), Expression::Constant(r * r))
+            }
+        }
+    }
+
+    /// Returns the values of the randomness used in the assignments.
+    pub fn values<F: Field>(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        challenges: &Challenges,
+    ) -> Challenges<Value<F>> {
+        match self {
+            Self::Challenge => challenges.values(layouter),
+            Self::Fixed(r) => {
+                let r = F::from(*r);
+                Challenges::mock(Value::known(r), Value::known(r * r))
+            }
+        }
+    }
+}
+
+impl<F: Field> MPTCircuit<F> {
+    /// Creates the circuit of `nodes`, with the keccak data of the nodes, the preimage check of
+    /// the first start node, the storage leaf constraints and the challenge randomness.
+    pub fn new(nodes: Vec<Node>, degree: usize, max_nodes: usize) -> Self {
+        let keccak_data = nodes
+            .iter()
+            .flat_map(|node| node.keccak_data.iter().map(|k| k.to_vec()))
+            .collect();
+        let disable_preimage_check = nodes
+            .first()
+            .and_then(|node| node.start.as_ref())
+            .map_or(false, |start| start.disable_preimage_check);
+        Self {
+            nodes,
+            keccak_data,
+            degree,
+            max_nodes,
+            disable_preimage_check,
+            disable_storage: false,
+            randomness: MPTRandomness::Challenge,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the number of rows needed to assign the witness and the tables of the circuit,
+    /// without the unusable rows. The witness and each of the tables have their own columns, so
+    /// they are assigned next to each other.
+    pub fn min_num_rows(&self) -> usize {
+        let witness_rows = self.nodes.iter().map(|node| node.values.len()).sum();
+        [
+            witness_rows,
+            self.params().fixed_table_num_rows(),
+            // The mult table has a row for every power from 0 to `max_nodes`
+            self.max_nodes + 1,
+            // The keccak table has an all-zero row and a row per input
+            self.keccak_data.len() + 1,
+        ]
+        .into_iter()
+        .max()
+        .unwrap()
+    }
+
+    /// Returns the state selectors of the MPT state machine together with the rows on which they
+    /// are enabled, i.e. the rows on which the constraints of each state are active.
+    /// The rows are read from the cells assigned by a MockProver run, those with both `q_enable`
+    /// and the state selector set. Useful to debug constraints that are not enabled where
+    /// expected.
+    #[cfg(any(test, feature = "test-circuits"))]
+    pub fn active_rows(&self) -> Result<Vec<(&'static str, Vec<usize>)>, Error> {
+        let prover = MockProver::<F>::run(self.degree as u32, self, vec![])?;
+
+        // Configure the circuit like the prover did to find the selector columns
+        let mut meta = ConstraintSystem::<F>::default();
+        let (config, _) = Self::configure_with_params(&mut meta, self.params());
+        let is_set = |cell: &CellValue<F>| *cell == CellValue::Assigned(F::ONE);
+        let q_enable = &prover.fixed()[config.q_enable.index()];
+        let state_machine = &config.state_machine;
+
+        Ok([
+            ("is_start", state_machine.is_start),
+            ("is_branch", state_machine.is_branch),
+            ("is_account", state_machine.is_account),
+            ("is_storage", state_machine.is_storage),
+        ]
+        .into_iter()
+        .map(|(name, column)| {
+            let selector = &prover.advice()[column.index()];
+            let rows = (0..q_enable.len())
+                .filter(|row| is_set(&q_enable[*row]) && is_set(&selector[*row]))
+                .collect();
+            (name, rows)
+        })
+        .collect())
+    }
+}
+
+/// The MPT circuit is a standalone circuit: the block witness has no MPT proofs, they are
+/// generated from the `eth_getProof` responses of the modified accounts and storage slots (see
+/// [`EthProof`]) and given to [`MPTCircuit::new`]. The `SubCircuit` implementation only lets the
+/// MPT circuit be sized and checked like the other sub-circuits, e.g. with
+/// `util::unusable_rows`.
+impl<F: Field> SubCircuit<F> for MPTCircuit<F> {
+    type Config = MPTConfig<F>;
+
+    /// The same for all the [`MPTCircuitParams`], the storage leaf constraints and the
+    /// randomness don't query more rotations than the state cell manager.
+    fn unusable_rows() -> usize {
+        // The columns of the state cell manager are queried at STATE_HEIGHT distinct rotations,
+        // more than any other column, so returns (STATE_HEIGHT + 3) unusable rows.
+        STATE_HEIGHT + 3
+    }
+
+    /// Returns an MPT circuit without nodes and with the default [`MPTCircuitParams`], as the
+    /// block witness does not contain MPT proofs. Use [`MPTCircuit::new`] to prove MPT proofs.
+    fn new_from_block(_block: &witness::Block<F>) -> Self {
+        Self::default()
+    }
+
+    /// Return the minimum number of rows required to prove the block, the rows of the tables of
+    /// the circuit as there are no MPT proofs in the block
+    fn min_num_rows_block(block: &witness::Block<F>) -> (usize, usize) {
+        let num_rows = Self::new_from_block(block).min_num_rows();
+        (num_rows, num_rows)
+    }
+
+    /// Make the assignments to the MPTCircuit, the keccak table is loaded by the keccak circuit
+    fn synthesize_sub(
+        &self,
+        config: &Self::Config,
+        challenges: &Challenges<Value<F>>,
+        layouter: &mut impl Layouter<F>,
+    ) -> Result<(), Error> {
+        config.assign(layouter, &self.nodes, challenges)?;
+        config.load_fixed_table(layouter)?;
+        config.load_mult_table(layouter, challenges, self.max_nodes)
+    }
 }
 
 impl<F: Field> Circuit<F> for MPTCircuit<F> {
@@ -699,15 +969,24 @@ impl<F: Field> Circuit<F> for MPTCircuit<F> {
             degree: self.degree,
             disable_preimage_check: self.disable_preimage_check,
             max_nodes: self.max_nodes,
+            disable_storage: self.disable_storage,
+            randomness: self.randomness,
         }
     }
 
     fn configure_with_params(meta: &mut ConstraintSystem<F>, params: Self::Params) -> Self::Config {
         let challenges = Challenges::construct(meta);
-        let challenges_expr = challenges.exprs(meta);
+        let challenges_expr = params.randomness.exprs(meta, &challenges);
         let keccak_table = KeccakTable::construct(meta);
         (
-            MPTConfig::new(meta, challenges_expr, keccak_table, params),
+            MPTConfig::new(
+                meta,
+                MPTConfigArgs {
+                    keccak_table,
+                    challenges: challenges_expr,
+                    params,
+                },
+            ),
             challenges,
         )
     }
@@ -721,10 +1000,8 @@ impl<F: Field> Circuit<F> for MPTCircuit<F> {
         (config, _challenges): Self::Config,
         mut layouter: impl Layouter<F>,
     ) -> Result<(), Error> {
-        let challenges = _challenges.values(&mut layouter);
-        config.assign(&mut layouter, &self.nodes, &challenges)?;
-        config.load_fixed_table(&mut layouter)?;
-        config.load_mult_table(&mut layouter, &challenges, self.max_nodes)?;
+        let challenges = self.randomness.values(&mut layouter, &_challenges);
+        self.synthesize_sub(&config, &challenges, &mut layouter)?;
         config
             .keccak_table
             .dev_load(&mut layouter, &self.keccak_data, &challenges)?;
@@ -752,9 +1029,35 @@ pub fn load_proof<R: Read>(reader: R) -> Result<Vec<Node>, serde_json::Error> {
                 .push([vec![160], storage.key.to_vec()].concat().into());
         }
     }
+
+    if nodes
+        .iter()
+        .any(|node| !node.has_consistent_branch_children())
+    {
+        return Err(serde::de::Error::custom(
+            "S and C branches differ at a child other than the modified one",
+        ));
+    }
     Ok(nodes)
 }
 
+/// An `eth_getProof` response
+pub type EthProof = eth_types::EIP1186ProofResponse;
+
+#[cfg(feature = "eth-proof")]
+impl Node {
+    /// Converts an `eth_getProof` response into the nodes of a witness proving the account and
+    /// the storage values of the response at its state root, see
+    /// `geth_utils::get_witness_from_proof`. The values are read, not modified: storage values
+    /// are proven with `StorageChanged` proofs (`StorageDoesNotExist` when the value is 0) and an
+    /// account without storage values with a `NonceChanged` proof (`AccountDoesNotExist` when the
+    /// account is empty).
+    pub fn from_eth_proof(proof: EthProof) -> Result<Vec<Node>, serde_json::Error> {
+        let json = geth_utils::get_witness_from_proof(&proof);
+        load_proof(json.as_bytes())
+    }
+}
+
 /// Loads an MPT proof from disk
 pub fn load_proof_from_file(path: &str) -> Vec<Node> {
     let file = std::fs::File::open(path);
@@ -762,11 +1065,434 @@ pub fn load_proof_from_file(path: &str) -> Vec<Node> {
     load_proof(reader).unwrap()
 }
 
+/// Reconstructs the (hashed) key of the leaf modified by the proof by walking the nibbles of the
+/// branches and extensions on the path plus the key stored in the leaf, see [`leaf_key`]. For
+/// storage proofs this is the hashed storage slot, for account proofs the hashed address.
+/// The path is taken in the trie where the leaf is not moved by an added or removed branch: the
+/// trie after the modification (`C`) unless the branch is removed.
+/// Returns `None` for proofs that don't end in the modified leaf (non-existence proofs).
+pub fn modified_key(nodes: &[Node]) -> Option<Vec<u8>> {
+    let is_non_existing = |node: &Node| {
+        node.start.as_ref().map_or(false, |start| {
+            matches!(
+                start.proof_type,
+                MPTProofType::AccountDoesNotExist | MPTProofType::StorageDoesNotExist
+            )
+        })
+    };
+    if nodes.iter().any(is_non_existing) {
+        return None;
+    }
+    let is_leaf = |node: &Node| node.account.is_some() || node.storage.is_some();
+    let leaf_idx = nodes.iter().rposition(is_leaf)?;
+    // The storage trie starts after the account leaf
+    let path_start = nodes[..leaf_idx]
+        .iter()
+        .rposition(|node| node.start.is_some() || is_leaf(node))
+        .map_or(0, |idx| idx + 1);
+    let path = &nodes[path_start..=leaf_idx];
+    let is_s = [false, true].into_iter().find(|is_s| {
+        path.iter().all(|node| {
+            node.extension_branch
+                .as_ref()
+                .map_or(true, |extension_branch| {
+                    !extension_branch.is_placeholder[is_s.idx()]
+                })
+        })
+    })?;
+    leaf_key(path, is_s).map(|key| key.to_vec())
+}
+
+/// Returns the word encoded by the RLP string `item`, a single byte below 0x80 encoding itself.
+fn rlp_string_word(item: &[u8]) -> Option<U256> {
+    let (_, len, header_len) = rlp_decode_len(*item.first()?, *item.get(1).unwrap_or(&0))?;
+    let bytes = item.get(header_len..header_len + len)?;
+    (len <= 32).then(|| U256::from_big_endian(bytes))
+}
+
+/// Returns the new value and the storage key the proof `nodes` puts in the MPT table: the nonce,
+/// balance or code hash of the `C` account, or the `C` storage value with its (not hashed)
+/// storage key. The value is zero for non-existence proofs and the key is zero for account
+/// proofs. Returns `None` for the other proof types and for proofs without the expected leaf.
+pub fn modified_value(nodes: &[Node]) -> Option<(U256, H256)> {
+    let proof_type = nodes.first()?.start.as_ref()?.proof_type;
+    let leaf = nodes
+        .iter()
+        .rev()
+        .find(|node| node.account.is_some() || node.storage.is_some())?;
+    let account_value = |row: AccountRowType| {
+        leaf.account.as_ref()?;
+        rlp_string_word(&leaf.values[row as usize])
+    };
+    let storage_key = || Some(H256::from_slice(&leaf.storage.as_ref()?.address));
+
+    match proof_type {
+        MPTProofType::NonceChanged => Some((account_value(AccountRowType::NonceC)?, H256::zero())),
+        MPTProofType::BalanceChanged => {
+            Some((account_value(AccountRowType::BalanceC)?, H256::zero()))
+        }
+        MPTProofType::CodeHashChanged => {
+            Some((account_value(AccountRowType::CodehashC)?, H256::zero()))
+        }
+        MPTProofType::AccountDoesNotExist | MPTProofType::AccountDestructed => {
+            Some((U256::zero(), H256::zero()))
+        }
+        MPTProofType::StorageChanged => {
+            let storage = leaf.storage.as_ref()?;
+            // A short value is stored in its RLP byte, a placeholder leaf has the value 0
+            let value_rlp_byte = *storage.value_rlp_bytes[false.idx()].first()?;
+            let value = if value_rlp_byte < RLP_SHORT {
+                U256::from(value_rlp_byte)
+            } else {
+                rlp_string_word(&leaf.values[StorageRowType::ValueC as usize])?
+            };
+            Some((value, storage_key()?))
+        }
+        MPTProofType::StorageDoesNotExist => Some((U256::zero(), storage_key()?)),
+        _ => None,
+    }
+}
+
+/// Returns the RLC of the (hashed) key `key` with the randomness `r`, as accumulated by the
+/// circuit over the nibbles of the branches and extensions on the path and the key of the leaf:
+/// `key[0] + key[1] * r + key[2] * r^2 + ...`
+pub fn compute_key_rlc<F: Field>(key: &[u8], r: F) -> F {
+    key.iter()
+        .rev()
+        .fold(F::ZERO, |rlc, byte| rlc * r + F::from(*byte as u64))
+}
+
+/// Returns the number of leading extension and branch nodes two proofs share, i.e. the nodes that
+/// are on the same path in the tries. When the two proofs are adjacent in a witness these nodes
+/// can be deduplicated. The storage tries are only shared when both proofs are for the same
+/// account.
+pub fn common_branch_prefix(a: &[Node], b: &[Node]) -> usize {
+    let is_start = |node: &&Node| node.start.is_some();
+    let mut count = 0;
+    for (node_a, node_b) in a
+        .iter()
+        .skip_while(is_start)
+        .zip(b.iter().skip_while(is_start))
+    {
+        match (&node_a.extension_branch, &node_b.extension_branch) {
+            (Some(extension_branch_a), Some(extension_branch_b)) => {
+                let key = |node: &Node| {
+                    key_row_nibbles(&node.values[ExtensionBranchRowType::KeyS as usize])
+                };
+                let same_path = extension_branch_a.is_extension == extension_branch_b.is_extension
+                    && (!extension_branch_a.is_extension || key(node_a) == key(node_b))
+                    && extension_branch_a.branch.modified_index
+                        == extension_branch_b.branch.modified_index;
+                if !same_path {
+                    break;
+                }
+                count += 1;
+            }
+            _ => {
+                let address = |node: &Node| {
+                    node.account
+                        .as_ref()
+                        .map(|_| node.values[AccountRowType::Address as usize].to_vec())
+                };
+                if address(node_a).is_none() || address(node_a) != address(node_b) {
+                    break;
+                }
+            }
+        }
+    }
+    count
+}
+
+/// Turns the `S` side of an account proof into a proof that the code hash of the account is the
+/// empty code hash, i.e. that the account is an EOA. The `C` side is replaced by the `S` side so
+/// that the trie is not modified, the nodes below the account leaf are dropped.
+/// Returns `None` when the path to the account contains placeholders or modified extensions.
+pub fn code_hash_is_empty_proof(nodes: &[Node]) -> Option<Vec<Node>> {
+    unmodified_account_proof(nodes, MPTProofType::CodeHashIsEmpty)
+}
+
+/// Turns the `S` side of an account proof into a proof of the code hash returned by EXTCODEHASH
+/// for the account, see `code_hash_read_value`. Proofs that the account doesn't exist are
+/// returned as is, EXTCODEHASH returns 0 for them.
+/// Returns `None` when the path to the account contains placeholders or modified extensions.
+pub fn code_hash_read_proof(nodes: &[Node]) -> Option<Vec<Node>> {
+    if proof_type(nodes)? == MPTProofType::AccountDoesNotExist {
+        return Some(nodes.to_vec());
+    }
+    unmodified_account_proof(nodes, MPTProofType::CodeHashRead)
+}
+
+/// Returns the code hash EXTCODEHASH returns for the account of the proof, the value put in the
+/// MPT table by `code_hash_read_proof`: 0 when the account doesn't exist or is empty (no nonce,
+/// no balance and no code), the code hash of the account otherwise.
+pub fn code_hash_read_value(nodes: &[Node]) -> Option<U256> {
+    if proof_type(nodes)? == MPTProofType::AccountDoesNotExist {
+        return Some(U256::zero());
+    }
+    let node = nodes.iter().find(|node| node.account.is_some())?;
+    let value = |row: AccountRowType| {
+        let bytes = &node.values[row as usize];
+        let (offset, len) = rlp_payload(bytes)?;
+        Some(U256::from_big_endian(&bytes[offset..offset + len]))
+    };
+    let codehash = value(AccountRowType::CodehashS)?;
+    let is_empty = value(AccountRowType::NonceS)?.is_zero()
+        && value(AccountRowType::BalanceS)?.is_zero()
+        && codehash == U256::from_big_endian(&EMPTY_CODE_HASH);
+    Some(if is_empty { U256::zero() } else { codehash })
+}
+
+fn proof_type(nodes: &[Node]) -> Option<MPTProofType> {
+    nodes
+        .iter()
+        .find_map(|node| Some(node.start.as_ref()?.proof_type))
+}
+
+/// Turns the `S` side of an account proof into a proof of type `proof_type` that doesn't modify
+/// the trie, see `code_hash_is_empty_proof`.
+fn unmodified_account_proof(nodes: &[Node], proof_type: MPTProofType) -> Option<Vec<Node>> {
+    let account_idx = nodes.iter().position(|node| node.account.is_some())?;
+    let mut proof = nodes[..=account_idx].to_vec();
+    for node in proof.iter_mut() {
+        if let Some(start) = &mut node.start {
+            start.proof_type = proof_type;
+            node.values[StartRowType::RootC as usize] =
+                node.values[StartRowType::RootS as usize].clone();
+        }
+        if let Some(extension_branch) = &mut node.extension_branch {
+            if extension_branch.is_placeholder.contains(&true)
+                || extension_branch.is_mod_extension.contains(&true)
+            {
+                return None;
+            }
+            let branch = &mut extension_branch.branch;
+            branch.list_rlp_bytes[1] = branch.list_rlp_bytes[0].clone();
+            node.values[ExtensionBranchRowType::Mod as usize] = node.values
+                [ExtensionBranchRowType::Child0 as usize + branch.modified_index]
+                .clone();
+            node.values[ExtensionBranchRowType::ValueC as usize] =
+                node.values[ExtensionBranchRowType::ValueS as usize].clone();
+            node.keccak_data[1] = node.keccak_data[0].clone();
+            if extension_branch.is_extension {
+                node.keccak_data[3] = node.keccak_data[2].clone();
+            }
+        }
+        if let Some(account) = &mut node.account {
+            if account.is_mod_extension.contains(&true) {
+                return None;
+            }
+            account.list_rlp_bytes[1] = account.list_rlp_bytes[0].clone();
+            account.value_rlp_bytes[1] = account.value_rlp_bytes[0].clone();
+            account.value_list_rlp_bytes[1] = account.value_list_rlp_bytes[0].clone();
+            for (row_s, row_c) in [
+                (AccountRowType::KeyS, AccountRowType::KeyC),
+                (AccountRowType::NonceS, AccountRowType::NonceC),
+                (AccountRowType::BalanceS, AccountRowType::BalanceC),
+                (AccountRowType::StorageS, AccountRowType::StorageC),
+                (AccountRowType::CodehashS, AccountRowType::CodehashC),
+            ] {
+                node.values[row_c as usize] = node.values[row_s as usize].clone();
+            }
+            node.keccak_data[1] = node.keccak_data[0].clone();
+        }
+    }
+    // Keep the node closing the proof
+    proof.extend(nodes.last().filter(|node| node.start.is_some()).cloned());
+    Some(proof)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr};
-    use std::{fs, ops::Deref};
+    use crate::{
+        mpt_circuit::{
+            helpers::{empty_trie_hash_word, is_empty_trie_root, LeafKeyWitness},
+            param::{
+                ARITY, EMPTY_TRIE_HASH, KEY_TERMINAL_PREFIX_EVEN, KEY_TERMINAL_PREFIX_ODD,
+                RLP_LIST_LONG, RLP_LIST_SHORT, RLP_LONG, RLP_NIL, RLP_SHORT,
+                RLP_UNIT_NUM_VALUE_BYTES,
+            },
+            rlp_gadgets::{RLPItemWitness, RLPValueWitness},
+            witness_row::rlp_list_items,
+        },
+        util::{unusable_rows, word::WordLoHi},
+    };
+    use eth_types::keccak256;
+    use halo2_proofs::{
+        dev::{CellValue, MockProver, VerifyFailure},
+        halo2curves::bn256::Fr,
+    };
+    use std::fs;
+
+    /// Proves `nodes` with the fixed randomness `r` and asserts that the key RLC accumulated by
+    /// the circuit matches `compute_key_rlc(key, r)`. The leaf constrains the accumulated RLC to
+    /// be the RLC assigned to its `Key` row, which is the value compared.
+    fn assert_key_rlc_matches(nodes: Vec<Node>, key: &[u8], r: u64) {
+        let leaf_idx = nodes
+            .iter()
+            .rposition(|node| node.account.is_some() || node.storage.is_some())
+            .unwrap();
+        let key_row = if nodes[leaf_idx].account.is_some() {
+            AccountRowType::Key as usize
+        } else {
+            StorageRowType::Key as usize
+        };
+        let leaf_offset: usize = nodes[..leaf_idx].iter().map(|node| node.values.len()).sum();
+
+        let num_rows: usize = nodes.iter().map(|node| node.values.len()).sum();
+        let circuit = MPTCircuit::<Fr> {
+            randomness: MPTRandomness::Fixed(r),
+            ..MPTCircuit::new(nodes, MOCK_DEGREE, MOCK_MAX_NODES)
+        };
+        let params = circuit.params();
+        let prover = MockProver::<Fr>::run(params.degree as u32, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify_at_rows(0..num_rows, 0..num_rows), Ok(()));
+
+        // Configure the circuit like the prover did to find the cell of the key RLC
+        let mut meta = ConstraintSystem::<Fr>::default();
+        let (config, _) = MPTCircuit::<Fr>::configure_with_params(&mut meta, params);
+        let cell = config.rlp_item.hash_rlc_cell();
+        let row = leaf_offset + key_row + cell.rotation();
+        assert_eq!(
+            prover.advice()[cell.column().index()][row],
+            CellValue::Assigned(compute_key_rlc(key, Fr::from(r)))
+        );
+    }
+
+    #[test]
+    fn test_mpt_key_rlc() {
+        for file in ["UpdateOneLevel.json", "ExtensionInFirstStorageLevel.json"] {
+            let nodes = load_proof_from_file(&format!("src/mpt_circuit/tests/{}", file));
+            let key = modified_key(&nodes).unwrap();
+            assert_key_rlc_matches(nodes, &key, 0x100);
+        }
+
+        // The key RLC is sensitive to the order of the bytes
+        let key = [1, 2, 3];
+        let r = Fr::from(0x100);
+        assert_eq!(compute_key_rlc(&key, r), Fr::from(0x030201));
+    }
+
+    #[test]
+    fn test_mpt_storage_extension_nibbles_parity() {
+        // The extension in the storage trie has 1, 3 and 2 nibbles: the key RLC after the
+        // extension continues with the opposite (odd) or the same (even) nibble alignment
+        for (file, num_nibbles) in [
+            ("ExtensionInFirstStorageLevelOneKeyByte.json", 1),
+            ("ExtensionThreeKeyBytesSel2.json", 3),
+            ("ExtensionInFirstStorageLevelTwoKeyBytes.json", 2),
+        ] {
+            let nodes = load_proof_from_file(&format!("src/mpt_circuit/tests/{}", file));
+            let account_idx = nodes
+                .iter()
+                .position(|node| node.account.is_some())
+                .unwrap();
+            let extension = nodes[account_idx..]
+                .iter()
+                .find(|node| {
+                    node.extension_branch
+                        .as_ref()
+                        .map_or(false, |extension_branch| extension_branch.is_extension)
+                })
+                .unwrap();
+            let nibbles =
+                key_row_nibbles(&extension.values[ExtensionBranchRowType::KeyS as usize]).unwrap();
+            assert_eq!(nibbles.len(), num_nibbles, "{}", file);
+
+            let storage = nodes.iter().find_map(|node| node.storage.clone()).unwrap();
+            let key = modified_key(&nodes).unwrap();
+            assert_eq!(key, storage.key.to_vec(), "{}", file);
+            assert_key_rlc_matches(nodes, &key, 0x100);
+        }
+    }
+
+    #[test]
+    fn test_leaf_key_single_byte() {
+        // A leaf deep enough to have a single nibble left, or none, stores its key in a single
+        // byte without RLP byte
+        let r = Fr::from(0x100);
+        let (key_rlc, key_mult) = (Fr::from(0x21), r);
+        for (key, nibbles, expected) in [
+            (
+                KEY_TERMINAL_PREFIX_ODD + 5,
+                vec![5],
+                (Fr::from(0x0521), r * r),
+            ),
+            (KEY_TERMINAL_PREFIX_EVEN, vec![], (key_rlc, key_mult)),
+        ] {
+            let bytes = [vec![key], vec![0; RLP_UNIT_NUM_VALUE_BYTES]].concat();
+            let rlp_key = RLPItemWitness {
+                value: RLPValueWitness {
+                    is_short: true,
+                    bytes: bytes.clone(),
+                    ..Default::default()
+                },
+                bytes,
+                ..Default::default()
+            };
+            assert_eq!(rlp_key.len(), 1);
+            assert_eq!(key_row_nibbles(&[key]), Some(nibbles));
+            assert_eq!(
+                LeafKeyWitness::default().key(rlp_key, key_rlc, key_mult, r),
+                expected,
+                "{:#x}",
+                key
+            );
+        }
+    }
+
+    #[test]
+    fn test_modified_value() {
+        let value = |file: &str| {
+            modified_value(&load_proof_from_file(&format!(
+                "src/mpt_circuit/tests/{file}"
+            )))
+        };
+        assert_eq!(
+            value("BalanceModCShort.json"),
+            Some((0x62.into(), H256::zero()))
+        );
+        assert_eq!(
+            value("NonceModCLong.json"),
+            Some((0x8e.into(), H256::zero()))
+        );
+
+        // Short storage values are in the RLP byte of the value
+        let nodes = load_proof_from_file("src/mpt_circuit/tests/FromNilToValue.json");
+        let storage = nodes.iter().find_map(|node| node.storage.clone()).unwrap();
+        let key = H256::from_slice(&storage.address);
+        assert_eq!(modified_value(&nodes), Some((0x11.into(), key)));
+
+        // The value of a deleted leaf is 0
+        let nodes = load_proof_from_file("src/mpt_circuit/tests/Delete.json");
+        assert_eq!(
+            modified_value(&nodes).map(|(value, _)| value),
+            Some(U256::zero())
+        );
+
+        let nodes = load_proof_from_file("src/mpt_circuit/tests/NonExistingStorage.json");
+        let storage = nodes.iter().find_map(|node| node.storage.clone()).unwrap();
+        let key = H256::from_slice(&storage.address);
+        assert_eq!(modified_value(&nodes), Some((U256::zero(), key)));
+    }
+
+    #[test]
+    fn test_modified_key() {
+        let nodes = load_proof_from_file("src/mpt_circuit/tests/ExtensionInFirstStorageLevel.json");
+        let storage = nodes.iter().find_map(|node| node.storage.clone()).unwrap();
+        assert_eq!(modified_key(&nodes), Some(storage.key.to_vec()));
+
+        let nodes = load_proof_from_file("src/mpt_circuit/tests/ExtensionAddedOneKeyByteSel1.json");
+        let storage = nodes.iter().find_map(|node| node.storage.clone()).unwrap();
+        assert_eq!(modified_key(&nodes), Some(storage.key.to_vec()));
+
+        let nodes = load_proof_from_file("src/mpt_circuit/tests/AccountAfterFirstLevel.json");
+        let account = nodes.iter().find_map(|node| node.account.clone()).unwrap();
+        assert_eq!(modified_key(&nodes), Some(account.key.to_vec()));
+
+        let nodes = load_proof_from_file("src/mpt_circuit/tests/NonExistingStorage.json");
+        assert_eq!(modified_key(&nodes), None);
+    }
 
     #[test]
     fn test_mpt() {
@@ -790,30 +1516,964 @@ mod tests {
                 let nodes = load_proof_from_file(path.to_str().unwrap());
                 let num_rows: usize = nodes.iter().map(|node| node.values.len()).sum();
 
-                let mut keccak_data = vec![];
-                for node in nodes.iter() {
-                    for k in node.keccak_data.iter() {
-                        keccak_data.push(k.deref().clone());
-                    }
-                }
-
-                let disable_preimage_check = nodes[0].start.clone().unwrap().disable_preimage_check;
-                let degree = 15;
-                let max_nodes = 520;
-                let circuit = MPTCircuit::<Fr> {
-                    nodes,
-                    keccak_data,
-                    degree,
-                    max_nodes,
-                    disable_preimage_check,
-                    _marker: PhantomData,
-                };
-
                 println!("{} {:?}", idx, path);
-                let prover = MockProver::<Fr>::run(degree as u32, &circuit, vec![]).unwrap();
+                let prover = mock_prove_nodes(nodes).unwrap();
                 assert_eq!(prover.verify_at_rows(0..num_rows, 0..num_rows,), Ok(()));
                 // assert_eq!(prover.verify(), Ok(()));
                 // prover.assert_satisfied();
             });
     }
+
+    #[test]
+    fn test_mpt_active_rows() {
+        let nodes = load_proof_from_file("src/mpt_circuit/tests/UpdateOneLevel.json");
+        let storage_offset: usize = nodes
+            .iter()
+            .take_while(|node| node.storage.is_none())
+            .map(|node| node.values.len())
+            .sum();
+        let circuit = MPTCircuit::<Fr>::new(nodes, MOCK_DEGREE, MOCK_MAX_NODES);
+
+        let active_rows = circuit.active_rows().unwrap();
+        let names: Vec<_> = active_rows.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, ["is_start", "is_branch", "is_account", "is_storage"]);
+
+        // Every node enables the state of its type on its first row, starting with the start
+        // node on row 0
+        let rows = |name: &str| {
+            active_rows
+                .iter()
+                .find(|(state, _)| *state == name)
+                .map(|(_, rows)| rows.clone())
+                .unwrap()
+        };
+        assert_eq!(rows("is_start")[0], 0);
+        assert_eq!(
+            active_rows
+                .iter()
+                .map(|(_, rows)| rows.len())
+                .sum::<usize>(),
+            circuit.nodes.len()
+        );
+        assert!(!rows("is_branch").is_empty());
+        assert_eq!(rows("is_account").len(), 1);
+        let storage_rows = rows("is_storage");
+        assert_eq!(storage_rows, vec![storage_offset]);
+
+        // The storage leaf constraints hold on the rows they are active on
+        let prover = MockProver::<Fr>::run(MOCK_DEGREE as u32, &circuit, vec![]).unwrap();
+        assert_eq!(
+            prover.verify_at_rows(storage_rows.iter().copied(), storage_rows.iter().copied()),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_mpt_disable_storage() {
+        let nodes = load_proof_from_file("src/mpt_circuit/tests/BalanceModCLong.json");
+        assert!(nodes.iter().all(|node| node.storage.is_none()));
+        let num_rows: usize = nodes.iter().map(|node| node.values.len()).sum();
+        let circuit = MPTCircuit::<Fr> {
+            disable_storage: true,
+            ..MPTCircuit::new(nodes, MOCK_DEGREE, MOCK_MAX_NODES)
+        };
+        let prover = MockProver::<Fr>::run(MOCK_DEGREE as u32, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify_at_rows(0..num_rows, 0..num_rows,), Ok(()));
+
+        // The storage leaf constraints are not part of the circuit
+        let num_constraints = |disable_storage| {
+            let mut meta = ConstraintSystem::<Fr>::default();
+            let params = MPTCircuitParams {
+                disable_storage,
+                ..circuit.params()
+            };
+            MPTCircuit::<Fr>::configure_with_params(&mut meta, params);
+            meta.gates()
+                .iter()
+                .map(|gate| gate.polynomials().len())
+                .sum::<usize>()
+        };
+        assert!(num_constraints(true) < num_constraints(false));
+    }
+
+    #[test]
+    fn test_mpt_fixed_randomness() {
+        // Production builds only accept the challenge randomness
+        assert!(MPTRandomness::Challenge.is_allowed(false));
+        assert!(!MPTRandomness::Fixed(0x100).is_allowed(false));
+
+        // Tests can prove the MPT with a fixed randomness
+        let nodes = load_proof_from_file("src/mpt_circuit/tests/UpdateOneLevel.json");
+        let num_rows: usize = nodes.iter().map(|node| node.values.len()).sum();
+        let circuit = MPTCircuit::<Fr> {
+            randomness: MPTRandomness::Fixed(0x100),
+            ..MPTCircuit::new(nodes, MOCK_DEGREE, MOCK_MAX_NODES)
+        };
+        let prover = MockProver::<Fr>::run(MOCK_DEGREE as u32, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify_at_rows(0..num_rows, 0..num_rows), Ok(()));
+    }
+
+    fn verify_nodes(nodes: Vec<Node>) -> Result<(), Vec<VerifyFailure>> {
+        let num_rows: usize = nodes.iter().map(|node| node.values.len()).sum();
+        let prover = mock_prove_nodes(nodes).unwrap();
+        prover.verify_at_rows(0..num_rows, 0..num_rows)
+    }
+
+    /// An MPT circuit of which some advice cells are overwritten once the witness is assigned,
+    /// to check the constraints on witnesses that cannot be assigned
+    #[derive(Default)]
+    struct OverriddenMPTCircuit {
+        circuit: MPTCircuit<Fr>,
+        /// The column, the row and the new value of the overwritten cells
+        overrides: Vec<(Column<Advice>, usize, Fr)>,
+    }
+
+    impl Circuit<Fr> for OverriddenMPTCircuit {
+        type Config = (MPTConfig<Fr>, Challenges);
+        type FloorPlanner = SimpleFloorPlanner;
+        type Params = MPTCircuitParams;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn params(&self) -> Self::Params {
+            self.circuit.params()
+        }
+
+        fn configure_with_params(
+            meta: &mut ConstraintSystem<Fr>,
+            params: Self::Params,
+        ) -> Self::Config {
+            MPTCircuit::configure_with_params(meta, params)
+        }
+
+        fn configure(_meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            unreachable!();
+        }
+
+        fn synthesize(
+            &self,
+            (config, challenges): Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let challenges = self.circuit.randomness.values(&mut layouter, &challenges);
+            // The cells are overwritten in the region they are assigned in
+            layouter.assign_region(
+                || "MPT",
+                |mut region| {
+                    config.assign_nodes(&mut region, &self.circuit.nodes, &challenges)?;
+                    for &(column, row, value) in self.overrides.iter() {
+                        region.assign_advice(|| "override", column, row, || Value::known(value))?;
+                    }
+                    Ok(())
+                },
+            )?;
+            config.load_fixed_table(&mut layouter)?;
+            config.load_mult_table(&mut layouter, &challenges, self.circuit.max_nodes)?;
+            config
+                .keccak_table
+                .dev_load(&mut layouter, &self.circuit.keccak_data, &challenges)?;
+
+            Ok(())
+        }
+    }
+
+    /// Verifies `nodes` with the advice cells in `overrides` overwritten
+    fn verify_nodes_with_overrides(
+        nodes: Vec<Node>,
+        overrides: Vec<(Column<Advice>, usize, Fr)>,
+    ) -> Result<(), Vec<VerifyFailure>> {
+        let num_rows: usize = nodes.iter().map(|node| node.values.len()).sum();
+        let circuit = OverriddenMPTCircuit {
+            circuit: MPTCircuit::new(nodes, MOCK_DEGREE, MOCK_MAX_NODES),
+            overrides,
+        };
+        let prover = MockProver::<Fr>::run(MOCK_DEGREE as u32, &circuit, vec![]).unwrap();
+        prover.verify_at_rows(0..num_rows, 0..num_rows)
+    }
+
+    /// Runs the MockProver on `nodes`, fails if the witness cannot be assigned.
+    fn mock_prove_nodes(nodes: Vec<Node>) -> Result<MockProver<Fr>, Error> {
+        let circuit = MPTCircuit::<Fr>::new(nodes, MOCK_DEGREE, MOCK_MAX_NODES);
+        MockProver::<Fr>::run(MOCK_DEGREE as u32, &circuit, vec![])
+    }
+
+    /// Reduces a witness failing verification to a minimal failing witness, to debug a large
+    /// witness. The nodes are removed by whole proofs, from the node starting a proof up to the
+    /// `Disabled` node closing it, as a proof missing some of its nodes always fails. The proofs
+    /// are removed greedily, one at a time, as long as the remaining witness still fails.
+    fn minimize_failing_witness(nodes: &[Node]) -> Vec<Node> {
+        let mut proofs: Vec<&[Node]> = nodes
+            .split_inclusive(|node| {
+                node.start
+                    .as_ref()
+                    .map_or(false, |start| start.proof_type == MPTProofType::Disabled)
+            })
+            .collect();
+        let mut idx = 0;
+        while idx < proofs.len() {
+            let mut candidate = proofs.clone();
+            candidate.remove(idx);
+            if !candidate.is_empty() && verify_nodes(candidate.concat()).is_err() {
+                proofs = candidate;
+            } else {
+                idx += 1;
+            }
+        }
+        proofs.concat()
+    }
+
+    /// Sets the `C` value of the storage leaf to the RLP string `value` and updates the hashes of
+    /// all the nodes above the leaf, up to the `C` root, so that the proof stays consistent.
+    fn set_storage_value_c(nodes: &mut [Node], value: &[u8]) {
+        let node = nodes
+            .iter_mut()
+            .find(|node| node.storage.is_some())
+            .unwrap();
+        let old_rlp = node.keccak_data[1].to_vec();
+        let key = rlp_list_items(&old_rlp).unwrap()[0].to_vec();
+        let value_rlp = [&[RLP_SHORT + value.len() as u8][..], value].concat();
+        let value_rlp_bytes = vec![RLP_SHORT + value_rlp.len() as u8];
+        let payload_len = key.len() + value_rlp_bytes.len() + value_rlp.len();
+        let list_rlp_bytes = vec![RLP_LIST_LONG + 1, payload_len as u8];
+        let new_rlp = [
+            &list_rlp_bytes[..],
+            &key[..],
+            &value_rlp_bytes[..],
+            &value_rlp[..],
+        ]
+        .concat();
+
+        let storage = node.storage.as_mut().unwrap();
+        storage.list_rlp_bytes[1] = list_rlp_bytes.into();
+        storage.value_rlp_bytes[1] = value_rlp_bytes.into();
+        let mut value_row = value_rlp;
+        value_row.resize(RLP_UNIT_NUM_BYTES, 0);
+        node.values[StorageRowType::ValueC as usize] = value_row.into();
+        node.keccak_data[1] = new_rlp.clone().into();
+
+        replace_hash_up_to_root(nodes, old_rlp, new_rlp);
+    }
+
+    /// Replaces the hash of the node `old_rlp` by the hash of `new_rlp` in its parent, and the
+    /// hash of every modified parent in its own parent until the root is reached.
+    fn replace_hash_up_to_root(nodes: &mut [Node], mut old_rlp: Vec<u8>, mut new_rlp: Vec<u8>) {
+        let replace = |bytes: &[u8], from: &[u8], to: &[u8]| -> Option<Vec<u8>> {
+            let pos = bytes
+                .windows(from.len())
+                .position(|window| window == from)?;
+            Some([&bytes[..pos], to, &bytes[pos + from.len()..]].concat())
+        };
+
+        loop {
+            let (old_hash, new_hash) = (keccak256(&old_rlp), keccak256(&new_rlp));
+            let (old_hash, new_hash) = (&old_hash[..], &new_hash[..]);
+            let mut parent = None;
+            for node in nodes.iter_mut() {
+                for data in node.keccak_data.iter_mut() {
+                    if let Some(new_data) = replace(data, old_hash, new_hash) {
+                        parent = Some((data.to_vec(), new_data.clone()));
+                        *data = new_data.into();
+                    }
+                }
+                for row in node.values.iter_mut() {
+                    if let Some(new_row) = replace(row, old_hash, new_hash) {
+                        *row = new_row.into();
+                    }
+                }
+            }
+            match parent {
+                Some((old_parent, new_parent)) => (old_rlp, new_rlp) = (old_parent, new_parent),
+                None => break,
+            }
+        }
+    }
+
+    /// Turns a proof inserting an account into the proof of a contract creation: the new account
+    /// has nonce 1, no balance, an empty storage trie and the code hash `code_hash`. When the
+    /// account is inserted into a placeholder branch, the existing leaf drifts into the new
+    /// branch as in the original proof.
+    /// Returns `None` when the proof doesn't insert an account.
+    fn contract_creation_proof(nodes: &[Node], code_hash: &[u8; 32]) -> Option<Vec<Node>> {
+        let mut proof = nodes.to_vec();
+        let account_idx = proof.iter().position(|node| node.account.is_some())?;
+        let (parent, extension_branch) = proof[..account_idx]
+            .iter()
+            .rev()
+            .find_map(|node| Some((node, node.extension_branch.as_ref()?)))?;
+        if extension_branch.is_placeholder[1] || extension_branch.is_mod_extension.contains(&true) {
+            return None;
+        }
+        // Either a branch is added for the account, or the account leaf in `S` is a placeholder
+        // for the empty child of the branch
+        let is_drifted = extension_branch.is_placeholder[0];
+        let modified_index = extension_branch.branch.modified_index;
+        let is_empty_child =
+            parent.values[ExtensionBranchRowType::Child0 as usize + modified_index][0] == RLP_SHORT;
+        if !is_drifted && !is_empty_child {
+            return None;
+        }
+
+        let node = &mut proof[account_idx];
+        let account = node.account.as_mut().unwrap();
+        if account.is_mod_extension.contains(&true) {
+            return None;
+        }
+        let old_rlp = node.keccak_data[1].to_vec();
+        let key = rlp_list_items(&old_rlp)?[0].to_vec();
+        let leaf = |nonce: u8| {
+            let fields = [
+                vec![nonce],
+                vec![RLP_SHORT],
+                [&[RLP_SHORT + 32][..], &EMPTY_TRIE_HASH[..]].concat(),
+                [&[RLP_SHORT + 32][..], &code_hash[..]].concat(),
+            ];
+            let payload_len: usize = fields.iter().map(|field| field.len()).sum();
+            let value_list_rlp_bytes = vec![RLP_LIST_LONG + 1, payload_len as u8];
+            let value_rlp_bytes = vec![RLP_LONG + 1, (payload_len + 2) as u8];
+            let list_rlp_bytes = vec![RLP_LIST_LONG + 1, (key.len() + payload_len + 4) as u8];
+            let rlp = [
+                &list_rlp_bytes[..],
+                &key[..],
+                &value_rlp_bytes[..],
+                &value_list_rlp_bytes[..],
+                &fields.concat()[..],
+            ]
+            .concat();
+            let rows = fields.map(|mut field| {
+                field.resize(RLP_UNIT_NUM_BYTES, 0);
+                field
+            });
+            (
+                list_rlp_bytes,
+                value_rlp_bytes,
+                value_list_rlp_bytes,
+                rlp,
+                rows,
+            )
+        };
+
+        // Without a drift the placeholder leaf in `S` is the new account before its nonce is set,
+        // otherwise it is the existing account that drifts
+        let sides = if is_drifted {
+            vec![(false, 1)]
+        } else {
+            vec![(true, RLP_SHORT), (false, 1)]
+        };
+        for (is_s, nonce) in sides {
+            let (list_rlp_bytes, value_rlp_bytes, value_list_rlp_bytes, rlp, rows) = leaf(nonce);
+            let idx = if is_s { 0 } else { 1 };
+            account.list_rlp_bytes[idx] = list_rlp_bytes.into();
+            account.value_rlp_bytes[idx] = value_rlp_bytes.into();
+            account.value_list_rlp_bytes[idx] = value_list_rlp_bytes.into();
+            let first_row = if is_s {
+                AccountRowType::NonceS
+            } else {
+                AccountRowType::NonceC
+            } as usize;
+            for (offset, row) in rows.into_iter().enumerate() {
+                node.values[first_row + offset] = row.into();
+            }
+            node.keccak_data[idx] = rlp.into();
+        }
+        let new_rlp = node.keccak_data[1].to_vec();
+
+        proof[0].start.as_mut()?.proof_type = MPTProofType::NonceChanged;
+        replace_hash_up_to_root(&mut proof, old_rlp, new_rlp);
+        Some(proof)
+    }
+
+    #[test]
+    fn test_mpt_storage_value_too_long() {
+        // A storage slot holds a single word
+        let mut nodes = load_proof_from_file("src/mpt_circuit/tests/UpdateOneLevelBigVal.json");
+        set_storage_value_c(&mut nodes, &[0xab; 32]);
+
+        // The assignment rejects a longer value, so the `C` value RLP byte of the word is
+        // overwritten once assigned to declare a 33 byte value instead
+        let mut meta = ConstraintSystem::<Fr>::default();
+        let params = MPTCircuit::<Fr>::new(vec![], MOCK_DEGREE, MOCK_MAX_NODES).params();
+        let (config, _) = MPTCircuit::<Fr>::configure_with_params(&mut meta, params);
+        let cell = config
+            .state_machine
+            .storage_config
+            .value_rlp_byte_cell(false);
+        let leaf_offset: usize = nodes
+            .iter()
+            .take_while(|node| node.storage.is_none())
+            .map(|node| node.values.len())
+            .sum();
+        let row = leaf_offset + cell.rotation();
+
+        for (value_len, is_ok) in [(32, true), (33, false)] {
+            let value_rlp_byte = RLP_SHORT + 1 + value_len;
+            let overrides = vec![(cell.column(), row, Fr::from(value_rlp_byte as u64))];
+            let result = verify_nodes_with_overrides(nodes.clone(), overrides);
+            assert_eq!(result.is_ok(), is_ok, "{value_len}");
+        }
+    }
+
+    #[test]
+    fn test_mpt_storage_value_too_long_on_assign() {
+        // A 33 byte value is rejected when the witness is assigned
+        let mut nodes = load_proof_from_file("src/mpt_circuit/tests/UpdateOneLevelBigVal.json");
+        set_storage_value_c(&mut nodes, &[0xab; 33]);
+        assert!(matches!(mock_prove_nodes(nodes), Err(Error::Synthesis)));
+    }
+
+    #[test]
+    fn test_mpt_storage_value_rlp_byte_too_long() {
+        // The value RLP byte declares a 72 byte value, the witness cannot be assigned
+        for idx in [0, 1] {
+            let mut nodes = load_proof_from_file("src/mpt_circuit/tests/UpdateOneLevel.json");
+            let storage = nodes
+                .iter_mut()
+                .find_map(|node| node.storage.as_mut())
+                .unwrap();
+            storage.value_rlp_bytes[idx] = vec![200].into();
+            assert!(matches!(mock_prove_nodes(nodes), Err(Error::Synthesis)));
+        }
+    }
+
+    #[test]
+    fn test_mpt_leaf_invalid_list_rlp_byte() {
+        // A list header announcing a 3 byte length, which no leaf is long enough to need
+        let invalid_rlp_byte = RLP_LIST_LONG + 3;
+        for (file, is_storage) in [
+            ("UpdateOneLevel.json", true),
+            ("UpdateOneLevelBigVal.json", true),
+            ("BalanceModCShort.json", false),
+        ] {
+            let mut nodes = load_proof_from_file(&format!("src/mpt_circuit/tests/{}", file));
+            assert_eq!(verify_nodes(nodes.clone()), Ok(()), "{}", file);
+
+            let node = nodes
+                .iter_mut()
+                .rfind(|node| match is_storage {
+                    true => node.storage.is_some(),
+                    false => node.account.is_some(),
+                })
+                .unwrap();
+            let old_rlp = node.keccak_data[1].to_vec();
+            let new_rlp = [&[invalid_rlp_byte][..], &old_rlp[1..]].concat();
+            let list_rlp_bytes = match is_storage {
+                true => &mut node.storage.as_mut().unwrap().list_rlp_bytes[1],
+                false => &mut node.account.as_mut().unwrap().list_rlp_bytes[1],
+            };
+            *list_rlp_bytes = [&[invalid_rlp_byte][..], &list_rlp_bytes[1..]]
+                .concat()
+                .into();
+            node.keccak_data[1] = new_rlp.clone().into();
+            replace_hash_up_to_root(&mut nodes, old_rlp, new_rlp);
+
+            assert!(verify_nodes(nodes).is_err(), "{}", file);
+        }
+    }
+
+    #[test]
+    fn test_mpt_storage_leaf_key_trailing_byte() {
+        // The bytes of a row past the length of its RLP item have to be zero, otherwise they
+        // would be part of the RLC of the key without being hashed
+        for row in [StorageRowType::KeyS as usize, StorageRowType::KeyC as usize] {
+            let mut nodes = load_proof_from_file("src/mpt_circuit/tests/UpdateOneLevel.json");
+            let leaf = nodes
+                .iter_mut()
+                .find(|node| node.storage.is_some())
+                .unwrap();
+            let mut key = leaf.values[row].to_vec();
+            assert_eq!(key[0], RLP_SHORT + 32);
+            assert_eq!(key[33], 0);
+            key[33] = 1;
+            leaf.values[row] = key.into();
+
+            let failures = verify_nodes(nodes).unwrap_err();
+            assert!(
+                failures
+                    .iter()
+                    .any(|failure| matches!(failure, VerifyFailure::Lookup { .. })),
+                "row {}",
+                row
+            );
+        }
+    }
+
+    #[test]
+    fn test_mpt_wrong_modified_index() {
+        let nodes = load_proof_from_file("src/mpt_circuit/tests/AddBranch.json");
+        let branch_idx = |is_placeholder: bool| {
+            nodes
+                .iter()
+                .rposition(|node| {
+                    node.extension_branch
+                        .as_ref()
+                        .map_or(false, |extension_branch| {
+                            extension_branch.is_placeholder.iter().any(|p| *p) == is_placeholder
+                        })
+                })
+                .unwrap()
+        };
+
+        // The added branch, its modified child is the new leaf and the drifted child the leaf
+        // that was moved down into the branch
+        let added_branch = branch_idx(true);
+        let branch = &nodes[added_branch]
+            .extension_branch
+            .as_ref()
+            .unwrap()
+            .branch;
+        let (modified_index, drifted_index) = (branch.modified_index, branch.drifted_index);
+        let empty_index = (0..ARITY)
+            .find(|idx| *idx != modified_index && *idx != drifted_index)
+            .unwrap();
+        // The branch above it
+        let parent_branch = branch_idx(false);
+        let parent_modified_index = nodes[parent_branch]
+            .extension_branch
+            .as_ref()
+            .unwrap()
+            .branch
+            .modified_index;
+
+        for (node_idx, wrong_index) in [
+            (added_branch, drifted_index),
+            (added_branch, empty_index),
+            (parent_branch, (parent_modified_index + 1) % ARITY),
+        ] {
+            let mut nodes = nodes.clone();
+            let extension_branch = nodes[node_idx].extension_branch.as_mut().unwrap();
+            extension_branch.branch.modified_index = wrong_index;
+            assert!(
+                verify_nodes(nodes).is_err(),
+                "modified_index {wrong_index} of node {node_idx} was accepted"
+            );
+        }
+    }
+
+    #[test]
+    fn test_minimize_failing_witness() {
+        // Two storage modifications, nodes 13 to 25 are the second proof
+        let mut nodes = load_proof_from_file("src/mpt_circuit/tests/UpdateTwoModifications.json");
+        let second_proof = 13;
+
+        // Corrupt the last branch of the second proof
+        let branch_idx = nodes
+            .iter()
+            .rposition(|node| node.extension_branch.is_some())
+            .unwrap();
+        assert!(branch_idx > second_proof);
+        let branch = &mut nodes[branch_idx].extension_branch.as_mut().unwrap().branch;
+        branch.modified_index = (branch.modified_index + 1) % ARITY;
+
+        let minimized = minimize_failing_witness(&nodes);
+        let values = |nodes: &[Node]| {
+            nodes
+                .iter()
+                .map(|node| node.values.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(values(&minimized), values(&nodes[second_proof..]));
+        assert!(verify_nodes(minimized).is_err());
+    }
+
+    #[test]
+    fn test_mpt_drifted_leaf_in_parallel_branch() {
+        // Insertions add a placeholder `S` branch, deletions a placeholder `C` branch
+        for file in [
+            "AddBranch.json",
+            "DeleteBranch.json",
+            "AccountAddPlaceholderBranch.json",
+            "AccountDeletePlaceholderBranch.json",
+        ] {
+            let nodes = load_proof_from_file(&format!("src/mpt_circuit/tests/{}", file));
+            let branch_idx = nodes
+                .iter()
+                .position(|node| {
+                    node.extension_branch
+                        .as_ref()
+                        .map_or(false, |extension_branch| {
+                            extension_branch.is_placeholder.iter().any(|p| *p)
+                        })
+                })
+                .unwrap();
+            let extension_branch = nodes[branch_idx].extension_branch.as_ref().unwrap();
+            let parallel = extension_branch
+                .is_placeholder
+                .iter()
+                .position(|p| !*p)
+                .unwrap();
+            let leaf_idx = branch_idx + 1;
+            assert!(nodes[leaf_idx].account.is_some() || nodes[leaf_idx].storage.is_some());
+
+            // The drifted leaf is the last stream hashed by the leaf, it has to be the child at
+            // `drifted_index` of the non-placeholder branch
+            let drifted_rlp = nodes[leaf_idx].keccak_data.last().unwrap();
+            let children = rlp_list_items(&nodes[branch_idx].keccak_data[parallel]).unwrap();
+            let drifted_child = children[extension_branch.branch.drifted_index];
+            assert_eq!(
+                drifted_child,
+                [&[160][..], &keccak256(drifted_rlp.as_slice())[..]].concat(),
+                "{}",
+                file
+            );
+            assert!(verify_nodes(nodes.clone()).is_ok(), "{}", file);
+
+            // The hash of the drifted leaf computed by the circuit is looked up in the keccak
+            // table, a drifted leaf that is not the child of the parallel branch is rejected
+            let mut nodes = nodes;
+            let mut drifted_rlp = nodes[leaf_idx].keccak_data.last().unwrap().to_vec();
+            *drifted_rlp.last_mut().unwrap() ^= 1;
+            *nodes[leaf_idx].keccak_data.last_mut().unwrap() = drifted_rlp.into();
+            assert!(verify_nodes(nodes).is_err(), "{}", file);
+        }
+    }
+
+    #[test]
+    fn test_empty_trie_hash() {
+        // The empty trie is the RLP of the empty string
+        assert_eq!(keccak256([RLP_NIL]), EMPTY_TRIE_HASH);
+        assert!(is_empty_trie_root(&keccak256([RLP_NIL])));
+        assert!(!is_empty_trie_root(&EMPTY_CODE_HASH));
+        assert!(!is_empty_trie_root(&EMPTY_TRIE_HASH[1..]));
+
+        let hash = H256::from(EMPTY_TRIE_HASH);
+        assert_eq!(empty_trie_hash_word::<Fr>(), WordLoHi::from(hash));
+    }
+
+    #[test]
+    fn test_rlp_decode_len() {
+        for (first_byte, second_byte, expected) in [
+            // Single byte
+            (0x00, 0xff, Some((false, 1, 0))),
+            (0x7f, 0xff, Some((false, 1, 0))),
+            // Short string
+            (RLP_SHORT, 0xff, Some((false, 0, 1))),
+            (RLP_SHORT + 1, 0x01, Some((false, 1, 1))),
+            (RLP_SHORT + 32, 0x00, Some((false, 32, 1))),
+            (RLP_LONG, 0x00, Some((false, 55, 1))),
+            // Long string
+            (RLP_LONG + 1, 56, Some((true, 56, 2))),
+            (RLP_LONG + 1, 0xff, Some((true, 255, 2))),
+            (RLP_LONG + 2, 0x01, None),
+            // Lists
+            (RLP_LIST_SHORT, 0x00, None),
+            (RLP_LIST_LONG + 1, 0x40, None),
+        ] {
+            assert_eq!(
+                rlp_decode_len(first_byte, second_byte),
+                expected,
+                "{:#x}",
+                first_byte
+            );
+        }
+    }
+
+    #[test]
+    fn test_storage_item_rows() {
+        // The rows and item types `MPTContext::storage_item` reads, in the order the witness
+        // generator writes them
+        for (row, idx, item_type) in [
+            (StorageRowType::KeyS, 0, RlpItemType::Key),
+            (StorageRowType::ValueS, 1, RlpItemType::Value),
+            (StorageRowType::KeyC, 2, RlpItemType::Key),
+            (StorageRowType::ValueC, 3, RlpItemType::Value),
+            (StorageRowType::Drifted, 4, RlpItemType::Key),
+            (StorageRowType::Wrong, 5, RlpItemType::Key),
+            (StorageRowType::LongExtNodeKey, 6, RlpItemType::Key),
+            (StorageRowType::LongExtNodeNibbles, 7, RlpItemType::Nibbles),
+            (StorageRowType::LongExtNodeValue, 8, RlpItemType::Value),
+            (StorageRowType::ShortExtNodeKey, 9, RlpItemType::Key),
+            (
+                StorageRowType::ShortExtNodeNibbles,
+                10,
+                RlpItemType::Nibbles,
+            ),
+            (StorageRowType::ShortExtNodeValue, 11, RlpItemType::Value),
+            (StorageRowType::Address, 12, RlpItemType::Hash),
+            (StorageRowType::Key, 13, RlpItemType::Hash),
+        ] {
+            assert_eq!(row as usize, idx);
+            assert_eq!(NODE_RLP_TYPES_STORAGE[idx], item_type);
+        }
+        assert_eq!(StorageRowType::Count as usize, NODE_RLP_TYPES_STORAGE.len());
+
+        // Every storage leaf of a witness has one value per row
+        let nodes = load_proof_from_file("src/mpt_circuit/tests/UpdateOneLevel.json");
+        let leaf = nodes.iter().find(|node| node.storage.is_some()).unwrap();
+        assert_eq!(leaf.values.len(), StorageRowType::Count as usize);
+    }
+
+    #[test]
+    fn test_mpt_storage_default_zero() {
+        // The slot was never written: its default zero value is proven by a non-existence proof
+        let nodes = load_proof_from_file("src/mpt_circuit/tests/NonExistingStorageNil.json");
+        let proof_type = nodes[0].start.as_ref().unwrap().proof_type;
+        assert_eq!(proof_type, MPTProofType::storage(0.into(), 0.into()));
+        assert_eq!(proof_type, MPTProofType::StorageDoesNotExist);
+        assert_eq!(verify_nodes(nodes), Ok(()));
+
+        // The slot is explicitly set to zero: its leaf is deleted by a storage change
+        let nodes = load_proof_from_file("src/mpt_circuit/tests/Delete.json");
+        let proof_type = nodes[0].start.as_ref().unwrap().proof_type;
+        assert_eq!(proof_type, MPTProofType::storage(4.into(), 0.into()));
+        assert_eq!(proof_type, MPTProofType::StorageChanged);
+        assert_eq!(verify_nodes(nodes), Ok(()));
+    }
+
+    #[test]
+    fn test_mpt_storage_root_of_account() {
+        // The storage trie of the account is a single leaf, the account stores its hash as the
+        // storage root
+        let nodes = load_proof_from_file("src/mpt_circuit/tests/OnlyLeafInStorageProof.json");
+        assert_eq!(verify_nodes(nodes.clone()), Ok(()));
+
+        // Changing the leaf updates the storage root of the account up to the state root
+        let mut updated = nodes.clone();
+        set_storage_value_c(&mut updated, &[0xab; 32]);
+        assert_eq!(verify_nodes(updated.clone()), Ok(()));
+
+        // The changed leaf is not the root of the storage trie of the unchanged account
+        let storage_idx = nodes.iter().position(|n| n.storage.is_some()).unwrap();
+        let mut mismatch = nodes;
+        mismatch[storage_idx] = updated[storage_idx].clone();
+        assert!(verify_nodes(mismatch).is_err());
+    }
+
+    #[test]
+    fn test_mpt_common_branch_prefix() {
+        // Two storage proofs of the same account, the storage keys diverge at the first branch of
+        // the storage trie
+        let nodes = load_proof_from_file("src/mpt_circuit/tests/UpdateTwoModifications.json");
+        let second = nodes
+            .iter()
+            .rposition(|node| {
+                node.start
+                    .as_ref()
+                    .map_or(false, |start| start.proof_type != MPTProofType::Disabled)
+            })
+            .unwrap();
+        let (a, b) = nodes.split_at(second);
+        let account_idx = a.iter().position(|node| node.account.is_some()).unwrap();
+        let account_path_len = a[..account_idx]
+            .iter()
+            .filter(|node| node.extension_branch.is_some())
+            .count();
+        assert_eq!(common_branch_prefix(a, b), account_path_len);
+        assert_eq!(common_branch_prefix(b, a), account_path_len);
+
+        // A proof shares all of its branches with itself
+        let num_branches = a
+            .iter()
+            .filter(|node| node.extension_branch.is_some())
+            .count();
+        assert_eq!(common_branch_prefix(a, a), num_branches);
+    }
+
+    #[test]
+    fn test_mpt_code_hash_is_empty() {
+        for file in ["BalanceModCShort.json", "AccountExtensionInFirstLevel.json"] {
+            let nodes = load_proof_from_file(&format!("src/mpt_circuit/tests/{}", file));
+            let proof = code_hash_is_empty_proof(&nodes).unwrap();
+            assert_eq!(verify_nodes(proof), Ok(()), "{}", file);
+        }
+
+        // The account is a contract, its code hash can't be claimed to be empty
+        let nodes = load_proof_from_file("src/mpt_circuit/tests/LongKey.json");
+        let proof = code_hash_is_empty_proof(&nodes).unwrap();
+        assert!(verify_nodes(proof).is_err());
+
+        // The path to the account contains a placeholder branch
+        let nodes = load_proof_from_file("src/mpt_circuit/tests/AccountBranchPlaceholder.json");
+        assert!(code_hash_is_empty_proof(&nodes).is_none());
+    }
+
+    #[test]
+    fn test_mpt_code_hash_read() {
+        let read = |file: &str| {
+            let nodes = load_proof_from_file(&format!("src/mpt_circuit/tests/{}", file));
+            let proof = code_hash_read_proof(&nodes).unwrap();
+            assert_eq!(verify_nodes(proof), Ok(()), "{}", file);
+            code_hash_read_value(&nodes).unwrap()
+        };
+        let empty_code_hash = U256::from_big_endian(&EMPTY_CODE_HASH);
+
+        // Contract
+        let code_hash = read("LongKey.json");
+        assert!(!code_hash.is_zero() && code_hash != empty_code_hash);
+        // EOA
+        assert_eq!(read("BalanceModCShort.json"), empty_code_hash);
+        // Empty account, no nonce and no balance
+        assert_eq!(read("AccountExtensionInFirstLevel.json"), U256::zero());
+        // Non-existing account
+        assert_eq!(read("NonExistingAccount.json"), U256::zero());
+
+        // The path to the account contains a placeholder branch
+        let nodes = load_proof_from_file("src/mpt_circuit/tests/AccountBranchPlaceholder.json");
+        assert!(code_hash_read_proof(&nodes).is_none());
+    }
+
+    #[test]
+    fn test_mpt_contract_creation() {
+        let code_hash = keccak256(&[0x60, 0x00, 0x60, 0x00, 0xf3]);
+        for file in [
+            "AddAccount.json",
+            "AccountAddPlaceholderBranch.json",
+            "AccountAddPlaceholderExtension.json",
+        ] {
+            let nodes = load_proof_from_file(&format!("src/mpt_circuit/tests/{}", file));
+            let proof = contract_creation_proof(&nodes, &code_hash).unwrap();
+            assert_eq!(verify_nodes(proof), Ok(()), "{}", file);
+        }
+
+        // The contract is inserted into a new branch and the existing account drifts into it
+        let nodes = load_proof_from_file("src/mpt_circuit/tests/AccountAddPlaceholderBranch.json");
+        let proof = contract_creation_proof(&nodes, &code_hash).unwrap();
+        assert!(proof.iter().any(|node| {
+            node.extension_branch
+                .as_ref()
+                .map_or(false, |extension_branch| extension_branch.is_placeholder[0])
+        }));
+
+        // The drifted leaf has to be the leaf replaced by the new branch
+        let account_idx = proof
+            .iter()
+            .position(|node| node.account.is_some())
+            .unwrap();
+        let mut wrong_drift = proof;
+        let drifted = &mut wrong_drift[account_idx].values[AccountRowType::Drifted as usize];
+        let mut row = drifted.to_vec();
+        row[2] ^= 1;
+        *drifted = row.into();
+        assert!(verify_nodes(wrong_drift).is_err());
+
+        // The account already exists
+        let nodes = load_proof_from_file("src/mpt_circuit/tests/BalanceModCShort.json");
+        assert!(contract_creation_proof(&nodes, &code_hash).is_none());
+    }
+
+    #[test]
+    fn test_mpt_unusable_rows() {
+        for disable_storage in [false, true] {
+            for randomness in [MPTRandomness::Challenge, MPTRandomness::Fixed(0x100)] {
+                let params = MPTCircuitParams {
+                    disable_storage,
+                    randomness,
+                    ..MPTCircuit::<Fr>::new(vec![], MOCK_DEGREE, MOCK_MAX_NODES).params()
+                };
+                assert_eq!(
+                    MPTCircuit::<Fr>::unusable_rows(),
+                    unusable_rows::<Fr, MPTCircuit<Fr>>(params),
+                    "{params:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn mpt_circuit_unusable_rows() {
+        let circuit = MPTCircuit::<Fr>::new_from_block(&witness::Block::default());
+        assert_eq!(
+            MPTCircuit::<Fr>::unusable_rows(),
+            unusable_rows::<Fr, MPTCircuit::<Fr>>(circuit.params()),
+        );
+        // The other params are covered by `test_mpt_unusable_rows`
+
+        // The MPT circuit is standalone, the block has no MPT proofs to build it from
+        assert!(circuit.nodes.is_empty());
+        assert!(circuit.keccak_data.is_empty());
+        assert_eq!(circuit.params(), MPTCircuitParams::default());
+        // Without MPT proofs in the block only the tables are assigned
+        assert_eq!(
+            MPTCircuit::<Fr>::min_num_rows_block(&witness::Block::default()),
+            (
+                circuit.params().fixed_table_num_rows(),
+                circuit.params().fixed_table_num_rows()
+            ),
+        );
+    }
+
+    #[test]
+    fn test_mpt_keccak_lookups_batched() {
+        let mut meta = ConstraintSystem::<Fr>::default();
+        let (config, _) =
+            MPTCircuit::<Fr>::configure_with_params(&mut meta, MPTCircuitParams::default());
+
+        // The keccak tuples stored with `=>> @KECCAK` are all looked up through the single
+        // keccak lookup column of the state cell manager
+        let num_keccak_lookups = config
+            .cb
+            .base
+            .lookups
+            .iter()
+            .filter(|lookup| lookup.description == format!("{:?}", MptTableType::Keccak))
+            .count();
+        assert_eq!(num_keccak_lookups, 1);
+
+        // Each tuple takes a row of the column in the region of its state
+        let num_keccak_tuples = |region: MPTRegion| {
+            config
+                .cb
+                .base
+                .get_stored_expressions(region as usize)
+                .iter()
+                .filter(|stored| stored.cell_type == MptCellType::Lookup(MptTableType::Keccak))
+                .count()
+        };
+        assert!(num_keccak_tuples(MPTRegion::Storage) > num_keccak_lookups);
+        for region in [MPTRegion::Branch, MPTRegion::Account, MPTRegion::Storage] {
+            assert!(num_keccak_tuples(region) <= STATE_HEIGHT);
+        }
+    }
+
+    #[test]
+    fn test_mpt_storage_leaf_rotations() {
+        let mut meta = ConstraintSystem::<Fr>::default();
+        let params = MPTCircuit::<Fr>::new(vec![], MOCK_DEGREE, MOCK_MAX_NODES).params();
+        let (config, _) = MPTCircuit::<Fr>::configure_with_params(&mut meta, params);
+
+        let rotations: Vec<_> = config
+            .cb
+            .base
+            .rotations()
+            .into_iter()
+            .filter(|(name, _)| name.contains("storage_leaf.rs"))
+            .collect();
+        assert!(!rotations.is_empty());
+        for (name, rotations) in rotations.iter() {
+            println!("{}: {:?}", name, rotations);
+            // The storage leaf constraints only reach the rows of the storage leaf
+            assert!(rotations
+                .iter()
+                .all(|rot| (0..StorageRowType::Count as i32).contains(rot)));
+        }
+    }
+
+    #[test]
+    fn test_is_placeholder_branch() {
+        let placeholders = |path: &str, is_s: bool| -> Vec<usize> {
+            load_proof_from_file(path)
+                .iter()
+                .enumerate()
+                .filter(|(_, node)| node.is_placeholder_branch(is_s))
+                .map(|(idx, _)| idx)
+                .collect()
+        };
+
+        // A leaf is turned into a branch, the `S` proof has a placeholder branch
+        let path = "src/mpt_circuit/tests/AddBranch.json";
+        assert_eq!(placeholders(path, true), vec![10]);
+        assert!(placeholders(path, false).is_empty());
+        // The placeholder is filled with the branch of the other proof
+        let node = &load_proof_from_file(path)[10];
+        assert_eq!(node.keccak_data[0].to_vec(), node.keccak_data[1].to_vec());
+
+        // A branch is turned into a leaf, the `C` proof has a placeholder branch
+        let path = "src/mpt_circuit/tests/DeleteBranch.json";
+        assert!(placeholders(path, true).is_empty());
+        assert_eq!(placeholders(path, false), vec![11]);
+
+        let path = "src/mpt_circuit/tests/UpdateOneLevel.json";
+        assert!(placeholders(path, true).is_empty());
+        assert!(placeholders(path, false).is_empty());
+    }
 }
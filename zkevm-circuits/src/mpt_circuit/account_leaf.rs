@@ -17,16 +17,16 @@ use crate::{
         cached_region::CachedRegion,
         cell_manager::Cell,
         constraint_builder::{RLCChainableRev, RLCable},
-        gadgets::IsEqualGadget,
+        gadgets::{IsEqualGadget, IsEqualWordGadget, IsZeroGadget},
     },
     evm_circuit::util::from_bytes,
     mpt_circuit::{
         helpers::{
-            key_memory, main_memory, num_nibbles, parent_memory, DriftedGadget, Indexable,
-            IsPlaceholderLeafGadget, KeyData, MPTConstraintBuilder, ParentData, WrongGadget,
-            KECCAK,
+            empty_trie_hash_word, key_memory, main_memory, num_nibbles, parent_memory,
+            DriftedGadget, Indexable, IsPlaceholderLeafGadget, KeyData, MPTConstraintBuilder,
+            ParentData, WrongGadget, KECCAK,
         },
-        param::{EMPTY_TRIE_HASH, KEY_LEN_IN_NIBBLES, RLP_LIST_LONG, RLP_LONG},
+        param::{EMPTY_CODE_HASH, KEY_LEN_IN_NIBBLES, RLP_LIST_LONG, RLP_LONG},
         MPTConfig, MPTContext, MptMemory, RlpItemType,
     },
     table::MPTProofType,
@@ -51,6 +51,10 @@ pub(crate) struct AccountLeafConfig<F> {
     is_balance_mod: IsEqualGadget<F>,
     is_storage_mod: IsEqualGadget<F>,
     is_codehash_mod: IsEqualGadget<F>,
+    is_codehash_empty_proof: IsEqualGadget<F>,
+    is_codehash_read: IsEqualGadget<F>,
+    is_nonce_and_balance_zero: IsZeroGadget<F>,
+    is_empty_code_hash: IsEqualWordGadget<F>,
     is_mod_extension: [Cell<F>; 2],
     mod_extension: ModExtensionGadget<F>,
 }
@@ -202,6 +206,16 @@ impl<F: Field> AccountLeafConfig<F> {
                 config.main_data.proof_type.expr(),
                 MPTProofType::CodeHashChanged.expr(),
             );
+            config.is_codehash_empty_proof = IsEqualGadget::construct(
+                &mut cb.base,
+                config.main_data.proof_type.expr(),
+                MPTProofType::CodeHashIsEmpty.expr(),
+            );
+            config.is_codehash_read = IsEqualGadget::construct(
+                &mut cb.base,
+                config.main_data.proof_type.expr(),
+                MPTProofType::CodeHashRead.expr(),
+            );
 
             for is_s in [true, false] {
                 ifx! {not!(config.is_mod_extension[is_s.idx()].expr()) => {
@@ -281,7 +295,7 @@ impl<F: Field> AccountLeafConfig<F> {
                     // branch.
                     ifx! {not!(config.is_placeholder_leaf[is_s.idx()]) => {
                         let hash = parent_data[is_s.idx()].hash.expr();
-                        require!((1.expr(), leaf_rlc, rlp_key.rlp_list.num_bytes(), hash.lo(), hash.hi()) =>> @KECCAK);
+                        require!((1.expr(), leaf_rlc, rlp_key.leaf_len(), hash.lo(), hash.hi()) =>> @KECCAK);
                     } elsex {
                         // For NonExistingAccountProof prove there is no leaf.
 
@@ -292,7 +306,7 @@ impl<F: Field> AccountLeafConfig<F> {
                             ifx! {parent_data[is_s.idx()].is_root.expr() => {
                                 // If leaf is placeholder and the parent is root (no branch above leaf) and the proof is NonExistingStorageProof,
                                 // the trie needs to be empty.
-                                let empty_hash = WordLoHi::<F>::from(U256::from_big_endian(&EMPTY_TRIE_HASH));
+                                let empty_hash = empty_trie_hash_word::<F>();
                                 let hash = parent_data[is_s.idx()].hash.expr();
                                 require!(hash.lo() => Expression::Constant(empty_hash.lo()));
                                 require!(hash.hi() => Expression::Constant(empty_hash.hi()));
@@ -321,6 +335,8 @@ impl<F: Field> AccountLeafConfig<F> {
                     // The RLP encoded string always has 2 RLP bytes.
                     value_list_num_bytes[is_s.idx()] = value_rlp_bytes[1].expr() + 2.expr();
 
+                    // The account is always longer than 55 bytes, its list has 2 RLP bytes.
+                    require!(config.rlp_key[is_s.idx()].rlp_list_bytes[0] => RLP_LIST_LONG + 1);
                     // Account length needs to equal all key bytes and all values list bytes.
                     require!(config.rlp_key[is_s.idx()].rlp_list.len() => config.rlp_key[is_s.idx()].key_value.num_bytes() + value_list_num_bytes[is_s.idx()].expr());
                 }};
@@ -433,6 +449,37 @@ impl<F: Field> AccountLeafConfig<F> {
                 require!(config.main_data.new_root => config.main_data.old_root);
                 require!(key_rlc[true.idx()] => key_rlc[false.idx()]);
             }}
+            let empty_code_hash = WordLoHi::<F>::from(U256::from_big_endian(&EMPTY_CODE_HASH));
+            ifx! {config.is_codehash_empty_proof => {
+                // Proving that the account is an EOA doesn't modify the tree, all the account
+                // fields are already checked to be the same in S and C above
+                require!(config.main_data.new_root => config.main_data.old_root);
+                require!(codehash[true.idx()].lo() => Expression::Constant(empty_code_hash.lo()));
+                require!(codehash[true.idx()].hi() => Expression::Constant(empty_code_hash.hi()));
+            }}
+            // An empty account (no nonce, no balance and no code, EIP-161) has its code hash
+            // read as 0 (EIP-1052). All the limbs fit in 128 bits so their sum can't wrap around.
+            config.is_nonce_and_balance_zero = IsZeroGadget::construct(
+                &mut cb.base,
+                nonce[true.idx()].lo()
+                    + nonce[true.idx()].hi()
+                    + balance[true.idx()].lo()
+                    + balance[true.idx()].hi(),
+            );
+            config.is_empty_code_hash = IsEqualWordGadget::construct(
+                &mut cb.base,
+                &codehash[true.idx()],
+                &empty_code_hash.map(Expression::Constant),
+            );
+            let read_codehash = codehash[true.idx()].mul_selector(
+                1.expr()
+                    - config.is_nonce_and_balance_zero.expr() * config.is_empty_code_hash.expr(),
+            );
+            ifx! {config.is_codehash_read => {
+                // Reading the code hash doesn't modify the tree, all the account fields are
+                // already checked to be the same in S and C above
+                require!(config.main_data.new_root => config.main_data.old_root);
+            }}
 
             // Put the data in the lookup table
             let (proof_type, old_value_lo, old_value_hi, new_value_lo, new_value_hi) = _matchx! {cb, (
@@ -440,6 +487,8 @@ impl<F: Field> AccountLeafConfig<F> {
                 config.is_balance_mod => (MPTProofType::BalanceChanged.expr(), balance[true.idx()].lo(), balance[true.idx()].hi(), balance[false.idx()].lo(), balance[false.idx()].hi()),
                 config.is_storage_mod => (MPTProofType::StorageChanged.expr(), storage[true.idx()].lo(), storage[true.idx()].hi(), storage[false.idx()].lo(), storage[false.idx()].hi()),
                 config.is_codehash_mod => (MPTProofType::CodeHashChanged.expr(), codehash[true.idx()].lo(), codehash[true.idx()].hi(), codehash[false.idx()].lo(), codehash[false.idx()].hi()),
+                config.is_codehash_empty_proof => (MPTProofType::CodeHashIsEmpty.expr(), codehash[true.idx()].lo(), codehash[true.idx()].hi(), codehash[false.idx()].lo(), codehash[false.idx()].hi()),
+                config.is_codehash_read => (MPTProofType::CodeHashRead.expr(), read_codehash.lo(), read_codehash.hi(), read_codehash.lo(), read_codehash.hi()),
                 config.is_account_delete_mod => (MPTProofType::AccountDestructed.expr(), 0.expr(), 0.expr(), 0.expr(), 0.expr()),
                 config.is_non_existing_account_proof => (MPTProofType::AccountDoesNotExist.expr(), 0.expr(), 0.expr(), 0.expr(), 0.expr()),
                 _ => (MPTProofType::Disabled.expr(), 0.expr(), 0.expr(), 0.expr(), 0.expr()),
@@ -682,6 +731,35 @@ impl<F: Field> AccountLeafConfig<F> {
             main_data.proof_type.scalar(),
             MPTProofType::CodeHashChanged.scalar(),
         )? == true.scalar();
+        let is_codehash_empty_proof = self.is_codehash_empty_proof.assign(
+            region,
+            offset,
+            main_data.proof_type.scalar(),
+            MPTProofType::CodeHashIsEmpty.scalar(),
+        )? == true.scalar();
+        let is_codehash_read = self.is_codehash_read.assign(
+            region,
+            offset,
+            main_data.proof_type.scalar(),
+            MPTProofType::CodeHashRead.scalar(),
+        )? == true.scalar();
+        let empty_code_hash = WordLoHi::<F>::from(U256::from_big_endian(&EMPTY_CODE_HASH));
+        let is_nonce_and_balance_zero = self.is_nonce_and_balance_zero.assign(
+            region,
+            offset,
+            nonce[true.idx()].lo()
+                + nonce[true.idx()].hi()
+                + balance[true.idx()].lo()
+                + balance[true.idx()].hi(),
+        )? == true.scalar();
+        self.is_empty_code_hash
+            .assign(region, offset, codehash[true.idx()], empty_code_hash)?;
+        let read_codehash = if is_nonce_and_balance_zero && codehash[true.idx()] == empty_code_hash
+        {
+            WordLoHi::zero()
+        } else {
+            codehash[true.idx()]
+        };
         // Drifted leaf handling
         self.drifted.assign(
             region,
@@ -726,6 +804,10 @@ impl<F: Field> AccountLeafConfig<F> {
             (MPTProofType::StorageChanged, storage)
         } else if is_codehash_mod {
             (MPTProofType::CodeHashChanged, codehash)
+        } else if is_codehash_empty_proof {
+            (MPTProofType::CodeHashIsEmpty, codehash)
+        } else if is_codehash_read {
+            (MPTProofType::CodeHashRead, vec![read_codehash; 2])
         } else if is_account_delete_mod {
             (MPTProofType::AccountDestructed, vec![WordLoHi::zero(); 2])
         } else if is_non_existing_proof {
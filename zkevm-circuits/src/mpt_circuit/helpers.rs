@@ -17,14 +17,15 @@ use crate::{
     mpt_circuit::{
         param::{
             ADDRESS_WIDTH, EMPTY_TRIE_HASH, HASH_WIDTH, KEY_LEN_IN_NIBBLES, KEY_PREFIX_EVEN,
-            KEY_TERMINAL_PREFIX_EVEN, RLP_UNIT_NUM_BYTES, RLP_UNIT_NUM_VALUE_BYTES,
+            KEY_TERMINAL_PREFIX_EVEN, KEY_TERMINAL_PREFIX_ODD, RLP_HASH_VALUE, RLP_LONG, RLP_SHORT,
+            RLP_UNIT_NUM_BYTES, RLP_UNIT_NUM_VALUE_BYTES,
         },
-        rlp_gadgets::{get_ext_odd_nibble, get_terminal_odd_nibble},
+        rlp_gadgets::{get_ext_odd_nibble, get_len_short, get_terminal_odd_nibble},
     },
     table::LookupTable,
     util::{word::WordLoHi, Challenges, Expr},
 };
-use eth_types::{Field, OpsIdentity, Word as U256};
+use eth_types::{keccak256, Field, OpsIdentity, Word as U256};
 use gadgets::util::{not, or, pow, xor, Scalar};
 use halo2_proofs::{
     circuit::Value,
@@ -196,6 +197,19 @@ impl LeafKeyWitness {
         key_mult: F,
         r: F,
     ) -> (F, F) {
+        if rlp_key.is_short() {
+            // A single byte key has no RLP byte: it's the terminal prefix, with the last nibble of
+            // the key when odd
+            return match rlp_key.bytes[0] {
+                KEY_TERMINAL_PREFIX_EVEN => (key_rlc, key_mult),
+                byte => (
+                    key_rlc
+                        + (F::from(byte as u64) - F::from(KEY_TERMINAL_PREFIX_ODD as u64))
+                            * key_mult,
+                    key_mult * r,
+                ),
+            };
+        }
         if rlp_key.len() <= 1 {
             return (key_rlc, key_mult);
         }
@@ -376,9 +390,20 @@ impl<F: Field> ListKeyGadget<F> {
             .0
             .rlc_chain_rev(self.key_value.rlc_chain_data())
     }
+
+    /// Length in bytes of the leaf (list RLP bytes included), the length used in the keccak
+    /// lookup of the leaf hash in its parent.
+    pub(crate) fn leaf_len(&self) -> Expression<F> {
+        self.rlp_list.num_bytes()
+    }
 }
 
 impl ListKeyWitness {
+    /// Length in bytes of the leaf, see [`ListKeyGadget::leaf_len`]
+    pub(crate) fn leaf_len(&self) -> usize {
+        self.rlp_list.num_bytes()
+    }
+
     /// Number of bytes of RLP (including list RLP bytes) and key
     pub(crate) fn rlc_leaf<F: Field>(&self, r: F) -> (F, F) {
         self.rlp_list
@@ -922,6 +947,44 @@ pub(crate) fn main_memory() -> MptCellType {
     MptCellType::MemMain
 }
 
+/// The hash of the empty trie as a word.
+pub(crate) fn empty_trie_hash_word<F: Field>() -> WordLoHi<F> {
+    WordLoHi::from(U256::from_big_endian(&EMPTY_TRIE_HASH))
+}
+
+/// Returns whether `bytes` is the hash of the empty trie.
+pub(crate) fn is_empty_trie_root(bytes: &[u8]) -> bool {
+    bytes == EMPTY_TRIE_HASH.as_slice()
+}
+
+/// Returns whether `node_rlp` is referenced by `reference`, either by its hash or, for nodes
+/// shorter than a hash, directly.
+pub(crate) fn is_referenced(reference: &[u8], node_rlp: &[u8]) -> bool {
+    if node_rlp.len() < HASH_WIDTH {
+        reference.starts_with(node_rlp)
+    } else {
+        reference.first() == Some(&RLP_HASH_VALUE)
+            && reference.get(1..HASH_WIDTH + 1) == Some(&keccak256(node_rlp)[..])
+    }
+}
+
+/// Decodes the length of an RLP string from its first two bytes to return
+/// (is_long, payload_len, header_len): `is_long` is set for strings of more than 55 bytes,
+/// `header_len` is the number of RLP bytes preceding the payload. `second_byte` is only read for
+/// long strings. Returns `None` for lists and for strings longer than 255 bytes, which need more
+/// than one length byte.
+pub(crate) fn rlp_decode_len(first_byte: u8, second_byte: u8) -> Option<(bool, usize, usize)> {
+    const RLP_SHORT_INCLUSIVE: u8 = RLP_SHORT - 1;
+    const RLP_LONG_1: u8 = RLP_LONG + 1;
+
+    match first_byte {
+        0..=RLP_SHORT_INCLUSIVE => Some((false, 1, 0)),
+        RLP_SHORT..=RLP_LONG => Some((false, get_len_short::value(first_byte), 1)),
+        RLP_LONG_1 => Some((true, second_byte as usize, 2)),
+        _ => None,
+    }
+}
+
 /// MPTConstraintBuilder
 #[derive(Clone)]
 pub struct MPTConstraintBuilder<F> {
@@ -1069,7 +1132,7 @@ impl<F: Field> IsPlaceholderLeafGadget<F> {
         parent_word: WordLoHi<Expression<F>>,
     ) -> Self {
         circuit!([meta, cb.base], {
-            let empty_hash = WordLoHi::<F>::from(U256::from_big_endian(&EMPTY_TRIE_HASH));
+            let empty_hash = empty_trie_hash_word::<F>();
             let is_empty_trie = IsEqualWordGadget::construct(
                 &mut cb.base,
                 &parent_word,
@@ -1101,7 +1164,7 @@ impl<F: Field> IsPlaceholderLeafGadget<F> {
         offset: usize,
         hash: WordLoHi<F>,
     ) -> Result<(), Error> {
-        let empty_hash = WordLoHi::<F>::from(U256::from_big_endian(&EMPTY_TRIE_HASH));
+        let empty_hash = empty_trie_hash_word::<F>();
         self.is_empty_trie
             .assign(region, offset, hash, empty_hash)?;
         self.is_nil_in_branch_at_mod_index.assign(
@@ -1177,7 +1240,7 @@ impl<F: Field> DriftedGadget<F> {
                             let leaf_rlc = config.drifted_rlp_key.rlc2(&cb.keccak_r).rlc_chain_rev((leaf_no_key_rlc[is_s.idx()].expr(), leaf_no_key_rlc_mult[is_s.idx()].expr()));
                             // The drifted leaf needs to be stored in the branch at `drifted_index`.
                             let hash = parent_data[is_s.idx()].drifted_parent_hash.expr();
-                            require!((1.expr(), leaf_rlc.expr(), config.drifted_rlp_key.rlp_list.num_bytes(), hash.lo(), hash.hi()) =>> @KECCAK);
+                            require!((1.expr(), leaf_rlc.expr(), config.drifted_rlp_key.leaf_len(), hash.lo(), hash.hi()) =>> @KECCAK);
                         }
                     }}
                 }}
@@ -1518,6 +1581,12 @@ impl<F: Field> MainRLPGadget<F> {
         Ok(rlp)
     }
 
+    /// The cell holding the RLC of the content of the item with the key randomness
+    #[cfg(test)]
+    pub(crate) fn hash_rlc_cell(&self) -> Cell<F> {
+        self.hash_rlc.clone()
+    }
+
     pub(crate) fn create_view(
         &self,
         meta: &mut VirtualCells<F>,
@@ -26,6 +26,12 @@ pub const EMPTY_TRIE_HASH: [u8; 32] = [
     108, 173, 192, 1, 98, 47, 181, 227, 99, 180, 33,
 ];
 
+// Code hash of accounts without code (keccak of the empty string)
+pub const EMPTY_CODE_HASH: [u8; 32] = [
+    197, 210, 70, 1, 134, 247, 35, 60, 146, 126, 125, 178, 220, 199, 3, 192, 229, 0, 182, 83, 202,
+    130, 39, 59, 123, 250, 216, 4, 93, 133, 164, 112,
+];
+
 // Number of bytes required to decode an RLP item
 pub const RLP_UNIT_NUM_BYTES: usize = 34;
 pub const RLP_UNIT_NUM_VALUE_BYTES: usize = RLP_UNIT_NUM_BYTES - 1;
@@ -1,4 +1,4 @@
-use eth_types::{Field, OpsIdentity, U256};
+use eth_types::{Field, OpsIdentity};
 use gadgets::util::Scalar;
 use halo2_proofs::{
     circuit::Value,
@@ -16,12 +16,12 @@ use crate::{
     },
     mpt_circuit::{
         helpers::{
-            key_memory, main_memory, num_nibbles, parent_memory, DriftedGadget,
-            IsPlaceholderLeafGadget, KeyData, MPTConstraintBuilder, MainData, ParentData,
-            ParentDataWitness, KECCAK,
+            empty_trie_hash_word, key_memory, main_memory, num_nibbles, parent_memory,
+            DriftedGadget, IsPlaceholderLeafGadget, KeyData, MPTConstraintBuilder, MainData,
+            ParentData, ParentDataWitness, KECCAK,
         },
-        param::{EMPTY_TRIE_HASH, KEY_LEN_IN_NIBBLES},
-        MPTConfig, MPTContext, MptMemory, RlpItemType,
+        param::{HASH_WIDTH, KEY_LEN_IN_NIBBLES, RLP_SHORT},
+        MPTConfig, MPTContext, MptMemory,
     },
     table::MPTProofType,
     util::word::WordLoHi,
@@ -64,35 +64,20 @@ impl<F: Field> StorageLeafConfig<F> {
 
         circuit!([meta, cb], {
             let key_items = [
-                ctx.rlp_item(meta, cb, StorageRowType::KeyS as usize, RlpItemType::Key),
-                ctx.rlp_item(meta, cb, StorageRowType::KeyC as usize, RlpItemType::Key),
+                ctx.storage_item(meta, cb, StorageRowType::KeyS),
+                ctx.storage_item(meta, cb, StorageRowType::KeyC),
             ];
             config.value_rlp_bytes = [cb.base.query_bytes(), cb.base.query_bytes()];
+            // Storage slots hold a single word. The `Value` item type limits the length of the
+            // decoded value to 32 bytes, so longer values cannot be claimed.
             let value_item = [
-                ctx.rlp_item(
-                    meta,
-                    cb,
-                    StorageRowType::ValueS as usize,
-                    RlpItemType::Value,
-                ),
-                ctx.rlp_item(
-                    meta,
-                    cb,
-                    StorageRowType::ValueC as usize,
-                    RlpItemType::Value,
-                ),
+                ctx.storage_item(meta, cb, StorageRowType::ValueS),
+                ctx.storage_item(meta, cb, StorageRowType::ValueC),
             ];
-            let drifted_item =
-                ctx.rlp_item(meta, cb, StorageRowType::Drifted as usize, RlpItemType::Key);
-            let expected_item =
-                ctx.rlp_item(meta, cb, StorageRowType::Wrong as usize, RlpItemType::Key);
-            let address_item = ctx.rlp_item(
-                meta,
-                cb,
-                StorageRowType::Address as usize,
-                RlpItemType::Hash,
-            );
-            let key_item = ctx.rlp_item(meta, cb, StorageRowType::Key as usize, RlpItemType::Hash);
+            let drifted_item = ctx.storage_item(meta, cb, StorageRowType::Drifted);
+            let expected_item = ctx.storage_item(meta, cb, StorageRowType::Wrong);
+            let address_item = ctx.storage_item(meta, cb, StorageRowType::Address);
+            let key_item = ctx.storage_item(meta, cb, StorageRowType::Key);
 
             for is_s in [true, false] {
                 config.is_mod_extension[is_s.idx()] = cb.query_bool();
@@ -197,6 +182,11 @@ impl<F: Field> StorageLeafConfig<F> {
                     // Make sure the RLP encoding is correct.
                     // storage = [key, "value"]
                     require!(rlp_key.rlp_list.len() => key_items[is_s.idx()].num_bytes() + config.rlp_value[is_s.idx()].num_bytes());
+                    // The leaf has at most 2 * 34 bytes of data so its list header is either a short
+                    // list header, in [RLP_LIST_SHORT, RLP_LIST_LONG], or RLP_LIST_LONG + 1 followed by
+                    // the length.
+                    require!(rlp_key.rlp_list.is_list() => true);
+                    require!(rlp_key.rlp_list.is_short() + rlp_key.rlp_list.is_long() => true);
 
                     // Check if the leaf is in its parent.
                     // Check is skipped for placeholder leaves which are dummy leaves.
@@ -204,11 +194,11 @@ impl<F: Field> StorageLeafConfig<F> {
                     // the leaf too - in this case `parent_data.hash` contains the hash of the node above the placeholder
                     // branch.
                     ifx! {not!(is_placeholder_leaf) => {
-                        config.is_not_hashed[is_s.idx()] = LtGadget::construct(&mut cb.base, rlp_key.rlp_list.num_bytes(), 32.expr());
+                        config.is_not_hashed[is_s.idx()] = LtGadget::construct(&mut cb.base, rlp_key.leaf_len(), 32.expr());
                         ifx!{or::expr(&[parent_data[is_s.idx()].is_root.expr(), not!(config.is_not_hashed[is_s.idx()])]) => {
                             // Hashed leaf in parent branch
                             let hash = parent_data[is_s.idx()].hash.expr();
-                            require!((1.expr(), leaf_rlc.expr(), rlp_key.rlp_list.num_bytes(), hash.lo(), hash.hi()) =>> @KECCAK);
+                            require!((1.expr(), leaf_rlc.expr(), rlp_key.leaf_len(), hash.lo(), hash.hi()) =>> @KECCAK);
                         } elsex {
                             // Non-hashed leaf in parent branch
                             require!(leaf_rlc => parent_data[is_s.idx()].rlc.expr());
@@ -223,7 +213,7 @@ impl<F: Field> StorageLeafConfig<F> {
                             ifx! {parent_data[is_s.idx()].is_root.expr() => {
                                 // If leaf is placeholder and the parent is root (no branch above leaf) and the proof is NonExistingStorageProof,
                                 // the trie needs to be empty.
-                                let empty_hash = WordLoHi::<F>::from(U256::from_big_endian(&EMPTY_TRIE_HASH));
+                                let empty_hash = empty_trie_hash_word::<F>();
                                 let hash = parent_data[is_s.idx()].hash.expr();
                                 require!(hash.lo() => Expression::Constant(empty_hash.lo()));
                                 require!(hash.hi() => Expression::Constant(empty_hash.hi()));
@@ -404,6 +394,19 @@ impl<F: Field> StorageLeafConfig<F> {
         config
     }
 
+    /// Length in bytes of the `S` or `C` leaf, the length used in the keccak lookup of the leaf
+    /// hash in its parent.
+    #[cfg(test)]
+    pub(crate) fn leaf_len(&self, is_s: bool) -> Expression<F> {
+        self.rlp_key[is_s.idx()].leaf_len()
+    }
+
+    /// The cell holding the RLP byte of the `S` or `C` value
+    #[cfg(test)]
+    pub(crate) fn value_rlp_byte_cell(&self, is_s: bool) -> Cell<F> {
+        self.value_rlp_bytes[is_s.idx()][0].clone()
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub fn assign(
         &self,
@@ -461,7 +464,7 @@ impl<F: Field> StorageLeafConfig<F> {
             self.is_not_hashed[is_s.idx()].assign(
                 region,
                 offset,
-                rlp_key_witness.rlp_list.num_bytes().scalar(),
+                rlp_key_witness.leaf_len().scalar(),
                 32.scalar(),
             )?;
 
@@ -492,6 +495,17 @@ impl<F: Field> StorageLeafConfig<F> {
             );
 
             // Value
+            // The value is a word, its RLP is at most `HASH_WIDTH + 1` bytes long. A longer
+            // value is a malformed witness, for which the value RLC would be wrong.
+            let value_rlp_byte = storage.value_rlp_bytes[is_s.idx()][0];
+            if value_rlp_byte > RLP_SHORT + HASH_WIDTH as u8 + 1 {
+                log::error!(
+                    "storage leaf value RLP byte {} declares a value longer than {} bytes",
+                    value_rlp_byte,
+                    HASH_WIDTH + 1
+                );
+                return Err(Error::Synthesis);
+            }
             for (cell, byte) in self.value_rlp_bytes[is_s.idx()]
                 .iter()
                 .zip(storage.value_rlp_bytes[is_s.idx()].iter())
@@ -618,3 +632,176 @@ impl<F: Field> StorageLeafConfig<F> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mpt_circuit::{
+        helpers::ListKeyWitness,
+        load_proof_from_file,
+        param::{RLP_LIST_LONG, RLP_LIST_SHORT},
+        rlp_gadgets::{decode_rlp, RLPListWitness},
+        witness_row::rlp_list_items,
+        MPTCircuit, MPTCircuitParams, MOCK_DEGREE, MOCK_MAX_NODES,
+    };
+    use eth_types::keccak256;
+    use halo2_proofs::{
+        halo2curves::bn256::Fr,
+        plonk::{Circuit, ConstraintSystem},
+    };
+    use std::{collections::HashMap, fs};
+
+    /// Evaluates `expr` with the advice cells set to `values`
+    fn evaluate(expr: &Expression<Fr>, values: &HashMap<(usize, i32), Fr>) -> Fr {
+        expr.evaluate(
+            &|scalar| scalar,
+            &|_| unimplemented!("selector column"),
+            &|_| unimplemented!("fixed column"),
+            &|query| values[&(query.column_index(), query.rotation().0)],
+            &|_| unimplemented!("instance column"),
+            &|_| unimplemented!("challenge"),
+            &|a| -a,
+            &|a, b| a + b,
+            &|a, b| a * b,
+            &|a, scalar| a * scalar,
+        )
+    }
+
+    /// Sets the list RLP cells of the `S` or `C` leaf to `list_rlp_bytes`, like in `assign`, and
+    /// evaluates the leaf length of the circuit.
+    fn eval_leaf_len(
+        storage_config: &StorageLeafConfig<Fr>,
+        is_s: bool,
+        list_rlp_bytes: &[u8],
+    ) -> Fr {
+        let (_, is_short, is_long, is_very_long) = decode_rlp(list_rlp_bytes[0]);
+        let rlp_key = &storage_config.rlp_key[is_s.idx()];
+        let mut values = HashMap::new();
+        let mut set = |cell: &Cell<Fr>, value: Fr| {
+            values.insert((cell.column().index(), cell.rotation() as i32), value);
+        };
+        for (idx, cell) in rlp_key.rlp_list_bytes.iter().enumerate() {
+            set(cell, list_rlp_bytes.get(idx).copied().unwrap_or(0).scalar());
+        }
+        set(&rlp_key.rlp_list.is_short, is_short.scalar());
+        set(&rlp_key.rlp_list.is_long, is_long.scalar());
+        set(&rlp_key.rlp_list.is_very_long, is_very_long.scalar());
+        evaluate(&storage_config.leaf_len(is_s), &values)
+    }
+
+    #[test]
+    fn test_storage_leaf_len_encodings() {
+        let mut meta = ConstraintSystem::<Fr>::default();
+        let (config, _) =
+            MPTCircuit::<Fr>::configure_with_params(&mut meta, MPTCircuitParams::default());
+        let storage_config = &config.state_machine.storage_config;
+
+        let full_key = [&[0xa1, 0x20][..], &[0x11; 32]].concat();
+        let long_value = [&[0xa0][..], &[0x22; 32]].concat();
+        for (name, key, value) in [
+            // Short list: the whole key and a short value
+            ("short", full_key.clone(), vec![0x01]),
+            // Long list: the whole key and a 32 bytes value
+            ("long", full_key, long_value.clone()),
+            // Leaf at the last level, no nibble left in the key
+            ("last_level", vec![0x20], vec![0x01]),
+            // A single nibble left in the key
+            ("one_nibble", vec![0x35], long_value),
+        ] {
+            let payload_len = key.len() + value.len();
+            let list_rlp_bytes = if payload_len <= 55 {
+                vec![RLP_LIST_SHORT + payload_len as u8]
+            } else {
+                vec![RLP_LIST_LONG + 1, payload_len as u8]
+            };
+            let leaf = [list_rlp_bytes.clone(), key, value].concat();
+            assert_eq!(
+                rlp_list_items(&leaf).map(|items| items.len()),
+                Some(2),
+                "{name}"
+            );
+
+            let (is_list, is_short, is_long, is_very_long) = decode_rlp(leaf[0]);
+            let witness = ListKeyWitness {
+                rlp_list: RLPListWitness {
+                    is_short,
+                    is_long,
+                    is_very_long,
+                    is_string: !is_list,
+                    bytes: list_rlp_bytes.clone(),
+                },
+                ..Default::default()
+            };
+            assert_eq!(witness.leaf_len(), leaf.len(), "{name}");
+            for is_s in [true, false] {
+                assert_eq!(
+                    eval_leaf_len(storage_config, is_s, &list_rlp_bytes),
+                    Fr::from(leaf.len() as u64),
+                    "{name}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_storage_leaf_len() {
+        let mut meta = ConstraintSystem::<Fr>::default();
+        let params = MPTCircuit::<Fr>::new(vec![], MOCK_DEGREE, MOCK_MAX_NODES).params();
+        let (config, _) = MPTCircuit::<Fr>::configure_with_params(&mut meta, params);
+        let storage_config = &config.state_machine.storage_config;
+
+        let mut num_leaves = 0;
+        for entry in fs::read_dir("src/mpt_circuit/tests").unwrap() {
+            let path = entry.unwrap().path();
+            let nodes = load_proof_from_file(path.to_str().unwrap());
+            let keccak_data: Vec<_> = nodes.iter().flat_map(|node| &node.keccak_data).collect();
+            let is_referenced = |hash: &[u8]| {
+                nodes
+                    .iter()
+                    .flat_map(|node| &node.values)
+                    .any(|value| value.windows(hash.len()).any(|bytes| bytes == hash))
+            };
+
+            for node in nodes.iter() {
+                let Some(storage) = &node.storage else {
+                    continue;
+                };
+                for is_s in [true, false] {
+                    let list_rlp_bytes = &storage.list_rlp_bytes[is_s.idx()];
+                    let (_, is_short, is_long, is_very_long) = decode_rlp(list_rlp_bytes[0]);
+
+                    // The leaf is the node starting with the list RLP bytes and the key whose
+                    // hash is in the proof
+                    let key_row = if is_s {
+                        StorageRowType::KeyS
+                    } else {
+                        StorageRowType::KeyC
+                    };
+                    let key_item = &node.values[key_row as usize];
+                    let key_len = if key_item[0] < 0x80 {
+                        1
+                    } else {
+                        1 + (key_item[0] - 0x80) as usize
+                    };
+                    let num_rlp_bytes = [is_short, is_long, is_very_long]
+                        .iter()
+                        .position(|is| *is)
+                        .map_or(0, |idx| idx + 1);
+                    let prefix = [&list_rlp_bytes[..num_rlp_bytes], &key_item[..key_len]].concat();
+                    let Some(leaf) = keccak_data.iter().find(|data| {
+                        data.starts_with(&prefix) && is_referenced(&keccak256(data.as_slice()))
+                    }) else {
+                        continue;
+                    };
+
+                    // Evaluate the circuit length with the cells assigned like in `assign`
+                    let len = eval_leaf_len(storage_config, is_s, list_rlp_bytes);
+
+                    assert_eq!(len, Fr::from(leaf.len() as u64), "{:?}", path);
+                    num_leaves += 1;
+                }
+            }
+        }
+        assert!(num_leaves > 0);
+    }
+}
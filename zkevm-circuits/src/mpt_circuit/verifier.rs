@@ -0,0 +1,462 @@
+//! A plain keccak based verifier of MPT proofs, used to cross-check the MPT circuit, and a
+//! verifier of single account proofs running the MPT circuit constraints.
+
+use eth_types::{Address, H256, U256};
+use halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr};
+use thiserror::Error;
+
+use super::{
+    helpers::{is_empty_trie_root, is_referenced},
+    modified_key,
+    param::{ARITY, HASH_WIDTH, RLP_NIL},
+    witness_row::{rlp_list_items, rlp_payload, AccountRowType, Node, StartRowType},
+    MPTCircuit, MOCK_DEGREE, MOCK_MAX_NODES,
+};
+use crate::table::MPTProofType;
+
+/// Errors of the MPT proof verification
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum MptProofError {
+    /// The proof does not start with a start node
+    #[error("MissingStart")]
+    MissingStart,
+    /// The root of the proof is not the expected one
+    #[error("RootMismatch")]
+    RootMismatch,
+    /// A node is not referenced by its parent
+    #[error("InvalidReference(node: {index}, is_s: {is_s})")]
+    InvalidReference {
+        /// Index of the node in the proof
+        index: usize,
+        /// Whether the reference is in the `S` or `C` trie
+        is_s: bool,
+    },
+    /// A node has missing or malformed RLP data
+    #[error("MalformedNode({0})")]
+    MalformedNode(usize),
+    /// Proofs where the extension node nibbles are modified are not supported
+    #[error("Unsupported")]
+    Unsupported,
+    /// The proof is not a proof of a single account
+    #[error("NotAccountProof")]
+    NotAccountProof,
+    /// The account of the proof is not the expected one
+    #[error("AddressMismatch")]
+    AddressMismatch,
+    /// The nibbles of the path and the key of the leaf are not the key of the proof
+    #[error("KeyMismatch")]
+    KeyMismatch,
+    /// The proof proves that the account doesn't exist
+    #[error("AccountDoesNotExist")]
+    AccountDoesNotExist,
+    /// The witness does not satisfy the MPT circuit constraints
+    #[error("CircuitFailure({0})")]
+    CircuitFailure(String),
+}
+
+/// Fields of an account, see [`verify_account_proof`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AccountData {
+    /// Nonce
+    pub nonce: U256,
+    /// Balance
+    pub balance: U256,
+    /// Root of the storage trie
+    pub storage_root: H256,
+    /// Hash of the code
+    pub code_hash: H256,
+}
+
+/// Returns whether `reference` points to no node, i.e. a nil branch child or an empty trie.
+fn is_empty(reference: &[u8]) -> bool {
+    reference.first() == Some(&RLP_NIL)
+        || reference
+            .get(1..HASH_WIDTH + 1)
+            .map_or(false, is_empty_trie_root)
+}
+
+/// Returns the stream `data_idx` of `node` if it is referenced by `parent`.
+fn referenced_data<'a>(
+    node: &'a Node,
+    data_idx: usize,
+    parent: &[u8],
+    index: usize,
+    is_s: bool,
+) -> Result<&'a [u8], MptProofError> {
+    let node_rlp = node
+        .keccak_data
+        .get(data_idx)
+        .ok_or(MptProofError::MalformedNode(index))?;
+    if is_referenced(parent, node_rlp) {
+        Ok(node_rlp.as_slice())
+    } else {
+        Err(MptProofError::InvalidReference { index, is_s })
+    }
+}
+
+/// Verifies the first proof in `nodes` by walking the nodes from the root down to the leaf and
+/// checking that each node is referenced by its parent, both in the trie before (`S`) and after
+/// (`C`) the modification. `expected_root` is the root of the `S` trie. Unless the proof proves
+/// that the account or the storage slot doesn't exist, the nibbles of the path followed by the
+/// nibbles of the leaf also have to be the key of the leaf.
+pub fn verify_mpt_proof(nodes: &[Node], expected_root: [u8; 32]) -> Result<(), MptProofError> {
+    let (start, nodes) = nodes.split_first().ok_or(MptProofError::MissingStart)?;
+    let proof_type = start
+        .start
+        .as_ref()
+        .ok_or(MptProofError::MissingStart)?
+        .proof_type;
+    let nodes = &nodes[..nodes
+        .iter()
+        .position(|node| node.start.is_some())
+        .unwrap_or(nodes.len())];
+
+    let is_mod_extension = |node: &Node| {
+        node.extension_branch
+            .as_ref()
+            .map(|node| node.is_mod_extension)
+            .into_iter()
+            .chain(node.account.as_ref().map(|node| node.is_mod_extension))
+            .chain(node.storage.as_ref().map(|node| node.is_mod_extension))
+            .any(|is_mod_extension| is_mod_extension.contains(&true))
+    };
+    if nodes.iter().any(is_mod_extension) {
+        return Err(MptProofError::Unsupported);
+    }
+
+    let root_s = &start.values[StartRowType::RootS as usize];
+    if root_s.get(1..HASH_WIDTH + 1) != Some(&expected_root[..]) {
+        return Err(MptProofError::RootMismatch);
+    }
+
+    for (side, is_s) in [(0, true), (1, false)] {
+        let mut parent: &[u8] = &start.values[side];
+        for (index, node) in nodes.iter().enumerate() {
+            // Index in the proof, including the start node
+            let index = index + 1;
+            if let Some(extension_branch) = &node.extension_branch {
+                // A placeholder branch only exists in the other trie
+                if extension_branch.is_placeholder[side] {
+                    continue;
+                }
+                // The extension nodes are hashed after the two branches
+                if extension_branch.is_extension {
+                    let extension = referenced_data(node, 2 + side, parent, index, is_s)?;
+                    parent = rlp_list_items(extension)
+                        .and_then(|items| items.get(1).copied())
+                        .ok_or(MptProofError::MalformedNode(index))?;
+                }
+                let branch = referenced_data(node, side, parent, index, is_s)?;
+                parent = rlp_list_items(branch)
+                    .filter(|children| children.len() == ARITY + 1)
+                    .map(|children| children[extension_branch.branch.modified_index])
+                    .ok_or(MptProofError::MalformedNode(index))?;
+            }
+
+            if node.account.is_some() || node.storage.is_some() {
+                // There is no leaf to check when it's added to (removed from) an empty position
+                if !is_empty(parent) {
+                    referenced_data(node, side, parent, index, is_s)?;
+                }
+                // The storage trie continues from the storage root of the account
+                if node.account.is_some() {
+                    let storage_row = if is_s {
+                        AccountRowType::StorageS
+                    } else {
+                        AccountRowType::StorageC
+                    };
+                    parent = node.values[storage_row as usize].as_slice();
+                }
+            }
+        }
+    }
+
+    if !matches!(
+        proof_type,
+        MPTProofType::AccountDoesNotExist | MPTProofType::StorageDoesNotExist
+    ) {
+        // The key of the last leaf, the storage leaf if there's one
+        let leaf_key = nodes.iter().rev().find_map(|node| {
+            node.storage
+                .as_ref()
+                .map(|storage| storage.key.to_vec())
+                .or_else(|| node.account.as_ref().map(|account| account.key.to_vec()))
+        });
+        if leaf_key.is_none() || modified_key(nodes) != leaf_key {
+            return Err(MptProofError::KeyMismatch);
+        }
+    }
+
+    Ok(())
+}
+
+/// Verifies the first proof in `nodes`, a proof of the account `address` in the trie of root
+/// `root`, and returns the fields of the account before the modification (`S`). The fields of an
+/// account created by the proof are the ones of an empty account.
+///
+/// The proof is first checked with [`verify_mpt_proof`], then proven with the `MockProver` on
+/// an MPT circuit without the storage leaf constraints. This covers the constraints of the start,
+/// branch, extension and account leaf nodes: the root, the references and hashes of the nodes,
+/// the RLP encoding of the nodes, the account key, the placeholder and drifted leaves and the
+/// lookups of the `S` and `C` account fields into the MPT table.
+/// It doesn't cover the constraints of the storage trie, the storage root is only returned, nor
+/// the checks the MPT table consumers do, e.g. that the `C` fields are the expected new ones.
+/// Proving a real proof, with the challenge randomness and the preimage checks, is left to the
+/// prover: the `MockProver` only evaluates the constraints.
+pub fn verify_account_proof(
+    root: [u8; 32],
+    address: Address,
+    nodes: &[Node],
+) -> Result<AccountData, MptProofError> {
+    let (start, rest) = nodes.split_first().ok_or(MptProofError::MissingStart)?;
+    let proof_type = start
+        .start
+        .as_ref()
+        .ok_or(MptProofError::MissingStart)?
+        .proof_type;
+    // The proof, with the node closing it
+    let end = rest
+        .iter()
+        .position(|node| node.start.is_some())
+        .map_or(nodes.len(), |idx| idx + 2);
+    let proof = &nodes[..end];
+
+    if proof.iter().any(|node| node.storage.is_some()) {
+        return Err(MptProofError::NotAccountProof);
+    }
+    let account_idx = proof
+        .iter()
+        .position(|node| node.account.is_some())
+        .ok_or(MptProofError::NotAccountProof)?;
+    let account = &proof[account_idx];
+    if account.account.as_ref().unwrap().address.as_slice() != address.as_bytes() {
+        return Err(MptProofError::AddressMismatch);
+    }
+    if proof_type == MPTProofType::AccountDoesNotExist {
+        return Err(MptProofError::AccountDoesNotExist);
+    }
+
+    verify_mpt_proof(proof, root)?;
+
+    let num_rows: usize = proof.iter().map(|node| node.values.len()).sum();
+    let circuit = MPTCircuit::<Fr> {
+        disable_preimage_check: false,
+        disable_storage: true,
+        ..MPTCircuit::new(proof.to_vec(), MOCK_DEGREE, MOCK_MAX_NODES)
+    };
+    let prover = MockProver::<Fr>::run(MOCK_DEGREE as u32, &circuit, vec![])
+        .map_err(|err| MptProofError::CircuitFailure(err.to_string()))?;
+    prover
+        .verify_at_rows(0..num_rows, 0..num_rows)
+        .map_err(|failures| MptProofError::CircuitFailure(failures[0].to_string()))?;
+
+    let field = |row: AccountRowType, max_len: usize| {
+        let bytes = &account.values[row as usize];
+        rlp_payload(bytes)
+            .filter(|(_, len)| *len <= max_len)
+            .map(|(offset, len)| &bytes[offset..offset + len])
+            .ok_or(MptProofError::MalformedNode(account_idx))
+    };
+    let hash = |row: AccountRowType| {
+        let bytes = field(row, HASH_WIDTH)?;
+        if bytes.len() == HASH_WIDTH {
+            Ok(H256::from_slice(bytes))
+        } else {
+            Err(MptProofError::MalformedNode(account_idx))
+        }
+    };
+    Ok(AccountData {
+        nonce: U256::from_big_endian(field(AccountRowType::NonceS, 32)?),
+        balance: U256::from_big_endian(field(AccountRowType::BalanceS, 32)?),
+        storage_root: hash(AccountRowType::StorageS)?,
+        code_hash: hash(AccountRowType::CodehashS)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mpt_circuit::{load_proof_from_file, param::EMPTY_CODE_HASH};
+    use eth_types::keccak256;
+    use std::fs;
+
+    fn root_s(nodes: &[Node]) -> [u8; 32] {
+        nodes[0].values[StartRowType::RootS as usize][1..HASH_WIDTH + 1]
+            .try_into()
+            .unwrap()
+    }
+
+    fn account_address(nodes: &[Node]) -> Address {
+        let account = nodes.iter().find_map(|node| node.account.as_ref()).unwrap();
+        Address::from_slice(&account.address)
+    }
+
+    fn circuit_verifies(nodes: Vec<Node>) -> bool {
+        let num_rows: usize = nodes.iter().map(|node| node.values.len()).sum();
+        let circuit = MPTCircuit::<Fr>::new(nodes, MOCK_DEGREE, MOCK_MAX_NODES);
+        let prover = MockProver::<Fr>::run(MOCK_DEGREE as u32, &circuit, vec![]).unwrap();
+        prover.verify_at_rows(0..num_rows, 0..num_rows).is_ok()
+    }
+
+    #[test]
+    fn verify_mpt_proofs() {
+        for entry in fs::read_dir("src/mpt_circuit/tests").unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().map_or(true, |e| e != "json") {
+                continue;
+            }
+            let nodes = load_proof_from_file(path.to_str().unwrap());
+            let result = verify_mpt_proof(&nodes, root_s(&nodes));
+            assert!(
+                result.is_ok() || result == Err(MptProofError::Unsupported),
+                "{path:?}: {result:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn verify_mpt_proof_agrees_with_circuit() {
+        let nodes = load_proof_from_file("src/mpt_circuit/tests/UpdateOneLevel.json");
+        let root = root_s(&nodes);
+        assert_eq!(verify_mpt_proof(&nodes, root), Ok(()));
+        assert!(circuit_verifies(nodes.clone()));
+
+        let mut wrong_root = root;
+        wrong_root[0] ^= 1;
+        assert_eq!(
+            verify_mpt_proof(&nodes, wrong_root),
+            Err(MptProofError::RootMismatch)
+        );
+
+        // Tamper with the value of the `S` storage leaf
+        let mut tampered = nodes;
+        let storage_idx = tampered.iter().position(|n| n.storage.is_some()).unwrap();
+        let mut leaf_s = tampered[storage_idx].keccak_data[0].to_vec();
+        *leaf_s.last_mut().unwrap() ^= 1;
+        tampered[storage_idx].keccak_data[0] = leaf_s.into();
+
+        assert_eq!(
+            verify_mpt_proof(&tampered, root),
+            Err(MptProofError::InvalidReference {
+                index: storage_idx,
+                is_s: true
+            })
+        );
+        assert!(!circuit_verifies(tampered));
+    }
+
+    #[test]
+    fn verify_mpt_proof_rejects_wrong_key() {
+        for (file, is_storage) in [
+            ("UpdateOneLevel.json", true),
+            ("BalanceModCShort.json", false),
+        ] {
+            let mut nodes = load_proof_from_file(&format!("src/mpt_circuit/tests/{file}"));
+            let root = root_s(&nodes);
+            assert_eq!(verify_mpt_proof(&nodes, root), Ok(()));
+
+            // Tamper with the key of the leaf of the first proof
+            let key = nodes
+                .iter_mut()
+                .find_map(|node| match (is_storage, node) {
+                    (
+                        true,
+                        Node {
+                            storage: Some(storage),
+                            ..
+                        },
+                    ) => Some(&mut storage.key),
+                    (
+                        false,
+                        Node {
+                            account: Some(account),
+                            ..
+                        },
+                    ) => Some(&mut account.key),
+                    _ => None,
+                })
+                .unwrap();
+            let mut wrong_key = key.to_vec();
+            wrong_key[0] ^= 1;
+            *key = wrong_key.into();
+            assert_eq!(
+                verify_mpt_proof(&nodes, root),
+                Err(MptProofError::KeyMismatch),
+                "{file}"
+            );
+        }
+    }
+
+    #[test]
+    fn verify_account_proof_returns_account() {
+        let nodes = load_proof_from_file("src/mpt_circuit/tests/BalanceModCShort.json");
+        let root = root_s(&nodes);
+        let address = account_address(&nodes);
+        let empty_trie_hash = H256::from_slice(&keccak256([RLP_NIL]));
+        assert_eq!(
+            verify_account_proof(root, address, &nodes),
+            Ok(AccountData {
+                nonce: 7.into(),
+                balance: 0x1c056bc976783b_u64.into(),
+                storage_root: empty_trie_hash,
+                code_hash: H256::from(EMPTY_CODE_HASH),
+            })
+        );
+
+        // An account created by the proof is empty before the modification
+        let nodes = load_proof_from_file("src/mpt_circuit/tests/AddAccount.json");
+        assert_eq!(
+            verify_account_proof(root_s(&nodes), account_address(&nodes), &nodes),
+            Ok(AccountData {
+                storage_root: empty_trie_hash,
+                code_hash: H256::from(EMPTY_CODE_HASH),
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn verify_account_proof_rejects_invalid_proofs() {
+        let nodes = load_proof_from_file("src/mpt_circuit/tests/BalanceModCShort.json");
+        let root = root_s(&nodes);
+        let account_idx = nodes
+            .iter()
+            .position(|node| node.account.is_some())
+            .unwrap();
+        let address = account_address(&nodes);
+
+        let mut wrong_root = root;
+        wrong_root[0] ^= 1;
+        assert_eq!(
+            verify_account_proof(wrong_root, address, &nodes),
+            Err(MptProofError::RootMismatch)
+        );
+        assert_eq!(
+            verify_account_proof(root, Address::zero(), &nodes),
+            Err(MptProofError::AddressMismatch)
+        );
+
+        // The balance doesn't match the hashed account leaf, which only the circuit checks
+        let mut tampered = nodes;
+        let mut balance = tampered[account_idx].values[AccountRowType::BalanceS as usize].to_vec();
+        balance[1] ^= 1;
+        tampered[account_idx].values[AccountRowType::BalanceS as usize] = balance.into();
+        assert_eq!(verify_mpt_proof(&tampered, root), Ok(()));
+        assert!(matches!(
+            verify_account_proof(root, address, &tampered),
+            Err(MptProofError::CircuitFailure(_))
+        ));
+
+        let nodes = load_proof_from_file("src/mpt_circuit/tests/UpdateOneLevel.json");
+        assert_eq!(
+            verify_account_proof(root_s(&nodes), address, &nodes),
+            Err(MptProofError::NotAccountProof)
+        );
+
+        let nodes = load_proof_from_file("src/mpt_circuit/tests/NonExistingAccount.json");
+        assert_eq!(
+            verify_account_proof(root_s(&nodes), account_address(&nodes), &nodes),
+            Err(MptProofError::AccountDoesNotExist)
+        );
+    }
+}
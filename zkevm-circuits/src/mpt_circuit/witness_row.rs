@@ -4,7 +4,13 @@ use crate::table::MPTProofType;
 
 use serde::{Deserialize, Serialize};
 
-use super::RlpItemType;
+use super::{
+    helpers::{is_referenced, rlp_decode_len},
+    param::{
+        ARITY, KEY_LEN, KEY_LEN_IN_NIBBLES, KEY_PREFIX_ODD, RLP_LIST_SHORT, RLP_LONG, RLP_SHORT,
+    },
+    RlpItemType,
+};
 
 #[derive(Debug, Eq, PartialEq)]
 pub(crate) enum StorageRowType {
@@ -220,6 +226,234 @@ pub struct Node {
     pub keccak_data: Vec<Hex>,
 }
 
+impl Node {
+    /// Returns whether the node with RLP encoding `node_rlp` is the child at `modified_index` of
+    /// this branch. `is_s` selects the branch before (`S`) or after (`C`) the modification.
+    /// Returns `false` if this is not a branch node.
+    pub fn is_modified_child(&self, node_rlp: &[u8], is_s: bool) -> bool {
+        let Some(extension_branch) = &self.extension_branch else {
+            return false;
+        };
+        // All `S` children are stored in the branch rows, the modified `C` child is stored in the
+        // first row.
+        let child = if is_s {
+            &self.values
+                [ExtensionBranchRowType::Child0 as usize + extension_branch.branch.modified_index]
+        } else {
+            &self.values[ExtensionBranchRowType::Mod as usize]
+        };
+        is_referenced(child, node_rlp)
+    }
+
+    /// Returns whether this branch is a placeholder: when a leaf is turned into a branch, the
+    /// `S` proof has a placeholder in place of the new branch of the `C` proof, and conversely
+    /// when a branch is turned into a leaf. `is_s` selects the branch before (`S`) or after (`C`)
+    /// the modification. Returns `false` if this is not a branch node.
+    pub fn is_placeholder_branch(&self, is_s: bool) -> bool {
+        self.extension_branch
+            .as_ref()
+            .map_or(false, |extension_branch| {
+                extension_branch.is_placeholder[if is_s { 0 } else { 1 }]
+            })
+    }
+
+    /// Returns whether the `S` and `C` branches have the same children at all positions except
+    /// the modified one. Returns `true` if this is not a branch node.
+    pub fn has_consistent_branch_children(&self) -> bool {
+        let Some(extension_branch) = &self.extension_branch else {
+            return true;
+        };
+        // The first two streams to be hashed are the `S` and `C` branches
+        let branches = [0, 1].map(|idx| {
+            self.keccak_data
+                .get(idx)
+                .and_then(|rlp| rlp_list_items(rlp))
+                .filter(|children| children.len() == ARITY + 1)
+        });
+        let [Some(children_s), Some(children_c)] = branches else {
+            return false;
+        };
+        (0..=ARITY).all(|idx| {
+            idx == extension_branch.branch.modified_index || children_s[idx] == children_c[idx]
+        })
+    }
+
+    /// Returns the RLP of the storage leaf assembled from its key and value rows: the list
+    /// header, the key and the value, which is stored directly in `value_rlp_bytes` when it is a
+    /// single byte and in the value row otherwise. `is_s` selects the leaf before (`S`) or after
+    /// (`C`) the modification. Returns `None` if this is not a storage leaf or if the leaf is a
+    /// placeholder.
+    pub fn leaf_rlp_bytes(&self, is_s: bool) -> Option<Vec<u8>> {
+        let storage = self.storage.as_ref()?;
+        let idx = if is_s { 0 } else { 1 };
+        let (key_row, value_row) = if is_s {
+            (StorageRowType::KeyS, StorageRowType::ValueS)
+        } else {
+            (StorageRowType::KeyC, StorageRowType::ValueC)
+        };
+        let row_item = |row: StorageRowType| -> Option<&[u8]> {
+            let row = self.values.get(row as usize)?;
+            let (offset, len) = rlp_payload(row)?;
+            Some(&row[..offset + len])
+        };
+
+        // Storage values are never zero, placeholder leaves have no value
+        let value_rlp_bytes: &[u8] = &storage.value_rlp_bytes[idx];
+        if value_rlp_bytes == [0] {
+            return None;
+        }
+        let mut rlp = storage.list_rlp_bytes[idx].to_vec();
+        rlp.extend_from_slice(row_item(key_row)?);
+        rlp.extend_from_slice(value_rlp_bytes);
+        if value_rlp_bytes[0] > RLP_SHORT {
+            rlp.extend_from_slice(row_item(value_row)?);
+        }
+        Some(rlp)
+    }
+}
+
+/// Returns the key of the leaf at the end of the trie path `nodes`, formed by the nibbles of
+/// the extension nodes and branches leading to the leaf followed by the nibbles stored in the
+/// leaf. `is_s` selects the path before (`S`) or after (`C`) the modification, the placeholder
+/// branches of that side are skipped. In `AccountDoesNotExist` and `StorageDoesNotExist`
+/// proofs the `S` key is the key of the wrong leaf.
+///
+/// For storage proofs `nodes` needs to start after the account leaf. Returns `None` if there is
+/// no leaf, if the extension node nibbles are modified or if the nibbles don't form a 64-nibble
+/// key.
+pub fn leaf_key(nodes: &[Node], is_s: bool) -> Option<[u8; KEY_LEN]> {
+    let nibbles = leaf_key_nibbles(nodes, is_s)?;
+    (nibbles.len() == KEY_LEN_IN_NIBBLES).then(|| nibbles_to_key(&nibbles))
+}
+
+/// Returns the number of nibbles of the key of the leaf at the end of the trie path `nodes`,
+/// which the circuit requires to be `KEY_LEN_IN_NIBBLES`. The nibbles are counted like in
+/// [`leaf_key`], so this can be used to find the witness of a key that is too short or too
+/// long. The leaf key is counted in all its encodings: a hex-prefix string (short key), a
+/// single byte holding one nibble (one nibble key) or no nibble (leaf in the last level).
+/// Returns `None` if there is no leaf or if the extension node nibbles are modified.
+pub fn leaf_key_num_nibbles(nodes: &[Node], is_s: bool) -> Option<usize> {
+    leaf_key_nibbles(nodes, is_s).map(|nibbles| nibbles.len())
+}
+
+/// Returns the nibbles of the path to the leaf at the end of `nodes` followed by the nibbles
+/// stored in the leaf, see [`leaf_key`].
+fn leaf_key_nibbles(nodes: &[Node], is_s: bool) -> Option<Vec<u8>> {
+    let side = if is_s { 0 } else { 1 };
+    let mut nibbles = vec![];
+    for node in nodes {
+        if node.start.is_some() {
+            nibbles.clear();
+        } else if let Some(extension_branch) = &node.extension_branch {
+            if extension_branch.is_placeholder[side] {
+                continue;
+            }
+            if extension_branch.is_extension {
+                nibbles.extend(key_row_nibbles(
+                    &node.values[ExtensionBranchRowType::KeyS as usize],
+                )?);
+            }
+            nibbles.push(extension_branch.branch.modified_index as u8);
+        } else {
+            let (is_mod_extension, key_row) = match (&node.account, &node.storage) {
+                (Some(account), _) => (
+                    account.is_mod_extension,
+                    if is_s {
+                        AccountRowType::KeyS as usize
+                    } else {
+                        AccountRowType::KeyC as usize
+                    },
+                ),
+                (_, Some(storage)) => (
+                    storage.is_mod_extension,
+                    if is_s {
+                        StorageRowType::KeyS as usize
+                    } else {
+                        StorageRowType::KeyC as usize
+                    },
+                ),
+                _ => continue,
+            };
+            if is_mod_extension.iter().any(|is_mod| *is_mod) {
+                return None;
+            }
+            nibbles.extend(key_row_nibbles(&node.values[key_row])?);
+            return Some(nibbles);
+        }
+    }
+    None
+}
+
+/// Packs the 64 nibbles of a key into its 32 bytes.
+pub fn nibbles_to_key(nibbles: &[u8]) -> [u8; KEY_LEN] {
+    assert_eq!(
+        nibbles.len(),
+        KEY_LEN_IN_NIBBLES,
+        "a key has {KEY_LEN_IN_NIBBLES} nibbles"
+    );
+    let mut key = [0u8; KEY_LEN];
+    for (byte, pair) in key.iter_mut().zip(nibbles.chunks(2)) {
+        *byte = pair[0] * 16 + pair[1];
+    }
+    key
+}
+
+/// Returns the nibbles of the hex-prefix encoded path stored in the key row `row` of a leaf or
+/// an extension node.
+pub(crate) fn key_row_nibbles(row: &[u8]) -> Option<Vec<u8>> {
+    let (offset, len) = rlp_payload(row)?;
+    let (first, rest) = row[offset..offset + len].split_first()?;
+    let mut nibbles = vec![];
+    // Odd number of nibbles, the first one is stored in the flag byte
+    if first & KEY_PREFIX_ODD != 0 {
+        nibbles.push(first & 0x0f);
+    }
+    for byte in rest {
+        nibbles.extend([byte >> 4, byte & 0x0f]);
+    }
+    Some(nibbles)
+}
+
+/// Returns the offset and the length of the payload of the RLP item at the start of `bytes`.
+pub(crate) fn rlp_payload(bytes: &[u8]) -> Option<(usize, usize)> {
+    let prefix = *bytes.first()?;
+    // The header of a list is the header of a string with the same length
+    let string_prefix = if prefix < RLP_LIST_SHORT {
+        prefix
+    } else {
+        prefix - (RLP_LIST_SHORT - RLP_SHORT)
+    };
+    let (offset, len) = match rlp_decode_len(string_prefix, bytes.get(1).copied().unwrap_or(0)) {
+        Some((_, len, header_len)) => (header_len, len),
+        // The length takes more than one byte
+        None => {
+            let num_bytes = (string_prefix - RLP_LONG) as usize;
+            let len = bytes
+                .get(1..1 + num_bytes)?
+                .iter()
+                .fold(0usize, |len, byte| len * 256 + *byte as usize);
+            (1 + num_bytes, len)
+        }
+    };
+    (offset + len <= bytes.len()).then_some((offset, len))
+}
+
+/// Returns the RLP encoded items of the RLP list `bytes`.
+pub(crate) fn rlp_list_items(bytes: &[u8]) -> Option<Vec<&[u8]>> {
+    if *bytes.first()? < RLP_LIST_SHORT {
+        return None;
+    }
+    let (offset, len) = rlp_payload(bytes)?;
+    let mut payload = &bytes[offset..offset + len];
+    let mut items = vec![];
+    while !payload.is_empty() {
+        let (offset, len) = rlp_payload(payload)?;
+        items.push(&payload[..offset + len]);
+        payload = &payload[offset + len..];
+    }
+    Some(items)
+}
+
 /// RLP types start
 pub const NODE_RLP_TYPES_START: [RlpItemType; StartRowType::Count as usize] =
     [RlpItemType::Hash, RlpItemType::Hash];
@@ -290,3 +524,169 @@ pub const NODE_RLP_TYPES_STORAGE: [RlpItemType; StorageRowType::Count as usize]
     RlpItemType::Hash,
     RlpItemType::Hash,
 ];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mpt_circuit::{
+        load_proof, load_proof_from_file,
+        param::{RLP_HASH_VALUE, RLP_NIL},
+    };
+    use eth_types::keccak256;
+
+    #[test]
+    fn test_is_modified_child() {
+        let nodes = load_proof_from_file("src/mpt_circuit/tests/UpdateOneLevel.json");
+        let storage_idx = nodes.iter().position(|n| n.storage.is_some()).unwrap();
+        let leaf = &nodes[storage_idx];
+        let branch = &nodes[storage_idx - 1];
+
+        // The leaf before and after the modification is at the modified index
+        assert!(branch.is_modified_child(&leaf.keccak_data[0], true));
+        assert!(branch.is_modified_child(&leaf.keccak_data[1], false));
+        assert!(!branch.is_modified_child(&leaf.keccak_data[0], false));
+
+        // The leaf is not a sibling at another (non-nil) position
+        let mut sibling = branch.clone();
+        let modified_index = branch
+            .extension_branch
+            .as_ref()
+            .unwrap()
+            .branch
+            .modified_index;
+        let sibling_index = (0..16)
+            .find(|i| *i != modified_index && branch.values[1 + i][0] == RLP_HASH_VALUE)
+            .unwrap();
+        sibling
+            .extension_branch
+            .as_mut()
+            .unwrap()
+            .branch
+            .modified_index = sibling_index;
+        assert!(!sibling.is_modified_child(&leaf.keccak_data[0], true));
+
+        // Leaves are never branches
+        assert!(!leaf.is_modified_child(&leaf.keccak_data[0], true));
+    }
+
+    #[test]
+    fn test_has_consistent_branch_children() {
+        let nodes = load_proof_from_file("src/mpt_circuit/tests/UpdateOneLevel.json");
+        assert!(nodes
+            .iter()
+            .all(|node| node.has_consistent_branch_children()));
+
+        // The branches differ at the modified index
+        let storage_idx = nodes.iter().position(|n| n.storage.is_some()).unwrap();
+        let branch = &nodes[storage_idx - 1];
+        assert_ne!(branch.keccak_data[0].deref(), branch.keccak_data[1].deref());
+
+        // Set the value of the `C` branch, which is never the modified child
+        let mut rlp_c = branch.keccak_data[1].to_vec();
+        assert_eq!(rlp_c.last(), Some(&RLP_NIL));
+        *rlp_c.last_mut().unwrap() = 1;
+        let mut tampered = branch.clone();
+        tampered.keccak_data[1] = rlp_c.into();
+        assert!(!tampered.has_consistent_branch_children());
+
+        // Malformed RLP
+        let mut tampered = branch.clone();
+        tampered.keccak_data[1] = branch.keccak_data[1][..10].to_vec().into();
+        assert!(!tampered.has_consistent_branch_children());
+
+        let mut nodes = nodes.clone();
+        nodes[storage_idx - 1] = tampered;
+        let json = serde_json::to_vec(&nodes).unwrap();
+        assert!(load_proof(json.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_leaf_rlp_bytes() {
+        let nodes = load_proof_from_file("src/mpt_circuit/tests/UpdateOneLevelBigVal.json");
+        let leaf = nodes.iter().find(|n| n.storage.is_some()).unwrap();
+        // Short value before, long value after the modification
+        for (idx, is_s) in [(0, true), (1, false)] {
+            let rlp = leaf.leaf_rlp_bytes(is_s).unwrap();
+            assert_eq!(keccak256(&rlp), keccak256(leaf.keccak_data[idx].deref()));
+        }
+        // Not a storage leaf
+        assert_eq!(nodes[0].leaf_rlp_bytes(true), None);
+
+        // The leaf does not exist before the modification
+        let nodes = load_proof_from_file("src/mpt_circuit/tests/FromNilToValue.json");
+        let leaf = nodes.iter().find(|n| n.storage.is_some()).unwrap();
+        assert_eq!(leaf.leaf_rlp_bytes(true), None);
+        assert_eq!(
+            leaf.leaf_rlp_bytes(false).as_deref(),
+            Some(leaf.keccak_data[1].as_slice())
+        );
+    }
+
+    #[test]
+    fn test_leaf_key() {
+        let nodes = load_proof_from_file("src/mpt_circuit/tests/BalanceModCShort.json");
+        let account = nodes.iter().find_map(|node| node.account.as_ref()).unwrap();
+        for is_s in [true, false] {
+            assert_eq!(
+                leaf_key(&nodes, is_s).unwrap().to_vec(),
+                account.key.to_vec()
+            );
+        }
+
+        // A path missing a branch has 63 nibbles
+        let truncated = [&nodes[..1], &nodes[2..]].concat();
+        assert_eq!(leaf_key(&truncated, true), None);
+
+        // Storage proofs start after the account leaf
+        let nodes = load_proof_from_file("src/mpt_circuit/tests/UpdateOneLevel.json");
+        let account_idx = nodes.iter().position(|n| n.account.is_some()).unwrap();
+        let storage = nodes.iter().find_map(|node| node.storage.as_ref()).unwrap();
+        for is_s in [true, false] {
+            assert_eq!(
+                leaf_key(&nodes[account_idx + 1..], is_s).unwrap().to_vec(),
+                storage.key.to_vec()
+            );
+        }
+
+        // The wrong leaf has a valid key sharing the path with the key that does not exist
+        let nodes = load_proof_from_file("src/mpt_circuit/tests/NonExistingAccount.json");
+        let account = nodes.iter().find_map(|node| node.account.as_ref()).unwrap();
+        let wrong_key = leaf_key(&nodes, true).unwrap();
+        assert_ne!(wrong_key.to_vec(), account.key.to_vec());
+        assert_eq!(wrong_key[..3], account.key[..3]);
+    }
+
+    #[test]
+    fn test_leaf_key_num_nibbles() {
+        // Full-length key
+        let nodes = load_proof_from_file("src/mpt_circuit/tests/BalanceModCShort.json");
+        for is_s in [true, false] {
+            assert_eq!(leaf_key_num_nibbles(&nodes, is_s), Some(KEY_LEN_IN_NIBBLES));
+        }
+        // A path missing a branch is one nibble short
+        let truncated = [&nodes[..1], &nodes[2..]].concat();
+        assert_eq!(
+            leaf_key_num_nibbles(&truncated, true),
+            Some(KEY_LEN_IN_NIBBLES - 1)
+        );
+
+        // The leaf key is a single byte holding one nibble, or no nibble in the last level
+        for (file, leaf_nibbles) in [("LeafWithOneNibble.json", 1), ("LeafInLastLevel.json", 0)] {
+            let nodes = load_proof_from_file(&format!("src/mpt_circuit/tests/{}", file));
+            let leaf = nodes.iter().find(|node| node.storage.is_some()).unwrap();
+            assert_eq!(
+                key_row_nibbles(&leaf.values[StorageRowType::KeyS as usize])
+                    .unwrap()
+                    .len(),
+                leaf_nibbles
+            );
+            let account_idx = nodes.iter().position(|n| n.account.is_some()).unwrap();
+            for is_s in [true, false] {
+                assert_eq!(
+                    leaf_key_num_nibbles(&nodes[account_idx + 1..], is_s),
+                    Some(KEY_LEN_IN_NIBBLES)
+                );
+            }
+        }
+    }
+}
@@ -26,9 +26,29 @@ pub enum MPTProofType {
     StorageChanged,
     /// Storage does not exist
     StorageDoesNotExist,
+    /// Code hash of the account is the empty code hash (the account is an EOA), the trie is not
+    /// modified
+    CodeHashIsEmpty,
+    /// Code hash of the account as read by EXTCODEHASH (0 for an empty account), the trie is not
+    /// modified
+    CodeHashRead,
 }
 impl_expr!(MPTProofType);
 
+impl MPTProofType {
+    /// Proof type of a storage slot update from `old_value` to `new_value`. A slot that has never
+    /// been written has no leaf in the trie and holds the default zero value, this is proven with
+    /// a non-existence proof. Explicitly setting a slot to zero deletes its leaf, which is a
+    /// change.
+    pub fn storage(old_value: U256, new_value: U256) -> Self {
+        if old_value.is_zero() && new_value.is_zero() {
+            Self::StorageDoesNotExist
+        } else {
+            Self::StorageChanged
+        }
+    }
+}
+
 impl From<AccountFieldTag> for MPTProofType {
     fn from(tag: AccountFieldTag) -> Self {
         match tag {
@@ -1,7 +1,10 @@
 //! Testing utilities
 
 use crate::{
-    evm_circuit::{cached::EvmCircuitCached, EvmCircuit},
+    evm_circuit::{
+        cached::EvmCircuitCached, param::EXECUTION_STATE_HEIGHT_MAP, step::ExecutionState,
+        EvmCircuit,
+    },
     state_circuit::StateCircuit,
     util::SubCircuit,
     witness::{Block, Rw},
@@ -10,9 +13,10 @@ use bus_mapping::{
     circuit_input_builder::{FeatureConfig, FixedCParams},
     mock::BlockData,
 };
-use eth_types::geth_types::GethData;
+use eth_types::{geth_types::GethData, Bytecode};
 use itertools::all;
 use std::cmp;
+use strum::IntoEnumIterator;
 use thiserror::Error;
 
 use crate::util::log2_ceil;
@@ -87,6 +91,7 @@ pub struct CircuitTestBuilder<const NACC: usize, const NTX: usize> {
     feature_config: Option<FeatureConfig>,
     block: Option<Block<Fr>>,
     block_modifiers: Vec<Box<dyn Fn(&mut Block<Fr>)>>,
+    extra_fixed_entries: Vec<[Fr; 4]>,
 }
 
 impl<const NACC: usize, const NTX: usize> CircuitTestBuilder<NACC, NTX> {
@@ -98,6 +103,7 @@ impl<const NACC: usize, const NTX: usize> CircuitTestBuilder<NACC, NTX> {
             feature_config: None,
             block: None,
             block_modifiers: vec![],
+            extra_fixed_entries: vec![],
         }
     }
 
@@ -154,6 +160,16 @@ impl<const NACC: usize, const NTX: usize> CircuitTestBuilder<NACC, NTX> {
         self.block_modifiers.push(modifier);
         self
     }
+
+    /// Allows to register additional rows of the EVM circuit fixed table, `[tag, value1, value2,
+    /// value3]`, loaded after the rows of the existing tags.
+    ///
+    /// That allows to test a new gadget with its fixed table lookup without modifying the
+    /// loading of the fixed table.
+    pub fn with_extra_fixed_entries(mut self, entries: Vec<[Fr; 4]>) -> Self {
+        self.extra_fixed_entries.extend(entries);
+        self
+    }
 }
 
 impl<const NACC: usize, const NTX: usize> CircuitTestBuilder<NACC, NTX> {
@@ -190,11 +206,14 @@ impl<const NACC: usize, const NTX: usize> CircuitTestBuilder<NACC, NTX> {
 
         // Mainnet EVM circuit constraints can be cached for test performance.
         // No cache for EVM circuit with customized features
+        let extra_fixed_entries = self.extra_fixed_entries.clone();
         let prover = if block.feature_config.is_mainnet() {
-            let circuit = EvmCircuitCached::get_test_circuit_from_block(block);
+            let circuit = EvmCircuitCached::get_test_circuit_from_block(block)
+                .with_extra_fixed_entries(extra_fixed_entries);
             MockProver::<Fr>::run(k, &circuit, vec![])
         } else {
-            let circuit = EvmCircuit::get_test_circuit_from_block(block);
+            let circuit = EvmCircuit::get_test_circuit_from_block(block)
+                .with_extra_fixed_entries(extra_fixed_entries);
             MockProver::<Fr>::run(k, &circuit, vec![])
         };
 
@@ -255,6 +274,165 @@ impl<const NACC: usize, const NTX: usize> CircuitTestBuilder<NACC, NTX> {
     pub fn run(self) {
         self.run_with_result().unwrap()
     }
+
+    /// Runs the test of a witness that is expected to be rejected: the circuits must fail
+    /// verification with at least one failure matching `predicate`, see
+    /// [`constraint_failure_in_gate`].
+    pub fn expect_failure(self, predicate: impl Fn(&VerifyFailure) -> bool) {
+        match self.run_with_result() {
+            Err(CircuitTestError::VerificationFailed { reasons, .. }) => assert!(
+                reasons.iter().any(predicate),
+                "no verification failure matches the expected one: {reasons:#?}"
+            ),
+            result => panic!("expected a verification failure, got {result:?}"),
+        }
+    }
+}
+
+/// Returns a predicate for [`CircuitTestBuilder::expect_failure`] matching the failures of the
+/// constraints of the gate `gate`, e.g. the gate of an execution gadget named after its
+/// `ExecutionGadget::NAME`.
+pub fn constraint_failure_in_gate(gate: &str) -> impl Fn(&VerifyFailure) -> bool + '_ {
+    move |failure| match failure {
+        VerifyFailure::ConstraintNotSatisfied { constraint, .. } => {
+            constraint.to_string().ends_with(&format!("('{gate}')"))
+        }
+        _ => false,
+    }
+}
+
+/// Build the witness [`Block`] of a block with a single transaction that runs `code`, using
+/// [`TestContext::simple_ctx_with_bytecode`] and the default circuit parameters.
+///
+/// Useful for gadget and sub-circuit tests that only need the witness of some bytecode.
+///
+/// ## Example:
+/// ```rust, no_run
+/// use eth_types::bytecode;
+/// use zkevm_circuits::test_util::block_for_bytecode;
+///
+/// let block = block_for_bytecode(bytecode! {
+///     PUSH32(3)
+///     PUSH32(2)
+///     EXP
+///     STOP
+/// });
+/// assert_eq!(block.exp_events.len(), 1);
+/// ```
+pub fn block_for_bytecode(code: Bytecode) -> Block<Fr> {
+    let ctx = TestContext::<2, 1>::simple_ctx_with_bytecode(code).unwrap();
+    CircuitTestBuilder::new_from_test_ctx(ctx)
+        .build_block()
+        .unwrap()
+}
+
+/// A small gadget test case for [`run_all_gadget_tests`]: `code` must leave the execution
+/// running (no `STOP` or `RETURN`) so that it can be followed by other cases.
+#[derive(Debug, Clone)]
+pub struct TestCase {
+    /// Name reported when the case fails
+    pub name: String,
+    /// Bytecode exercising the gadget
+    pub code: Bytecode,
+}
+
+impl TestCase {
+    /// Create a test case running `code`
+    pub fn new(name: impl Into<String>, code: Bytecode) -> Self {
+        Self {
+            name: name.into(),
+            code,
+        }
+    }
+}
+
+/// Run a batch of gadget test cases, all of which are expected to pass.
+///
+/// The cases are concatenated into the bytecode of a single transaction, so the block is built
+/// and the EVM and State circuits are proven once for the whole batch instead of once per case.
+/// If the batch fails, every case is run on its own to report which ones fail.
+pub fn run_all_gadget_tests(cases: &[TestCase]) {
+    let mut code = Bytecode::default();
+    for case in cases {
+        code.append(&case.code);
+    }
+    code.op_stop();
+
+    let ctx = TestContext::<2, 1>::simple_ctx_with_bytecode(code).unwrap();
+    if CircuitTestBuilder::new_from_test_ctx(ctx)
+        .run_with_result()
+        .is_ok()
+    {
+        return;
+    }
+
+    let failed: Vec<_> = cases
+        .iter()
+        .filter_map(|case| {
+            let mut code = case.code.clone();
+            code.op_stop();
+            let ctx = TestContext::<2, 1>::simple_ctx_with_bytecode(code).unwrap();
+            CircuitTestBuilder::new_from_test_ctx(ctx)
+                .run_with_result()
+                .err()
+                .map(|err| format!("{}: {err}", case.name))
+        })
+        .collect();
+    panic!("gadget test batch failed, failing cases: {failed:#?}");
+}
+
+/// Assert that all the opcodes of `execution_state` have the same constant gas cost, for the
+/// gadgets handling several opcodes that charge the constant gas cost of one of them in their
+/// `gas_left` transition.
+pub fn assert_shared_constant_gas_cost(execution_state: ExecutionState) {
+    let costs: Vec<_> = execution_state
+        .responsible_opcodes()
+        .iter()
+        .map(|op| (op.opcode(), op.opcode().constant_gas_cost()))
+        .collect();
+    assert!(
+        costs.windows(2).all(|pair| pair[0].1 == pair[1].1),
+        "opcodes of {execution_state:?} with different constant gas costs: {costs:?}"
+    );
+}
+
+/// Execution states without a gadget in the EVM circuit: the precompiles that are not
+/// implemented yet, and `ErrorInsufficientBalance`, which is part of the CALL and CREATE gadgets.
+const UNCONFIGURED_EXECUTION_STATES: [ExecutionState; 9] = [
+    ExecutionState::ErrorInsufficientBalance,
+    ExecutionState::PrecompileEcRecover,
+    ExecutionState::PrecompileSha256,
+    ExecutionState::PrecompileRipemd160,
+    ExecutionState::PrecompileBigModExp,
+    ExecutionState::PrecompileBn256Add,
+    ExecutionState::PrecompileBn256ScalarMul,
+    ExecutionState::PrecompileBn256Pairing,
+    ExecutionState::PrecompileBlake2f,
+];
+
+/// Assert that a gadget is configured in the EVM circuit for every [`ExecutionState`], with all
+/// the features enabled, so that an execution state added without its gadget fails a test.
+/// The states of `UNCONFIGURED_EXECUTION_STATES` are expected to have no gadget, configuring a
+/// gadget for one of them fails as well until it is removed from the list.
+pub fn assert_all_execution_states_covered() {
+    let (unconfigured, configured): (Vec<_>, Vec<_>) =
+        ExecutionState::iter().partition(|state| !EXECUTION_STATE_HEIGHT_MAP.contains_key(state));
+    let missing: Vec<_> = unconfigured
+        .into_iter()
+        .filter(|state| !UNCONFIGURED_EXECUTION_STATES.contains(state))
+        .collect();
+    assert!(
+        missing.is_empty(),
+        "execution states without a gadget: {missing:?}"
+    );
+    let newly_configured: Vec<_> = configured
+        .into_iter()
+        .filter(|state| UNCONFIGURED_EXECUTION_STATES.contains(state))
+        .collect();
+    assert!(
+        newly_configured.is_empty(),
+        "execution states with a gadget listed as unconfigured: {newly_configured:?}"
+    );
 }
 
 #[derive(Debug)]
@@ -224,3 +224,24 @@ pub(crate) fn unusable_rows<F: Field, C: Circuit<F>>(params: C::Params) -> usize
 
     cs.blinding_factors() + 1
 }
+
+/// Returns the statistics of the configuration of circuit `C` as a JSON object: the number of
+/// gates, lookups, advice and fixed columns, the max expression degree and the estimated number
+/// of rows `num_rows`.
+#[cfg(feature = "stats")]
+pub fn circuit_stats_json<F: Field, C: Circuit<F>>(
+    params: C::Params,
+    num_rows: usize,
+) -> serde_json::Value {
+    let mut cs = ConstraintSystem::default();
+    C::configure_with_params(&mut cs, params);
+
+    serde_json::json!({
+        "gates": cs.gates().len(),
+        "lookups": cs.lookups().len(),
+        "advice_columns": cs.num_advice_columns(),
+        "fixed_columns": cs.num_fixed_columns(),
+        "degree": cs.degree(),
+        "rows": num_rows,
+    })
+}
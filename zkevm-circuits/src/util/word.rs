@@ -5,7 +5,7 @@
 
 use bus_mapping::state_db::CodeDB;
 use eth_types::{Field, OpsIdentity, ToLittleEndian, H160, H256};
-use gadgets::util::{not, or, Expr};
+use gadgets::util::{not, or, select, Expr};
 use halo2_proofs::{
     circuit::{AssignedCell, Region, Value},
     plonk::{Advice, Column, Error, Expression, VirtualCells},
@@ -13,7 +13,10 @@ use halo2_proofs::{
 };
 use itertools::Itertools;
 
-use crate::evm_circuit::util::{from_bytes, CachedRegion, Cell};
+use crate::evm_circuit::util::{
+    constraint_builder::EVMConstraintBuilder, from_bytes, math_gadget::IsEqualWordGadget,
+    CachedRegion, Cell,
+};
 
 /// evm word 32 bytes, half word 16 bytes
 const N_BYTES_HALF_WORD: usize = 16;
@@ -138,15 +141,37 @@ impl<F: Field, const N: usize> WordLimbs<Cell<F>, N> {
         region: &mut CachedRegion<'_, '_, F>,
         offset: usize,
         word: eth_types::Word,
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        self.assign_le_bytes(region, offset, &word.to_le_bytes())
+    }
+
+    /// assign 32 little-endian bytes to wordlimbs
+    pub fn assign_le_bytes(
+        &self,
+        region: &mut CachedRegion<'_, '_, F>,
+        offset: usize,
+        bytes: &[u8; 32],
     ) -> Result<Vec<AssignedCell<F, F>>, Error> {
         self.assign_lo_hi::<N_BYTES_HALF_WORD, N_BYTES_HALF_WORD>(
             region,
             offset,
-            word.to_le_bytes()[0..N_BYTES_HALF_WORD].try_into().unwrap(),
-            word.to_le_bytes()[N_BYTES_HALF_WORD..].try_into().ok(),
+            bytes[0..N_BYTES_HALF_WORD].try_into().unwrap(),
+            bytes[N_BYTES_HALF_WORD..].try_into().ok(),
         )
     }
 
+    /// assign 32 big-endian bytes, e.g. a hash, to wordlimbs
+    pub fn assign_be_bytes(
+        &self,
+        region: &mut CachedRegion<'_, '_, F>,
+        offset: usize,
+        bytes: &[u8; 32],
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        let mut bytes = *bytes;
+        bytes.reverse();
+        self.assign_le_bytes(region, offset, &bytes)
+    }
+
     /// assign h160 to wordlimbs
     pub fn assign_h160(
         &self,
@@ -327,6 +352,32 @@ impl<F: Field> From<H160> for WordLoHi<F> {
 }
 
 impl<F: Field> WordLoHi<Value<F>> {
+    /// Witness counterpart of [`WordLoHi::select`]: `when_true` when `selector` is 1, `when_false`
+    /// when it is 0. In debug builds, panics when a known selector is not boolean, instead of
+    /// mixing both words like the expression does.
+    pub fn select_checked(selector: Value<F>, when_true: Self, when_false: Self) -> Self {
+        #[cfg(debug_assertions)]
+        selector.map(|selector| {
+            assert!(
+                selector == F::ZERO || selector == F::ONE,
+                "WordLoHi::select_checked: selector {:?} is not boolean",
+                selector
+            )
+        });
+        let select_limb = |when_true: Value<F>, when_false: Value<F>| {
+            selector
+                .zip(when_true)
+                .zip(when_false)
+                .map(|((selector, when_true), when_false)| {
+                    select::value(selector, when_true, when_false)
+                })
+        };
+        WordLoHi::new([
+            select_limb(when_true.lo(), when_false.lo()),
+            select_limb(when_true.hi(), when_false.hi()),
+        ])
+    }
+
     /// Assign advice
     pub fn assign_advice<A, AR>(
         &self,
@@ -418,6 +469,16 @@ impl<F: Field> WordLoHi<Expression<F>> {
     pub fn compress(&self) -> Expression<F> {
         self.lo() + self.hi() * Expression::Constant(F::from_repr(BASE_128_BYTES).unwrap())
     }
+
+    /// Returns the `IsEqualWordGadget` of `self` and `other`, whose `expr()` is 1 when both words
+    /// are equal and 0 otherwise. The gadget needs to be kept around to be assigned.
+    pub(crate) fn is_equal(
+        &self,
+        cb: &mut EVMConstraintBuilder<F>,
+        other: &Self,
+    ) -> IsEqualWordGadget<F, Self, Self> {
+        IsEqualWordGadget::construct(cb, self, other)
+    }
 }
 
 impl<F: Field, const N1: usize> WordLimbs<Expression<F>, N1> {
@@ -464,4 +525,94 @@ pub fn empty_code_hash_word_value<F: Field>() -> WordLoHi<Value<F>> {
     WordLoHi::from(CodeDB::empty_code_hash()).into_value()
 }
 
-// TODO unittest
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm_circuit::util::{
+        constraint_builder::ConstrainBuilderCommon, math_gadget::test_util::*,
+    };
+    use eth_types::{ToBigEndian, Word};
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    #[derive(Clone)]
+    /// WordBytesTestContainer: the bytes of witnesses[1], assigned in big and little endian, have
+    /// the same limbs as witnesses[0] assigned as a u256
+    struct WordBytesTestContainer<F> {
+        word: Word32Cell<F>,
+        be_bytes: Word32Cell<F>,
+        le_bytes: Word32Cell<F>,
+    }
+
+    impl<F: Field> MathGadgetContainer<F> for WordBytesTestContainer<F> {
+        fn configure_gadget_container(cb: &mut EVMConstraintBuilder<F>) -> Self {
+            let word = cb.query_word32();
+            let be_bytes = cb.query_word32();
+            let le_bytes = cb.query_word32();
+            for idx in 0..32 {
+                let limb = word.limbs[idx].expr();
+                cb.require_equal("be_bytes limb", be_bytes.limbs[idx].expr(), limb.expr());
+                cb.require_equal("le_bytes limb", le_bytes.limbs[idx].expr(), limb);
+            }
+
+            WordBytesTestContainer {
+                word,
+                be_bytes,
+                le_bytes,
+            }
+        }
+
+        fn assign_gadget_container(
+            &self,
+            witnesses: &[Word],
+            region: &mut CachedRegion<'_, '_, F>,
+        ) -> Result<(), Error> {
+            let offset = 0;
+            self.word.assign_u256(region, offset, witnesses[0])?;
+            self.be_bytes
+                .assign_be_bytes(region, offset, &witnesses[1].to_be_bytes())?;
+            self.le_bytes
+                .assign_le_bytes(region, offset, &witnesses[1].to_le_bytes())?;
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_word32_assign_bytes() {
+        let asymmetric = Word::from_big_endian(&(1..=32).collect::<Vec<u8>>());
+        for x in [
+            Word::zero(),
+            Word::one(),
+            WORD_LOW_MAX,
+            WORD_HIGH_MAX,
+            Word::MAX,
+            asymmetric,
+        ] {
+            try_test!(WordBytesTestContainer<Fr>, [x, x], true);
+        }
+
+        // The bytes in the opposite order are another number
+        let reversed = Word::from_little_endian(&(1..=32).collect::<Vec<u8>>());
+        try_test!(WordBytesTestContainer<Fr>, [asymmetric, reversed], false);
+    }
+
+    #[test]
+    fn test_word_select_checked() {
+        let when_true = WordLoHi::<Fr>::from(Word::MAX).into_value();
+        let when_false = WordLoHi::<Fr>::from(0x1234u64).into_value();
+        for (selector, expected) in [(Fr::from(1), Word::MAX), (Fr::from(0), Word::from(0x1234))] {
+            let word = WordLoHi::select_checked(Value::known(selector), when_true, when_false);
+            let expected = WordLoHi::<Fr>::from(expected);
+            word.lo().assert_if_known(|lo| *lo == expected.lo());
+            word.hi().assert_if_known(|hi| *hi == expected.hi());
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "is not boolean")]
+    fn test_word_select_checked_not_boolean() {
+        let word = WordLoHi::<Fr>::from(1u64).into_value();
+        WordLoHi::select_checked(Value::known(Fr::from(2)), word, word);
+    }
+}
@@ -1,4 +1,4 @@
-use super::{ExecStep, Rw, RwMap, Transaction};
+use super::{Call, ExecStep, Rw, RwMap, Transaction};
 use crate::{
     evm_circuit::{detect_fixed_table_tags, EvmCircuit},
     exp_circuit::param::OFFSET_INCREMENT,
@@ -73,6 +73,17 @@ impl<F: Field> Block<F> {
         }
     }
 
+    /// Iterate over the steps of all the transactions, along with the transaction and the call
+    /// they belong to, in the order the EVM circuit assigns them. The EndBlock steps that pad the
+    /// circuit are not included.
+    pub fn iter_steps(&self) -> impl Iterator<Item = (&Transaction, &Call, &ExecStep)> {
+        self.txs.iter().flat_map(|tx| {
+            tx.steps()
+                .iter()
+                .map(move |step| (tx, &tx.calls()[step.call_index], step))
+        })
+    }
+
     /// Get a read-write record
     pub(crate) fn get_rws(&self, step: &ExecStep, index: usize) -> Rw {
         self.rws[step.rw_index(index)]
@@ -21,13 +21,7 @@ pub struct MptUpdate {
 impl MptUpdate {
     fn proof_type<F: Field>(&self) -> F {
         let proof_type = match self.key {
-            Key::AccountStorage { .. } => {
-                if self.old_value.is_zero() && self.new_value.is_zero() {
-                    MPTProofType::StorageDoesNotExist
-                } else {
-                    MPTProofType::StorageChanged
-                }
-            }
+            Key::AccountStorage { .. } => MPTProofType::storage(self.old_value, self.new_value),
             Key::Account { field_tag, .. } => field_tag.into(),
         };
         F::from(proof_type as u64)